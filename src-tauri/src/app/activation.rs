@@ -0,0 +1,177 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::mod_store;
+
+/// How the mods of a profile are made available to SMAPI
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ActivationStrategy {
+    /// Links every mod of the profile into a mods folder of the profile, that SMAPI loads the mods from.
+    /// The installed mod folders are never touched.
+    Link,
+    /// Renames the folders of unused mods to start with a dot, so SMAPI skips them
+    Rename,
+}
+
+impl Default for ActivationStrategy {
+    fn default() -> Self {
+        ActivationStrategy::Link
+    }
+}
+
+/// Builds the mods folder of a profile out of links to the installed mod folders.
+/// The new folder is built next to the old one and only swapped in once it is complete,
+/// so a crash leaves the previous mods folder of the profile intact.
+///
+/// * `store` - The folder the mods are installed in
+/// * `target` - The mods folder of the profile
+/// * `mods` - The folder names of the mods of the profile together with the version to use
+///
+/// # Returns the mods that weren't found in the store or an error if a link couldn't be created
+pub fn link_profile(
+    store: &Path,
+    target: &Path,
+    mods: &Vec<(String, String)>,
+) -> Result<Vec<String>, String> {
+    let staging = sibling(target, "new");
+    let previous = sibling(target, "old");
+    check_links(target)?;
+    remove_links(&staging)?;
+    remove_links(&previous)?;
+    fs::create_dir_all(&staging).map_err(|e| e.to_string())?;
+
+    let mut missing: Vec<String> = Vec::new();
+    for (folder, version) in mods {
+        match mod_store::find_version(store, folder, version) {
+            Some(source) => create_link(&source, &staging.join(folder))
+                .map_err(|e| format!("Couldn't link {} ({})", folder, e))?,
+            None => missing.push(format!("{} {}", folder, version)),
+        }
+    }
+
+    if target.exists() {
+        fs::rename(target, &previous).map_err(|e| e.to_string())?;
+    }
+    fs::rename(&staging, target).map_err(|e| e.to_string())?;
+    remove_links(&previous)?;
+
+    Ok(missing)
+}
+
+/// Gets a hidden folder next to the mods folder of a profile
+fn sibling(target: &Path, suffix: &str) -> PathBuf {
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    target.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+/// Checks that a folder only contains links. Real files or folders are never deleted,
+/// so mods that were copied into the mods folder of a profile by hand stay safe.
+///
+/// * `dir` - The folder to check
+fn check_links(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut links: Vec<PathBuf> = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        let is_link = fs::symlink_metadata(&path)
+            .map(|metadata| metadata.file_type().is_symlink())
+            .unwrap_or(false);
+        if !is_link {
+            return Err(format!("{} is not a link and won't be removed", path.display()));
+        }
+        links.push(path);
+    }
+    Ok(links)
+}
+
+/// Removes a folder that only contains links, without touching the folders they point to
+///
+/// * `dir` - The folder to remove
+fn remove_links(dir: &Path) -> Result<(), String> {
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    for link in check_links(dir)? {
+        remove_link(&link).map_err(|e| e.to_string())?;
+    }
+    fs::remove_dir(dir).map_err(|e| e.to_string())
+}
+
+#[cfg(unix)]
+fn create_link(source: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, link)
+}
+
+/// Symlinks need developer mode or admin rights on Windows, junctions work without them
+#[cfg(windows)]
+fn create_link(source: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_dir(source, link).or_else(|_| junction::create(source, link))
+}
+
+#[cfg(unix)]
+fn remove_link(link: &Path) -> io::Result<()> {
+    fs::remove_file(link)
+}
+
+#[cfg(windows)]
+fn remove_link(link: &Path) -> io::Result<()> {
+    fs::remove_dir(link)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_link_profile() {
+        let tmp_dir = tempdir().unwrap();
+        let store = tmp_dir.path().join("mods");
+        let target = tmp_dir.path().join("profiles").join("Default");
+        fs::create_dir_all(store.join("Author.Active").join("1.0.0")).unwrap();
+        fs::create_dir_all(store.join(".Author.Inactive").join("2.0.0")).unwrap();
+        fs::write(store.join(".Author.Inactive").join("2.0.0").join("manifest.json"), "{}").unwrap();
+        fs::create_dir_all(store.join(".Author.Inactive").join(".1.0.0")).unwrap();
+
+        let mods = vec![
+            ("Author.Active".to_string(), "1.0.0".to_string()),
+            ("Author.Inactive".to_string(), "2.0.0".to_string()),
+            ("Author.Missing".to_string(), "1.0.0".to_string()),
+        ];
+        let missing = link_profile(&store, &target, &mods).unwrap();
+        assert_eq!(missing, vec!["Author.Missing 1.0.0"]);
+        assert!(target.join("Author.Inactive").join("manifest.json").exists());
+        assert!(fs::symlink_metadata(target.join("Author.Active")).unwrap().file_type().is_symlink());
+
+        // Switching the profile's mods replaces the links, but never touches the store
+        let mods = vec![
+            ("Author.Active".to_string(), "1.0.0".to_string()),
+            ("Author.Inactive".to_string(), "1.0.0".to_string()),
+        ];
+        link_profile(&store, &target, &mods).unwrap();
+        assert!(target.join("Author.Active").exists());
+        assert!(!target.join("Author.Inactive").join("manifest.json").exists());
+        assert!(store.join(".Author.Inactive").join("2.0.0").join("manifest.json").exists());
+        assert!(!sibling(&target, "old").exists());
+        assert!(!sibling(&target, "new").exists());
+    }
+
+    #[test]
+    fn test_real_folders_are_kept() {
+        let tmp_dir = tempdir().unwrap();
+        let store = tmp_dir.path().join("mods");
+        let target = tmp_dir.path().join("Default");
+        fs::create_dir_all(target.join("Copied Mod")).unwrap();
+
+        assert!(link_profile(&store, &target, &vec![]).is_err());
+        assert!(target.join("Copied Mod").exists());
+    }
+}
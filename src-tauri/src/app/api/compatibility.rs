@@ -96,28 +96,48 @@ struct SmapiMeta {
     broke_in: Option<String>,
 }
 
+/// The known download sources of a mod
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ModSource {
+    pub unique_id: String,
+    pub name: Option<String>,
+    pub nexus_id: Option<i32>,
+    pub github_repo: Option<String>,
+    pub custom_source_url: Option<String>,
+    pub version: Option<String>,
+    pub url: Option<String>,
+    pub installed: bool,
+    /// The highest version the mods of the profile require
+    pub minimum_version: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct SmapiMain {
     version: String,
     url: String,
 }
 
+/// Gets the version of the game that is sent to smapi.io, which needs it to tell which mods are broken
+///
+/// # Returns the version or an empty string if it couldn't be read
+fn game_version() -> String {
+    match version_extractor::game_version() {
+        Ok(version) => version,
+        Err(e) => {
+            println!("Error: {}", e);
+            "".to_string()
+        }
+    }
+}
+
 /// Get the compatibility of a list of mods
 ///
 /// * `mods` - The list of mods to check compatibility for
 ///
 /// # Returns the same list of mods with the updated compatibility info
 pub async fn get_compability(mods: Vec<ModInfo>) -> Option<Vec<ModInfo>> {
-    let url = Url::parse("https://smapi.io/api/v3.0/mods").unwrap();
-
     // Get game and api version
-    let mut game_version = "".to_string();
-    match version_extractor::get_version("Stardew Valley.exe") {
-        Some(version) => {
-            game_version = version;
-        }
-        None => {}
-    }
+    let game_version = game_version();
     let api_version: Option<String> = version_extractor::get_version("StardewModdingAPI.dll");
 
     // Collect mods to post by putting them into a vector of SmapiMod's
@@ -139,24 +159,90 @@ pub async fn get_compability(mods: Vec<ModInfo>) -> Option<Vec<ModInfo>> {
     let post_body_wrapper = SmapiPostWrapper::new(mods_post, api_version, game_version);
 
     // Send the post request
+    let res = post_mods(&post_body_wrapper).await?;
+
+    return if res.status().is_success() {
+        process_post_response(res, mods).await
+    } else {
+        println!("Error: {:?}", res.status());
+        None
+    };
+}
+
+/// Get the download sources of mods that aren't installed
+///
+/// * `unique_ids` - The unique ids of the mods
+///
+/// # Returns the known sources of every mod smapi.io knows
+pub async fn get_mod_sources(unique_ids: Vec<String>) -> Option<Vec<ModSource>> {
+    let game_version = game_version();
+    let api_version: Option<String> = version_extractor::get_version("StardewModdingAPI.dll");
+
+    // The mods aren't installed, so every available version is newer
+    let mods_post: Vec<SmapiMod> = unique_ids
+        .into_iter()
+        .map(|id| SmapiMod {
+            id,
+            installed_version: "0.0.0".to_string(),
+        })
+        .collect();
+    let post_body_wrapper = SmapiPostWrapper::new(mods_post, api_version, game_version);
+
+    let res = post_mods(&post_body_wrapper).await?;
+    if !res.status().is_success() {
+        println!("Error: {:?}", res.status());
+        return None;
+    }
+
+    let text = res.text().await.ok()?;
+    let wrapper: Vec<SmapiWrapper> = serde_json::from_str(text.as_str()).ok()?;
+
+    Some(
+        wrapper
+            .into_iter()
+            .map(|mod_info| {
+                let main = mod_info.metadata.main.clone();
+                ModSource {
+                    unique_id: mod_info.id,
+                    name: mod_info.metadata.name,
+                    nexus_id: mod_info.metadata.nexus_id,
+                    github_repo: mod_info.metadata.github_repo,
+                    custom_source_url: mod_info.metadata.custom_source_url,
+                    version: main.clone().map(|main| main.version),
+                    url: main.map(|main| main.url),
+                    installed: false,
+                    minimum_version: None,
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Sends a post request to the mods endpoint of smapi.io
+///
+/// * `post_body_wrapper` - The body of the request
+///
+/// # Returns the response or None if the request failed
+async fn post_mods(post_body_wrapper: &SmapiPostWrapper) -> Option<Response> {
+    let url = Url::parse("https://smapi.io/api/v3.0/mods").unwrap();
     let client = reqwest::Client::new();
     let res = client
         .post(url)
-        .body(serde_json::to_string(&post_body_wrapper).unwrap())
+        .body(serde_json::to_string(post_body_wrapper).unwrap())
         .header("Application-Name", "application/json")
         .header("User-Agent", "Junimo")
         .header("Content-Type", "application/json")
         .header("accept", "application/json")
         .send()
-        .await
-        .unwrap();
+        .await;
 
-    return if res.status().is_success() {
-        process_post_response(res, mods).await
-    } else {
-        println!("Error: {:?}", res.status());
-        None
-    };
+    match res {
+        Ok(res) => Some(res),
+        Err(e) => {
+            println!("Error: {}", e);
+            None
+        }
+    }
 }
 
 /// Process the response of the post request
@@ -189,6 +275,18 @@ async fn process_post_response(res: Response, mods: Vec<ModInfo>) -> Option<Vec<
                             let get_index = hash_map.get(&Some(mod_info.id.clone())).unwrap();
                             let mut copied_mod = mods[*get_index].clone();
 
+                            // Remember the suggested update for the update workflow
+                            match &mod_info.suggested_update {
+                                Some(update) => {
+                                    copied_mod.update_version = Some(update.version.clone());
+                                    copied_mod.update_url = Some(update.url.clone());
+                                }
+                                None => {
+                                    copied_mod.update_version = None;
+                                    copied_mod.update_url = None;
+                                }
+                            }
+
                             match mod_info.metadata.compability_status {
                                 Some(status) => {
                                     if status != "Ok" {
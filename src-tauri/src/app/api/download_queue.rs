@@ -0,0 +1,517 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use url::Url;
+
+use crate::app::utility::persistence;
+
+/// The state of a download in the queue
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum DownloadStatus {
+    Queued,
+    Downloading,
+    Paused,
+    /// The archive contains more than one mod and waits for the user to select the mods
+    AwaitingSelection,
+    Cancelled,
+    Failed,
+    Finished,
+}
+
+/// Where the file of a download comes from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum DownloadSource {
+    /// A nxm link of the "Mod Manager Download" button
+    Nxm { link: String },
+    /// The file of a Nexus mod that fits the version best
+    Nexus {
+        domain: String,
+        mod_id: u32,
+        version: String,
+    },
+    /// The newest main file of a Nexus mod, which has to be at least the minimum version
+    NexusLatest {
+        domain: String,
+        mod_id: u32,
+        minimum_version: Option<String>,
+    },
+    /// A direct download url
+    Url { url: String },
+}
+
+impl DownloadSource {
+    /// Checks if the source is a nxm link that can't be used anymore. The links of the
+    /// "Mod Manager Download" button only work until their expiry date.
+    ///
+    /// * `now` - The current time in seconds since the unix epoch
+    pub fn link_expired(&self, now: u64) -> bool {
+        let link = match self {
+            DownloadSource::Nxm { link } => link,
+            _ => return false,
+        };
+        Url::parse(link)
+            .ok()
+            .and_then(|url| {
+                url.query_pairs()
+                    .find(|(key, _)| key == "expires")
+                    .and_then(|(_, expires)| expires.parse::<u64>().ok())
+            })
+            .map_or(false, |expires| expires <= now)
+    }
+}
+
+/// A download in the queue
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Download {
+    pub id: u64,
+    pub name: String,
+    pub source: DownloadSource,
+    pub size: Option<u64>,
+    pub current: u64,
+    pub status: DownloadStatus,
+    pub error: Option<String>,
+    /// The unique id of the mod that is installed out of the archive, None to let the user choose
+    #[serde(default)]
+    pub unique_id: Option<String>,
+    /// The install plan that waits for the selection of the user
+    #[serde(default)]
+    pub plan_id: Option<u64>,
+}
+
+impl Download {
+    pub fn new(id: u64, name: String, source: DownloadSource) -> Self {
+        Download {
+            id,
+            name,
+            source,
+            size: None,
+            current: 0,
+            status: DownloadStatus::Queued,
+            error: None,
+            unique_id: None,
+            plan_id: None,
+        }
+    }
+
+    /// Checks if the download won't change anymore
+    pub fn is_done(&self) -> bool {
+        matches!(
+            self.status,
+            DownloadStatus::Cancelled | DownloadStatus::Failed | DownloadStatus::Finished
+        )
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    downloads: Vec<Download>,
+    next_id: u64,
+    waiters: HashMap<u64, oneshot::Sender<Result<(), String>>>,
+}
+
+/// The queue of all downloads. It is shared through the app state, so every clone
+/// works on the same downloads.
+#[derive(Clone, Default)]
+pub struct DownloadQueue {
+    state: Arc<Mutex<QueueState>>,
+}
+
+impl DownloadQueue {
+    /// Adds a download to the end of the queue
+    ///
+    /// * `name` - The name shown to the user
+    /// * `source` - Where the file comes from
+    ///
+    /// # Returns the queued download
+    pub fn add(&self, name: String, source: DownloadSource) -> Download {
+        let mut state = self.state.lock().unwrap();
+        let download = Download::new(state.next_id, name, source);
+        state.next_id += 1;
+        state.downloads.push(download.clone());
+        download
+    }
+
+    /// Adds a download and gets notified once it was installed, failed or got cancelled
+    ///
+    /// * `name` - The name shown to the user
+    /// * `source` - Where the file comes from
+    /// * `unique_id` - The mod that is installed out of the archive, so nobody has to select it
+    ///
+    /// # Returns the queued download and a receiver for its result
+    pub fn add_and_wait(
+        &self,
+        name: String,
+        source: DownloadSource,
+        unique_id: Option<String>,
+    ) -> (Download, oneshot::Receiver<Result<(), String>>) {
+        let mut state = self.state.lock().unwrap();
+        let mut download = Download::new(state.next_id, name, source);
+        download.unique_id = unique_id;
+        state.next_id += 1;
+        state.downloads.push(download.clone());
+
+        let (sender, receiver) = oneshot::channel();
+        state.waiters.insert(download.id, sender);
+        (download, receiver)
+    }
+
+    /// Gets a download by its id
+    pub fn get(&self, id: u64) -> Option<Download> {
+        let state = self.state.lock().unwrap();
+        state.downloads.iter().find(|d| d.id == id).cloned()
+    }
+
+    /// Gets the download that waits for the selection of an install plan
+    pub fn by_plan(&self, plan_id: u64) -> Option<Download> {
+        let state = self.state.lock().unwrap();
+        state
+            .downloads
+            .iter()
+            .find(|d| d.plan_id == Some(plan_id))
+            .cloned()
+    }
+
+    /// Gets all downloads in the order they were added
+    pub fn all(&self) -> Vec<Download> {
+        self.state.lock().unwrap().downloads.clone()
+    }
+
+    /// Changes a download
+    ///
+    /// * `id` - The id of the download
+    /// * `change` - The change to apply
+    ///
+    /// # Returns the changed download or None if there's no download with the id
+    pub fn update<F: FnOnce(&mut Download)>(&self, id: u64, change: F) -> Option<Download> {
+        let mut state = self.state.lock().unwrap();
+        let download = state.downloads.iter_mut().find(|d| d.id == id)?;
+        change(download);
+        Some(download.clone())
+    }
+
+    /// Cancels a download that isn't done yet
+    ///
+    /// # Returns the cancelled download
+    pub fn cancel(&self, id: u64) -> Option<Download> {
+        let download = self.transition(id, |status| *status != DownloadStatus::Finished, DownloadStatus::Cancelled)?;
+        self.notify(id, Err("Download cancelled".to_string()));
+        Some(download)
+    }
+
+    /// Pauses a queued or running download. Whoever waits for the download stops waiting,
+    /// as it is unknown when the download is resumed.
+    ///
+    /// # Returns the paused download
+    pub fn pause(&self, id: u64) -> Option<Download> {
+        let download = self.transition(
+            id,
+            |status| matches!(status, DownloadStatus::Queued | DownloadStatus::Downloading),
+            DownloadStatus::Paused,
+        )?;
+        self.notify(id, Err("Download paused".to_string()));
+        Some(download)
+    }
+
+    /// Puts a paused or failed download back into the queue
+    ///
+    /// # Returns the queued download
+    pub fn resume(&self, id: u64) -> Option<Download> {
+        self.transition(
+            id,
+            |status| matches!(status, DownloadStatus::Paused | DownloadStatus::Failed),
+            DownloadStatus::Queued,
+        )
+    }
+
+    /// Removes every download that won't change anymore
+    pub fn clear_done(&self) {
+        self.state.lock().unwrap().downloads.retain(|d| !d.is_done());
+    }
+
+    /// Takes the next queued downloads, so that at most `max` downloads are running
+    ///
+    /// * `max` - The maximum number of concurrent downloads
+    ///
+    /// # Returns the downloads that should be started now
+    pub fn start_next(&self, max: usize) -> Vec<Download> {
+        let mut state = self.state.lock().unwrap();
+        let running = state
+            .downloads
+            .iter()
+            .filter(|d| d.status == DownloadStatus::Downloading)
+            .count();
+
+        state
+            .downloads
+            .iter_mut()
+            .filter(|d| d.status == DownloadStatus::Queued)
+            .take(max.saturating_sub(running))
+            .map(|download| {
+                download.status = DownloadStatus::Downloading;
+                download.error = None;
+                download.clone()
+            })
+            .collect()
+    }
+
+    /// Finishes a running download. Downloads that were paused or cancelled in the meantime keep their state.
+    ///
+    /// * `id` - The id of the download
+    /// * `result` - The result of the download and installation
+    ///
+    /// # Returns the finished download
+    pub fn complete(&self, id: u64, result: Result<(), String>) -> Option<Download> {
+        let download = self.update(id, |download| {
+            if download.status != DownloadStatus::Downloading {
+                return;
+            }
+            match &result {
+                Ok(_) => download.status = DownloadStatus::Finished,
+                Err(e) => {
+                    download.status = DownloadStatus::Failed;
+                    download.error = Some(e.clone());
+                }
+            }
+        })?;
+
+        if download.status == DownloadStatus::Finished || download.status == DownloadStatus::Failed {
+            self.notify(id, result);
+        }
+        Some(download)
+    }
+
+    /// Lets a running download wait for the user to select the mods of its archive
+    ///
+    /// * `id` - The id of the download
+    /// * `plan_id` - The id of the install plan
+    ///
+    /// # Returns the waiting download or None if it was paused or cancelled in the meantime
+    pub fn await_selection(&self, id: u64, plan_id: u64) -> Option<Download> {
+        let mut state = self.state.lock().unwrap();
+        let download = state.downloads.iter_mut().find(|d| d.id == id)?;
+        if download.status != DownloadStatus::Downloading {
+            return None;
+        }
+        download.status = DownloadStatus::AwaitingSelection;
+        download.plan_id = Some(plan_id);
+        Some(download.clone())
+    }
+
+    /// Finishes a download once the selected mods of its install plan were installed
+    ///
+    /// * `plan_id` - The id of the install plan
+    /// * `result` - The result of the installation
+    ///
+    /// # Returns the finished download
+    pub fn finish_selection(&self, plan_id: u64, result: Result<(), String>) -> Option<Download> {
+        let download = {
+            let mut state = self.state.lock().unwrap();
+            let download = state
+                .downloads
+                .iter_mut()
+                .find(|d| d.plan_id == Some(plan_id) && d.status == DownloadStatus::AwaitingSelection)?;
+            match &result {
+                Ok(_) => download.status = DownloadStatus::Finished,
+                Err(e) => {
+                    download.status = DownloadStatus::Failed;
+                    download.error = Some(e.clone());
+                }
+            }
+            download.plan_id = None;
+            download.clone()
+        };
+        self.notify(download.id, result);
+        Some(download)
+    }
+
+    /// Saves every download that can still be continued
+    ///
+    /// * `path` - The path to the queue file
+    pub fn save(&self, path: &Path) {
+        let pending: Vec<Download> = self
+            .all()
+            .into_iter()
+            .filter(|download| {
+                download.status != DownloadStatus::Cancelled
+                    && download.status != DownloadStatus::Finished
+            })
+            .collect();
+
+        if let Err(e) = persistence::write_json(path, &pending) {
+            println!("Failed to save the download queue: {}", e);
+        }
+    }
+
+    /// Loads the saved downloads into the queue. Downloads that were running when the app was
+    /// closed are queued again, downloads of nxm links that expired in the meantime fail.
+    ///
+    /// * `path` - The path to the queue file
+    ///
+    /// # Returns the loaded downloads
+    pub fn load(&self, path: &Path) -> Vec<Download> {
+        let mut loaded: Vec<Download> = match persistence::exists(path) {
+            true => persistence::read_json(path, |data| Ok(serde_json::from_str(data)?)).unwrap_or_default(),
+            false => Vec::new(),
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        let mut state = self.state.lock().unwrap();
+        for download in loaded.iter_mut() {
+            // Install plans don't survive a restart, so the archive is downloaded again
+            if download.status == DownloadStatus::Downloading
+                || download.status == DownloadStatus::AwaitingSelection
+            {
+                download.status = DownloadStatus::Queued;
+                download.plan_id = None;
+            }
+            if !download.is_done() && download.source.link_expired(now) {
+                download.status = DownloadStatus::Failed;
+                download.error = Some(
+                    "The download link expired, please start the download on Nexus again".to_string(),
+                );
+            }
+            if state.downloads.iter().any(|d| d.id == download.id) {
+                continue;
+            }
+            state.next_id = state.next_id.max(download.id + 1);
+            state.downloads.push(download.clone());
+        }
+
+        loaded
+    }
+
+    /// Changes the status of a download if its current status allows it
+    fn transition<F: Fn(&DownloadStatus) -> bool>(
+        &self,
+        id: u64,
+        allowed: F,
+        status: DownloadStatus,
+    ) -> Option<Download> {
+        let mut state = self.state.lock().unwrap();
+        let download = state.downloads.iter_mut().find(|d| d.id == id)?;
+        if !allowed(&download.status) {
+            return None;
+        }
+        download.status = status;
+        Some(download.clone())
+    }
+
+    /// Sends the result to whoever waits for the download
+    fn notify(&self, id: u64, result: Result<(), String>) {
+        if let Some(sender) = self.state.lock().unwrap().waiters.remove(&id) {
+            let _ = sender.send(result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn url(url: &str) -> DownloadSource {
+        DownloadSource::Url {
+            url: url.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_concurrency_and_controls() {
+        let queue = DownloadQueue::default();
+        let first = queue.add("First".to_string(), url("a"));
+        let second = queue.add("Second".to_string(), url("b"));
+        let third = queue.add("Third".to_string(), url("c"));
+
+        let started: Vec<u64> = queue.start_next(2).iter().map(|d| d.id).collect();
+        assert_eq!(started, vec![first.id, second.id]);
+        assert!(queue.start_next(2).is_empty());
+
+        // A cancelled download frees its slot without stopping any other download
+        queue.cancel(first.id).unwrap();
+        assert!(queue.complete(first.id, Err("aborted".to_string())).is_some());
+        assert_eq!(queue.get(first.id).unwrap().status, DownloadStatus::Cancelled);
+
+        queue.pause(third.id).unwrap();
+        assert!(queue.start_next(2).is_empty());
+        queue.resume(third.id).unwrap();
+        assert_eq!(queue.start_next(2)[0].id, third.id);
+
+        queue.complete(second.id, Ok(())).unwrap();
+        assert_eq!(queue.get(second.id).unwrap().status, DownloadStatus::Finished);
+        assert!(queue.cancel(second.id).is_none());
+    }
+
+    #[test]
+    fn test_waiter_gets_result() {
+        let queue = DownloadQueue::default();
+        let (download, mut receiver) = queue.add_and_wait("First".to_string(), url("a"), None);
+
+        queue.start_next(1);
+        queue.complete(download.id, Err("broken".to_string()));
+        assert_eq!(receiver.try_recv().unwrap(), Err("broken".to_string()));
+
+        // Nobody knows when a paused download continues, so the waiter gets its answer right away
+        let (download, mut receiver) = queue.add_and_wait("Second".to_string(), url("b"), None);
+        queue.start_next(1);
+        queue.pause(download.id).unwrap();
+        assert_eq!(receiver.try_recv().unwrap(), Err("Download paused".to_string()));
+    }
+
+    #[test]
+    fn test_awaiting_selection() {
+        let queue = DownloadQueue::default();
+        let (download, mut receiver) = queue.add_and_wait("First".to_string(), url("a"), None);
+        let other = queue.add("Second".to_string(), url("b"));
+
+        queue.start_next(1);
+        let waiting = queue.await_selection(download.id, 7).unwrap();
+        assert_eq!(waiting.status, DownloadStatus::AwaitingSelection);
+        assert!(!waiting.is_done());
+        assert_eq!(queue.by_plan(7).unwrap().id, download.id);
+
+        // A waiting download doesn't block the queue
+        assert_eq!(queue.start_next(1)[0].id, other.id);
+        assert!(receiver.try_recv().is_err());
+
+        let finished = queue.finish_selection(7, Ok(())).unwrap();
+        assert_eq!(finished.status, DownloadStatus::Finished);
+        assert_eq!(receiver.try_recv().unwrap(), Ok(()));
+        assert!(queue.finish_selection(7, Ok(())).is_none());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("downloads.json");
+
+        let queue = DownloadQueue::default();
+        let first = queue.add("First".to_string(), url("a"));
+        let second = queue.add("Second".to_string(), url("b"));
+        queue.add("Third".to_string(), url("c"));
+        let nxm = |expires: u64| DownloadSource::Nxm {
+            link: format!("nxm://stardewvalley/mods/1/files/2?key=abc&expires={}&user_id=3", expires),
+        };
+        let expired = queue.add("Expired".to_string(), nxm(1));
+        let valid = queue.add("Valid".to_string(), nxm(u64::MAX));
+        queue.start_next(2);
+        queue.complete(second.id, Ok(()));
+        queue.save(&path);
+
+        let restored = DownloadQueue::default();
+        let loaded = restored.load(&path);
+        assert_eq!(loaded.len(), 4);
+        assert_eq!(restored.get(first.id).unwrap().status, DownloadStatus::Queued);
+        assert_eq!(restored.get(expired.id).unwrap().status, DownloadStatus::Failed);
+        assert_eq!(restored.get(valid.id).unwrap().status, DownloadStatus::Queued);
+        assert_eq!(restored.add("Sixth".to_string(), url("f")).id, 5);
+    }
+}
@@ -1,17 +1,20 @@
 use std::fs;
-use std::fs::File;
-use std::io::Write;
+use std::path::PathBuf;
 
-use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use tauri::{command, AppHandle, Manager, State};
 
-use crate::app::api::{mods_api, nexuswebsocket};
+use crate::app::api::compatibility::ModSource;
+use crate::app::api::download_queue::{Download, DownloadSource, DownloadStatus};
+use crate::app::api::{github, mods_api, nexuswebsocket, verification};
 use crate::app::app_state::AppState;
+use crate::app::error::JunimoError;
+use crate::app::mod_installation::{self, InstallOutcome};
 use crate::app::models::mod_info::ModInfo;
-use crate::app::mods::get_all_mods;
+use crate::app::models::nexus_file::NexusFile;
+use crate::app::utility::http_download::{self, TransferError};
 use crate::app::utility::paths;
-use crate::app::{console, mod_installation, mods};
+use crate::app::{config, console};
 
 #[derive(Serialize, Deserialize, Debug)]
 struct DownloadPaths {
@@ -20,37 +23,85 @@ struct DownloadPaths {
     URI: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct Download {
-    pub name: String,
-    pub size: u64,
-    pub current: u64,
-    pub aborted: bool,
-    pub finished: bool,
+/// Tauri command to get all downloads of the queue
+///
+/// * `app_state` - The state of the app
+#[command]
+pub fn get_downloads(app_state: State<'_, AppState>) -> Result<Vec<Download>, JunimoError> {
+    Ok(app_state.downloads.all())
 }
 
-impl Download {
-    pub fn new(name: String, size: u64) -> Self {
-        Download {
-            name,
-            size,
-            current: 0,
-            aborted: false,
-            finished: false,
+/// Tauri command to cancel a download
+///
+/// * `app_handle` - Tauri Handle
+/// * `app_state` - The state of the app
+/// * `id` - The id of the download
+#[command]
+pub fn stop_download(app_handle: AppHandle, app_state: State<'_, AppState>, id: u64) -> Result<(), JunimoError> {
+    if let Some(download) = app_state.downloads.cancel(id) {
+        http_download::discard_partial(&partial_path(id));
+        if let Some(plan_id) = download.plan_id {
+            mod_installation::cancel_installation(&app_handle, plan_id);
         }
+        queue_changed(&app_handle, &app_state, &download);
+    }
+    Ok(())
+}
+
+/// Tauri command to pause a download. Paused downloads keep their place in the queue.
+///
+/// * `app_handle` - Tauri Handle
+/// * `app_state` - The state of the app
+/// * `id` - The id of the download
+#[command]
+pub fn pause_download(app_handle: AppHandle, app_state: State<'_, AppState>, id: u64) -> Result<(), JunimoError> {
+    if let Some(download) = app_state.downloads.pause(id) {
+        queue_changed(&app_handle, &app_state, &download);
+    }
+    Ok(())
+}
+
+/// Tauri command to continue a paused or failed download
+///
+/// * `app_handle` - Tauri Handle
+/// * `app_state` - The state of the app
+/// * `id` - The id of the download
+#[command]
+pub fn resume_download(app_handle: AppHandle, app_state: State<'_, AppState>, id: u64) -> Result<(), JunimoError> {
+    if let Some(download) = app_state.downloads.resume(id) {
+        queue_changed(&app_handle, &app_state, &download);
+        process_queue(&app_handle, app_state.inner());
     }
+    Ok(())
 }
 
+/// Tauri command to remove all finished, failed and cancelled downloads from the list
+///
+/// * `app_state` - The state of the app
 #[command]
-pub fn stop_download(app_state: State<'_, AppState>) {
-    let mut signal = app_state.stop_download.lock().unwrap();
-    *signal = true;
+pub fn clear_downloads(app_state: State<'_, AppState>) -> Result<Vec<Download>, JunimoError> {
+    for download in app_state.downloads.all().iter().filter(|d| d.is_done()) {
+        http_download::discard_partial(&partial_path(download.id));
+    }
+    app_state.downloads.clear_done();
+    Ok(app_state.downloads.all())
+}
+
+/// Loads the downloads that were still pending when Junimo was closed and continues them
+///
+/// * `app_handle` - Tauri Handle
+/// * `app_state` - The state of the app
+pub fn restore_queue(app_handle: &AppHandle, app_state: &AppState) {
+    for download in app_state.downloads.load(&paths::download_queue_path()) {
+        app_handle.emit("download", &download).unwrap();
+    }
+    process_queue(app_handle, app_state);
 }
 
-/// Starts the download of a mod
+/// Queues the download of a nxm link
 ///
 /// * `app_handle` - Tauri Handle
-/// * `url_str` - The url of the mod
+/// * `url_str` - The nxm link of the mod
 /// * `app_state` - The state of the app
 pub async fn start_download(app_handle: &AppHandle, url_str: &str, app_state: AppState) {
     // Loads api key from our config file
@@ -61,111 +112,463 @@ pub async fn start_download(app_handle: &AppHandle, url_str: &str, app_state: Ap
         return;
     }
 
-    // Sends a request to the NexusMods API to get the download link
-    let client = reqwest::Client::new();
-    let res = client
-        .get(mods_api::get_download_link(url_str))
-        .header("accept", "application/json")
-        .header("apikey", key)
-        .send()
-        .await
-        .unwrap();
+    if let Err(e) = mods_api::get_download_link(url_str) {
+        console::error(app_handle, &e);
+        return;
+    }
+
+    // Gets infos about the mod to show its name in the queue
+    let name = match mods_api::get_infos(url_str).await {
+        Ok(infos) => infos.name,
+        Err(e) => {
+            console::error(app_handle, &e);
+            url_str.to_string()
+        }
+    };
+
+    let download = app_state.downloads.add(
+        name,
+        DownloadSource::Nxm {
+            link: url_str.to_string(),
+        },
+    );
+    queue_changed(app_handle, &app_state, &download);
+    process_queue(app_handle, &app_state);
+}
+
+/// Downloads and installs the suggested update of an installed mod
+///
+/// * `app_handle` - Tauri Handle
+/// * `mod_info` - The installed mod with a suggested update
+/// * `app_state` - The state of the app
+///
+/// # Returns a Result with the success state
+pub async fn download_update(
+    app_handle: &AppHandle,
+    mod_info: &ModInfo,
+    app_state: AppState,
+) -> Result<(), String> {
+    let key = nexuswebsocket::load_key();
+    if key.is_empty() {
+        return Err("No NexusMods API Key found. Please provide a key in settings.".to_string());
+    }
+
+    let update_version = mod_info
+        .update_version
+        .clone()
+        .ok_or("No update available".to_string())?;
 
-    if res.status().is_success() {
-        // Parses the response to get the download links
-        let body = res.text().await.unwrap();
-        let body_str = body.as_str();
-        let paths: Vec<DownloadPaths> = serde_json::from_str(body_str).unwrap();
+    // Resolve the Nexus id through the update keys, the suggested update url or the stored mod id
+    let nexus_id = mod_info
+        .update_keys
+        .as_ref()
+        .and_then(|keys| mods_api::nexus_id_from_update_keys(keys))
+        .or_else(|| {
+            mod_info
+                .update_url
+                .as_ref()
+                .and_then(|url| mods_api::nexus_id_from_url(url))
+        })
+        .or(if mod_info.mod_id != 0 {
+            Some(mod_info.mod_id)
+        } else {
+            None
+        })
+        .ok_or("Mod has no Nexus update key".to_string())?;
+
+    queue_and_wait(
+        app_handle,
+        &app_state,
+        mod_info.name.clone(),
+        DownloadSource::Nexus {
+            domain: mod_info.domain_name.clone(),
+            mod_id: nexus_id,
+            version: update_version,
+        },
+        mod_info.unique_id.clone(),
+    )
+    .await
+}
 
-        // Gets infos about the mod
-        let infos = mods_api::get_infos(url_str).await.unwrap();
+/// Downloads and installs a mod that another mod requires
+///
+/// * `app_handle` - Tauri Handle
+/// * `source` - The download source of the mod resolved by smapi.io
+/// * `app_state` - The state of the app
+///
+/// # Returns a Result with the success state
+pub async fn download_dependency(
+    app_handle: &AppHandle,
+    source: &ModSource,
+    app_state: AppState,
+) -> Result<(), String> {
+    let name = source.name.clone().unwrap_or(source.unique_id.clone());
 
-        let handle_clone = app_handle.clone();
-        download(&handle_clone, &paths[0].URI, infos, app_state).await;
+    let download_source = if let Some(nexus_id) = source.nexus_id {
+        if nexuswebsocket::load_key().is_empty() {
+            return Err("No NexusMods API Key found. Please provide a key in settings.".to_string());
+        }
+        DownloadSource::NexusLatest {
+            domain: "stardewvalley".to_string(),
+            mod_id: nexus_id as u32,
+            minimum_version: source.minimum_version.clone(),
+        }
+    } else if let Some(repo) = &source.github_repo {
+        let url = github::get_release_zip(repo)
+            .await
+            .ok_or(format!("No zip found in the latest release of {}", repo))?;
+        DownloadSource::Url { url }
     } else {
-        println!("Failed to get download link {}", res.status());
+        return Err("No automatic download source available".to_string());
+    };
+
+    queue_and_wait(
+        app_handle,
+        &app_state,
+        name,
+        download_source,
+        Some(source.unique_id.clone()),
+    )
+    .await
+}
+
+/// Adds a download to the queue and waits until it was installed
+///
+/// * `app_handle` - Tauri Handle
+/// * `app_state` - The state of the app
+/// * `name` - The name of the mod
+/// * `source` - Where the file comes from
+/// * `unique_id` - The mod that is installed out of the archive
+///
+/// # Returns a Result with the success state
+async fn queue_and_wait(
+    app_handle: &AppHandle,
+    app_state: &AppState,
+    name: String,
+    source: DownloadSource,
+    unique_id: Option<String>,
+) -> Result<(), String> {
+    let (download, receiver) = app_state.downloads.add_and_wait(name, source, unique_id);
+    queue_changed(app_handle, app_state, &download);
+    process_queue(app_handle, app_state);
+
+    receiver
+        .await
+        .unwrap_or(Err("Download was removed from the queue".to_string()))
+}
+
+/// Starts as many queued downloads as the config allows
+///
+/// * `app_handle` - Tauri Handle
+/// * `app_state` - The state of the app
+pub fn process_queue(app_handle: &AppHandle, app_state: &AppState) {
+    let config = config::load_config(paths::config_path());
+    let max_downloads = config.max_downloads.unwrap_or(2).max(1);
+
+    for download in app_state.downloads.start_next(max_downloads) {
+        queue_changed(app_handle, app_state, &download);
+
+        let app_handle = app_handle.clone();
+        let app_state = app_state.clone();
+        tauri::async_runtime::spawn(async move {
+            let result = match run_download(&app_handle, &app_state, &download).await {
+                Ok(InstallOutcome::AwaitingSelection(plan_id)) => {
+                    // The download only finishes once the user selected the mods of the archive
+                    let waiting = app_state.downloads.await_selection(download.id, plan_id);
+                    if let Some(waiting) = waiting {
+                        queue_changed(&app_handle, &app_state, &waiting);
+                    }
+                    process_queue(&app_handle, &app_state);
+                    return;
+                }
+                Ok(InstallOutcome::Installed) => Ok(()),
+                Err(e) => Err(e),
+            };
+            let error = result.clone().err();
+
+            if let Some(finished) = app_state.downloads.complete(download.id, result) {
+                report_finished(&app_handle, &app_state, &finished, error);
+            }
+            process_queue(&app_handle, &app_state);
+        });
     }
 }
 
-/// Downloads the mod
+/// Finishes the download whose install plan was applied or cancelled by the user
 ///
 /// * `app_handle` - Tauri Handle
-/// * `url_str` - Download url of the mod
-/// * `infos` - Infos about the mod
 /// * `app_state` - The state of the app
-async fn download(app_handle: &AppHandle, url_str: &str, infos: ModInfo, app_state: AppState) {
-    // Sends a request to the download url
-    let client = reqwest::Client::new();
-    let mut response = client.get(url_str).send().await.unwrap();
-    if !response.status().is_success() {
-        panic!("Received non-successful status code: {}", response.status());
-    }
-
-    // Gets the total size of the download
-    let total_size = response
-        .content_length()
-        .expect("Failed to get content length");
-
-    // Creates a download object and make it visible to the user by emitting it to the frontend
-    let mut download: Download = Download::new(infos.name.to_owned(), total_size);
-    app_handle.emit("download", &download).unwrap();
-
-    // Creates a zip file with the name of the mod in the temp folder
-    let zip_path = format!("{}.zip", infos.name);
-    let mut temp_path = paths::temp_path();
-    temp_path.push(&zip_path);
-    let mut file = File::create(&temp_path).unwrap();
-
-    let mut downloaded: u64 = 0;
-    let mut stream = response.bytes_stream();
-
-    // Downloads the mod chunk by chunk
-    while let Some(item) = stream.next().await {
-        // Check if the user wants to stop the download
-        let should_stop = app_state.stop_download.lock().unwrap();
-        if *should_stop {
-            break;
+/// * `plan_id` - The id of the install plan
+/// * `result` - The result of the installation
+pub fn selection_finished(
+    app_handle: &AppHandle,
+    app_state: &AppState,
+    plan_id: u64,
+    result: Result<(), String>,
+) {
+    let error = result.clone().err();
+    if let Some(finished) = app_state.downloads.finish_selection(plan_id, result) {
+        report_finished(app_handle, app_state, &finished, error);
+    }
+}
+
+/// Cancels the download whose install plan was discarded by the user
+///
+/// * `app_handle` - Tauri Handle
+/// * `app_state` - The state of the app
+/// * `plan_id` - The id of the install plan
+pub fn selection_cancelled(app_handle: &AppHandle, app_state: &AppState, plan_id: u64) {
+    let cancelled = app_state
+        .downloads
+        .by_plan(plan_id)
+        .and_then(|download| app_state.downloads.cancel(download.id));
+    if let Some(cancelled) = cancelled {
+        queue_changed(app_handle, app_state, &cancelled);
+    }
+}
+
+/// Tells the user about a download that won't change anymore
+///
+/// * `app_handle` - Tauri Handle
+/// * `app_state` - The state of the app
+/// * `finished` - The finished download
+/// * `error` - The error of the download or installation
+fn report_finished(
+    app_handle: &AppHandle,
+    app_state: &AppState,
+    finished: &Download,
+    error: Option<String>,
+) {
+    queue_changed(app_handle, app_state, finished);
+    match (&finished.status, error) {
+        (DownloadStatus::Finished, _) => {
+            app_handle.emit("downloadfinished", finished).unwrap();
         }
+        // Failed downloads are shown to the user, paused and cancelled ones only in the console
+        (DownloadStatus::Failed, Some(e)) => console::error(
+            app_handle,
+            &JunimoError::Other(format!("The download of {} failed: {}", &finished.name, e)),
+        ),
+        (_, Some(e)) => console::add_line(
+            app_handle,
+            format!(
+                "<span class=\"console-red\">[Junimo] Download of {} stopped: {}</span>",
+                &finished.name, e
+            ),
+        ),
+        _ => {}
+    }
+}
 
-        let chunk = item.expect("Failed to read chunk");
-        file.write_all(&chunk).unwrap();
-        let new = downloaded + (chunk.len() as u64);
-        download.current = new;
-        downloaded = new;
+/// Downloads and installs a single download of the queue
+///
+/// * `app_handle` - Tauri Handle
+/// * `app_state` - The state of the app
+/// * `download` - The download to run
+///
+/// # Returns whether the mods were installed or wait for the selection of the user
+async fn run_download(
+    app_handle: &AppHandle,
+    app_state: &AppState,
+    download: &Download,
+) -> Result<InstallOutcome, String> {
+    let (download_url, expected) = resolve_source(&download.source).await?;
+    let temp_path = download_file(app_handle, app_state, download.id, &download_url).await?;
 
-        // Emit the updated download object to the frontend
-        app_handle.emit("download", &download).unwrap();
+    // Never install an archive that doesn't match the file on Nexus
+    if let Some((domain, file)) = expected {
+        if let Err(e) = verification::verify_download(app_handle, &temp_path, &domain, &file).await {
+            return match verification::quarantine(&temp_path, &download.name) {
+                Ok(path) => Err(format!("{}. The archive was moved to {}", e, path.display())),
+                Err(_) => {
+                    let _ = fs::remove_file(&temp_path);
+                    Err(e)
+                }
+            };
+        }
     }
 
-    // Flushes and deletes the download object
-    file.flush().unwrap();
-    drop(file);
+    let unique_id = download.unique_id.clone();
+    let install_result =
+        mod_installation::start_installation(app_handle.clone(), &temp_path, unique_id)
+            .await
+            .map_err(|e| e.to_string());
+    match fs::remove_file(&temp_path) {
+        Ok(_) => (),
+        Err(_) => (),
+    }
+    install_result
+}
 
-    // Check if the download was finished or aborted
-    if &download.size == &download.current {
-        // Show the user that the download was finished
-        app_handle.emit("downloadfinished", &download).unwrap();
-        download.finished = true;
-        app_handle.emit("download", &download).unwrap();
-        let console_output = format!(
-            "<span class=\"console-green\">Downloaded</span>: {}",
-            infos.name
-        );
-        console::add_line(&app_handle, console_output);
+/// Gets the url the file of a download can be fetched from
+///
+/// * `source` - The source of the download
+///
+/// # Returns the download url and the Nexus file with its game domain, if the file is from Nexus
+async fn resolve_source(
+    source: &DownloadSource,
+) -> Result<(String, Option<(String, NexusFile)>), String> {
+    match source {
+        DownloadSource::Nxm { link } => {
+            let api_link = mods_api::get_download_link(link).map_err(|e| e.to_string())?;
+            let url = resolve_download_url(&api_link, &nexuswebsocket::load_key()).await?;
+            let expected = match mods_api::nxm_file_ids(link) {
+                Some((domain, mod_id, file_id)) => mods_api::get_mod_file(&domain, mod_id, file_id)
+                    .await
+                    .map(|file| (domain, file)),
+                None => None,
+            };
+            Ok((url, expected))
+        }
+        DownloadSource::Nexus {
+            domain,
+            mod_id,
+            version,
+        } => {
+            let files = mods_api::get_mod_files(domain, *mod_id)
+                .await
+                .ok_or("Failed to get the mod files from Nexus".to_string())?;
+            let file = mods_api::select_update_file(&files, version)
+                .ok_or(format!("Nexus has no main file of version {}", version))?;
 
-        // Start installing the mod through the downloaded zip file
-        let _ = mod_installation::start_installation(app_handle.clone(), &temp_path).await;
-        match fs::remove_file(&temp_path) {
-            Ok(_) => (),
-            Err(_) => (),
+            let link = mods_api::file_download_link(domain, *mod_id, file.file_id);
+            let url = resolve_download_url(&link, &nexuswebsocket::load_key()).await.map_err(|e| {
+                format!(
+                    "Nexus refused the download ({}). Direct downloads require a premium account, please use the \"Mod Manager Download\" button instead",
+                    e
+                )
+            })?;
+            Ok((url, Some((domain.clone(), file))))
         }
-    } else {
-        // Show the user that the download was aborted
-        let mut mod_list = get_all_mods();
-        mod_list.retain(|mod_info| mod_info.name != infos.name);
-        mods::save_mods(mod_list);
-        fs::remove_file(temp_path).unwrap();
-        download.aborted = true;
-        app_handle.emit("download", &download).unwrap();
+        DownloadSource::NexusLatest {
+            domain,
+            mod_id,
+            minimum_version,
+        } => {
+            let files = mods_api::get_mod_files(domain, *mod_id)
+                .await
+                .ok_or("Failed to get the mod files from Nexus".to_string())?;
+            let file = mods_api::select_latest_file(&files).ok_or("Nexus has no main file".to_string())?;
+            if let Some(minimum_version) = minimum_version {
+                if !mods_api::is_at_least(file.version.as_deref(), minimum_version) {
+                    return Err(format!(
+                        "The newest main file on Nexus is older than the required version {}",
+                        minimum_version
+                    ));
+                }
+            }
+
+            let link = mods_api::file_download_link(domain, *mod_id, file.file_id);
+            let url = resolve_download_url(&link, &nexuswebsocket::load_key()).await.map_err(|e| {
+                format!(
+                    "Nexus refused the download ({}). Direct downloads require a premium account, please use the \"Mod Manager Download\" button instead",
+                    e
+                )
+            })?;
+            Ok((url, Some((domain.clone(), file))))
+        }
+        DownloadSource::Url { url } => Ok((url.clone(), None)),
+    }
+}
+
+/// Requests the real download url of a Nexus download link
+///
+/// * `link` - The api link to the download_link.json of a file
+/// * `key` - The NexusMods api key
+///
+/// # Returns the download url or the status of the failed request
+async fn resolve_download_url(link: &str, key: &str) -> Result<String, String> {
+    let client = reqwest::Client::new();
+    let res = client
+        .get(link)
+        .header("accept", "application/json")
+        .header("apikey", key)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !res.status().is_success() {
+        return Err(res.status().to_string());
     }
+
+    // Parses the response to get the download links
+    let body = res.text().await.map_err(|e| e.to_string())?;
+    let paths: Vec<DownloadPaths> = serde_json::from_str(body.as_str()).map_err(|e| e.to_string())?;
+    match paths.into_iter().next() {
+        Some(path) => Ok(path.URI),
+        None => Err("No download link found".to_string()),
+    }
+}
+
+/// Downloads a file into the temp folder and shows the progress to the user.
+/// The download stops as soon as it was paused or cancelled. Paused and failed downloads keep
+/// their partial file, so they continue where they stopped.
+///
+/// * `app_handle` - Tauri Handle
+/// * `app_state` - The state of the app
+/// * `id` - The id of the download in the queue
+/// * `url_str` - Download url of the file
+///
+/// # Returns the path of the downloaded file
+async fn download_file(
+    app_handle: &AppHandle,
+    app_state: &AppState,
+    id: u64,
+    url_str: &str,
+) -> Result<PathBuf, String> {
+    let temp_path = partial_path(id);
+
+    let result = http_download::download_resumable(
+        url_str,
+        &temp_path,
+        |current, size| {
+            // Emit the updated download to the frontend
+            if let Some(download) = app_state.downloads.update(id, |d| {
+                d.current = current;
+                d.size = size;
+            }) {
+                app_handle.emit("download", &download).unwrap();
+            }
+        },
+        // Check if the user paused or cancelled the download
+        || match app_state.downloads.get(id) {
+            Some(download) => download.status != DownloadStatus::Downloading,
+            None => true,
+        },
+    )
+    .await;
+
+    match result {
+        Ok(_) => {
+            let name = app_state.downloads.get(id).map(|d| d.name).unwrap_or_default();
+            let console_output = format!("<span class=\"console-green\">Downloaded</span>: {}", name);
+            console::add_line(&app_handle, console_output);
+            Ok(temp_path)
+        }
+        Err(TransferError::Stopped) => {
+            if app_state.downloads.get(id).map(|d| d.status) != Some(DownloadStatus::Paused) {
+                http_download::discard_partial(&temp_path);
+            }
+            Err(TransferError::Stopped.to_string())
+        }
+        Err(TransferError::Failed(e)) => Err(e),
+    }
+}
+
+/// Gets the path a download is written to. Every download gets its own file, so mods with
+/// the same name can be downloaded at the same time and continued after a restart.
+///
+/// * `id` - The id of the download
+fn partial_path(id: u64) -> PathBuf {
+    paths::temp_path().join(format!("download-{}.zip", id))
+}
+
+/// Shows a changed download to the user and saves the queue
+///
+/// * `app_handle` - Tauri Handle
+/// * `app_state` - The state of the app
+/// * `download` - The changed download
+fn queue_changed(app_handle: &AppHandle, app_state: &AppState, download: &Download) {
+    app_state.downloads.save(&paths::download_queue_path());
+    app_handle.emit("download", download).unwrap();
 }
@@ -1,48 +1,258 @@
-use crate::app::console;
-use crate::app::utility::version_extractor;
-use tauri::{AppHandle, command};
+use std::cmp::Reverse;
 
+use serde::{Deserialize, Serialize};
+use tauri::{command, State};
+
+use crate::app::app_state::AppState;
+use crate::app::error::JunimoError;
+use crate::app::smapi_manager;
+use crate::app::utility::semantic_version::SemanticVersion;
+
+/// Which SMAPI releases are offered
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum SmapiChannel {
+    /// Only full releases
+    #[default]
+    Stable,
+    /// Full releases and betas
+    Beta,
+}
+
+/// A SMAPI release on GitHub
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SmapiRelease {
+    pub version: String,
+    pub prerelease: bool,
+    pub download_url: String,
+}
+
+/// Gets the SMAPI releases of a channel, the newest first
+///
+/// * `channel` - Whether betas are included
+///
+/// # Returns the releases or an error if GitHub couldn't be reached
 #[command]
-pub async fn check_smapi_version() -> Vec<String> {
+pub async fn get_smapi_releases(channel: SmapiChannel) -> Result<Vec<SmapiRelease>, JunimoError> {
+    let releases = fetch_smapi_releases().await?;
+    Ok(releases
+        .into_iter()
+        .filter(|release| channel == SmapiChannel::Beta || !release.prerelease)
+        .collect())
+}
+
+/// Gets the SMAPI release to install for the current profile. That is the version the profile is pinned to
+/// or the newest release of the channel.
+///
+/// * `app_state` - The app state
+/// * `channel` - Whether betas are offered
+///
+/// # Returns the download url, the version and the installed version, if SMAPI is installed
+#[command]
+pub async fn check_smapi_version(
+    app_state: State<'_, AppState>,
+    channel: SmapiChannel,
+) -> Result<Vec<String>, JunimoError> {
+    let releases = fetch_smapi_releases().await?;
+    let pinned = app_state.store.current_profile().smapi_version;
+
+    let release = match &pinned {
+        Some(pinned) => find_release(&releases, pinned)
+            .ok_or_else(|| JunimoError::NotFound(format!("The pinned SMAPI version {}", pinned)))?,
+        None => latest_release(&releases, channel).ok_or_else(|| {
+            JunimoError::Network("GitHub didn't return a SMAPI release".to_string())
+        })?,
+    };
+
+    let mut result = vec![release.download_url.clone(), release.version.clone()];
+    if let Some(installed) = smapi_manager::installed_version() {
+        result.push(installed);
+    }
+    Ok(result)
+}
+
+/// The number of releases GitHub returns per page at most
+const RELEASES_PER_PAGE: usize = 100;
+
+/// Loads all SMAPI releases from GitHub, page by page
+///
+/// # Returns the releases with an installer, the newest first
+async fn fetch_smapi_releases() -> Result<Vec<SmapiRelease>, JunimoError> {
+    let client = reqwest::Client::new();
+    let mut releases = Vec::new();
+    let mut page = 1;
+    loop {
+        let res = client
+            .get(format!(
+                "https://api.github.com/repos/Pathoschild/SMAPI/releases?per_page={}&page={}",
+                RELEASES_PER_PAGE, page
+            ))
+            .header("accept", "application/json")
+            .header("User-Agent", "Junimo Client")
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            return Err(JunimoError::Network(format!(
+                "GitHub answered the SMAPI release request with {}",
+                res.status()
+            )));
+        }
+
+        let body = res.text().await?;
+        let json: serde_json::Value = serde_json::from_str(&body)?;
+        let count = json.as_array().map_or(0, |page| page.len());
+        if let serde_json::Value::Array(page) = json {
+            releases.extend(page);
+        }
+        if count < RELEASES_PER_PAGE {
+            break;
+        }
+        page += 1;
+    }
+    Ok(parse_smapi_releases(&serde_json::Value::Array(releases)))
+}
+
+/// Parses the releases of the GitHub API. Releases without an installer are skipped.
+///
+/// * `json` - The answer of the GitHub API
+///
+/// # Returns the releases, the newest first
+pub fn parse_smapi_releases(json: &serde_json::Value) -> Vec<SmapiRelease> {
+    let mut releases: Vec<SmapiRelease> = json
+        .as_array()
+        .map(|releases| releases.iter().filter_map(parse_smapi_release).collect())
+        .unwrap_or_default();
+
+    // Tags that aren't a version come after all versions, ties are ordered by the tag
+    releases.sort_by_cached_key(|release| {
+        Reverse((release.version.parse::<SemanticVersion>().ok(), release.version.clone()))
+    });
+    releases
+}
+
+/// Parses a single release of the GitHub API
+///
+/// * `release` - The release
+///
+/// # Returns the release or None if it is a draft or has no installer
+fn parse_smapi_release(release: &serde_json::Value) -> Option<SmapiRelease> {
+    if release["draft"].as_bool().unwrap_or(false) {
+        return None;
+    }
+
+    // Every release has an installer for players and one for developers, which ends with "-for-developers.zip"
+    let download_url = release["assets"]
+        .as_array()?
+        .iter()
+        .find(|asset| {
+            asset["name"]
+                .as_str()
+                .map_or(false, |name| name.starts_with("SMAPI-") && name.ends_with("-installer.zip"))
+        })?["browser_download_url"]
+        .as_str()?
+        .to_string();
+
+    Some(SmapiRelease {
+        version: release["tag_name"].as_str()?.to_string(),
+        prerelease: release["prerelease"].as_bool().unwrap_or(false),
+        download_url,
+    })
+}
+
+/// Gets the newest release of a channel
+///
+/// * `releases` - The releases, the newest first
+/// * `channel` - Whether betas are included
+pub fn latest_release(releases: &Vec<SmapiRelease>, channel: SmapiChannel) -> Option<&SmapiRelease> {
+    releases
+        .iter()
+        .find(|release| channel == SmapiChannel::Beta || !release.prerelease)
+}
+
+/// Gets the release of a version
+///
+/// * `releases` - The releases
+/// * `version` - The version, with or without a leading "v"
+pub fn find_release<'a>(releases: &'a Vec<SmapiRelease>, version: &str) -> Option<&'a SmapiRelease> {
+    let wanted = version.parse::<SemanticVersion>().ok();
+    releases.iter().find(|release| match (&wanted, release.version.parse::<SemanticVersion>()) {
+        (Some(wanted), Ok(release_version)) => *wanted == release_version,
+        _ => release.version == version,
+    })
+}
+
+/// Gets the download url of the first zip in the latest release of a GitHub repository
+///
+/// * `repo` - The repository (e.g. Pathoschild/SMAPI)
+///
+/// # Returns the download url or None if the release has no zip
+pub async fn get_release_zip(repo: &str) -> Option<String> {
     let client = reqwest::Client::new();
     let res = client
-        .get("https://api.github.com/repos/Pathoschild/SMAPI/releases/latest")
+        .get(format!("https://api.github.com/repos/{}/releases/latest", repo))
         .header("accept", "application/json")
         .header("User-Agent", "Junimo Client")
         .send()
-        .await;
-
-    #[cfg(target_family = "windows")]
-    let smapi_version = version_extractor::get_version("StardewModdingAPI.exe");
-    #[cfg(target_family = "unix")]
-    let smapi_version = version_extractor::get_version("StardewModdingAPI.dll");
-
-    return match res {
-        Ok(res) => {
-            let body = res.text().await.unwrap();
-            let json: serde_json::Value = serde_json::from_str(&body).unwrap();
-            let version = json["tag_name"].as_str().unwrap();
-            let download_url = json["assets"][0]["browser_download_url"].as_str().unwrap();
-            let changelog = json["body"].as_str().unwrap();
-            let version = version.to_string();
-            let download_url = download_url.to_string();
-
-            if smapi_version.is_none() {
-                println!("None");
-                let download_url = download_url.replace("-for-developers", "");
-                vec![download_url, version]
-            }
-            else if version == smapi_version.clone().unwrap() {
-                let download_url = download_url.replace("-for-developers", "");
-                vec![download_url, version, smapi_version.unwrap().to_string()]
-            }
-            else {
-                vec![]
-            }
-        }
-        Err(e) => {
-            println!("{}", e);
-            vec![]
-        }
+        .await
+        .ok()?;
+
+    if !res.status().is_success() {
+        return None;
+    }
+
+    let body = res.text().await.ok()?;
+    let json: serde_json::Value = serde_json::from_str(&body).ok()?;
+    json["assets"]
+        .as_array()?
+        .iter()
+        .filter_map(|asset| asset["browser_download_url"].as_str())
+        .find(|url| url.ends_with(".zip"))
+        .map(|url| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn release(tag: &str, prerelease: bool) -> serde_json::Value {
+        json!({
+            "tag_name": tag,
+            "prerelease": prerelease,
+            "draft": false,
+            "assets": [
+                {
+                    "name": format!("SMAPI-{}-installer-for-developers.zip", tag),
+                    "browser_download_url": format!("https://github.com/{}/developers.zip", tag)
+                },
+                {
+                    "name": format!("SMAPI-{}-installer.zip", tag),
+                    "browser_download_url": format!("https://github.com/{}/installer.zip", tag)
+                }
+            ]
+        })
+    }
+
+    #[test]
+    fn test_parse_smapi_releases() {
+        let json = json!([
+            release("3.18.6", false),
+            release("4.1.0-beta.1", true),
+            release("4.0.8", false),
+            release("nightly", true),
+            release("alpha", true),
+            {"tag_name": "4.0.9", "draft": false, "assets": []}
+        ]);
+
+        let releases = parse_smapi_releases(&json);
+        let versions: Vec<&str> = releases.iter().map(|release| release.version.as_str()).collect();
+        assert_eq!(versions, vec!["4.1.0-beta.1", "4.0.8", "3.18.6", "nightly", "alpha"]);
+        assert_eq!(releases[1].download_url, "https://github.com/4.0.8/installer.zip");
+
+        assert_eq!(latest_release(&releases, SmapiChannel::Stable).unwrap().version, "4.0.8");
+        assert_eq!(latest_release(&releases, SmapiChannel::Beta).unwrap().version, "4.1.0-beta.1");
+        assert_eq!(find_release(&releases, "v3.18.6").unwrap().version, "3.18.6");
+        assert!(find_release(&releases, "4.1.0").is_none());
     }
 }
@@ -1,5 +1,7 @@
 pub mod compatibility;
+pub mod download_queue;
 pub mod downloader;
 pub mod github;
 pub mod mods_api;
 pub mod nexuswebsocket;
+pub mod verification;
@@ -2,19 +2,30 @@ use url::form_urlencoded::Parse;
 use url::Url;
 
 use crate::app::api::nexuswebsocket;
+use crate::app::error::JunimoError;
 use crate::app::models::mod_info::ModInfo;
+use crate::app::models::nexus_file::{Md5SearchResult, NexusFile, NexusFiles};
+use crate::app::utility::semantic_version::SemanticVersion;
 
-pub fn get_download_link(url_str: &str) -> String {
-    let url = Url::parse(url_str).unwrap();
+/// Builds the api link to request the download link of a nxm link
+///
+/// * `url_str` - The nxm link (e.g. nxm://stardewvalley/mods/1915/files/92455)
+///
+/// # Returns the api url or an error if the link is invalid
+pub fn get_download_link(url_str: &str) -> Result<String, JunimoError> {
+    let url = parse_nxm(url_str)?;
     let path_segments: Vec<&str> = url
         .path_segments()
         .map(|c| c.collect())
         .unwrap_or_else(Vec::new);
+    if path_segments.len() < 4 {
+        return Err(invalid_link(url_str));
+    }
     let query_pairs = url.query_pairs();
-    let domain = url.domain().unwrap().to_string();
+    let domain = url.domain().ok_or_else(|| invalid_link(url_str))?.to_string();
 
     if query_pairs.count() < 2 {
-        premium_link(domain.to_string(), path_segments)
+        Ok(premium_link(domain.to_string(), path_segments))
     } else {
         link_request(domain.to_string(), path_segments, query_pairs)
     }
@@ -28,9 +39,14 @@ fn premium_link(domain: String, path_segments: Vec<&str>) -> String {
     api_url
 }
 
-fn link_request(domain: String, path_segments: Vec<&str>, mut query_pairs: Parse) -> String {
-    let (_key1, value1) = query_pairs.next().unwrap();
-    let (_key2, value2) = query_pairs.next().unwrap();
+fn link_request(
+    domain: String,
+    path_segments: Vec<&str>,
+    mut query_pairs: Parse,
+) -> Result<String, JunimoError> {
+    let missing = || JunimoError::Other("The nxm link has no key or expiry date".to_string());
+    let (_key1, value1) = query_pairs.next().ok_or_else(missing)?;
+    let (_key2, value2) = query_pairs.next().ok_or_else(missing)?;
 
     let api_url = format!(
         "https://api.nexusmods.com/v1/games/{}/mods/{}/files/{}/download_link.json?key={}&expires={}",
@@ -40,19 +56,58 @@ fn link_request(domain: String, path_segments: Vec<&str>, mut query_pairs: Parse
         value1,
         value2
     );
-    api_url
+    Ok(api_url)
 }
 
-pub async fn get_infos(url_str: &str) -> Option<ModInfo> {
-    let url = Url::parse(url_str).unwrap();
-    let path_segments: Vec<&str> = url
-        .path_segments()
-        .map(|c| c.collect())
-        .unwrap_or_else(Vec::new);
+/// Gets the infos of the mod a nxm link belongs to from Nexus
+///
+/// * `url_str` - The nxm link of the mod
+///
+/// # Returns the infos of the mod or an error if the link is invalid or the request failed
+pub async fn get_infos(url_str: &str) -> Result<ModInfo, JunimoError> {
+    let api_url = api_url(url_str)?;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get(&api_url)
+        .header("accept", "application/json")
+        .header("apikey", nexuswebsocket::load_key())
+        .send()
+        .await?;
+
+    if !res.status().is_success() {
+        return Err(JunimoError::Network(res.status().to_string()));
+    }
+
+    let body = res.text().await?;
+    let mod_info: ModInfo = serde_json::from_str(body.as_str())?;
+    Ok(mod_info)
+}
+
+/// Builds the api link to request the download link of a specific mod file
+///
+/// * `domain` - The game domain on Nexus (e.g. stardewvalley)
+/// * `mod_id` - The Nexus id of the mod
+/// * `file_id` - The Nexus id of the file
+///
+/// # Returns the api url
+pub fn file_download_link(domain: &str, mod_id: u32, file_id: u64) -> String {
+    format!(
+        "https://api.nexusmods.com/v1/games/{}/mods/{}/files/{}/download_link.json",
+        domain, mod_id, file_id
+    )
+}
+
+/// Gets all files of a mod from Nexus
+///
+/// * `domain` - The game domain on Nexus (e.g. stardewvalley)
+/// * `mod_id` - The Nexus id of the mod
+///
+/// # Returns the files of the mod or None if the request failed
+pub async fn get_mod_files(domain: &str, mod_id: u32) -> Option<Vec<NexusFile>> {
     let api_url = format!(
-        "https://api.nexusmods.com/v1/games/{}/mods/{}.json",
-        url.domain().unwrap(),
-        path_segments[1]
+        "https://api.nexusmods.com/v1/games/{}/mods/{}/files.json",
+        domain, mod_id
     );
 
     let client = reqwest::Client::new();
@@ -62,30 +117,202 @@ pub async fn get_infos(url_str: &str) -> Option<ModInfo> {
         .header("apikey", nexuswebsocket::load_key())
         .send()
         .await
-        .unwrap();
+        .ok()?;
+
+    if res.status().is_success() {
+        let body = res.text().await.ok()?;
+        let files: NexusFiles = serde_json::from_str(body.as_str()).ok()?;
+        Some(files.files)
+    } else {
+        None
+    }
+}
+
+/// Gets a single file of a mod from Nexus
+///
+/// * `domain` - The game domain on Nexus (e.g. stardewvalley)
+/// * `mod_id` - The Nexus id of the mod
+/// * `file_id` - The Nexus id of the file
+///
+/// # Returns the file or None if the request failed
+pub async fn get_mod_file(domain: &str, mod_id: u32, file_id: u64) -> Option<NexusFile> {
+    let api_url = format!(
+        "https://api.nexusmods.com/v1/games/{}/mods/{}/files/{}.json",
+        domain, mod_id, file_id
+    );
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get(&api_url)
+        .header("accept", "application/json")
+        .header("apikey", nexuswebsocket::load_key())
+        .send()
+        .await
+        .ok()?;
 
     if res.status().is_success() {
-        let body = res.text().await.unwrap();
-        let body_str = body.as_str();
-        let mod_info: ModInfo = serde_json::from_str(body_str).unwrap();
-        Some(mod_info)
+        let body = res.text().await.ok()?;
+        serde_json::from_str(body.as_str()).ok()
     } else {
         None
     }
 }
 
-fn api_url(url_str: &str) -> String {
-    let url = Url::parse(url_str).unwrap();
+/// Searches Nexus for the files with the given md5 hash
+///
+/// * `domain` - The game domain on Nexus (e.g. stardewvalley)
+/// * `md5` - The md5 hash of the file
+///
+/// # Returns all matching files, an empty list if Nexus doesn't know the hash or an error if the request failed
+pub async fn md5_search(domain: &str, md5: &str) -> Result<Vec<Md5SearchResult>, String> {
+    let api_url = format!(
+        "https://api.nexusmods.com/v1/games/{}/mods/md5_search/{}.json",
+        domain, md5
+    );
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get(&api_url)
+        .header("accept", "application/json")
+        .header("apikey", nexuswebsocket::load_key())
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if res.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(vec![]);
+    }
+    if !res.status().is_success() {
+        return Err(res.status().to_string());
+    }
+
+    let body = res.text().await.map_err(|e| e.to_string())?;
+    serde_json::from_str(body.as_str()).map_err(|e| e.to_string())
+}
+
+/// Gets the game domain, mod id and file id of a nxm link
+///
+/// * `url_str` - The nxm link (e.g. nxm://stardewvalley/mods/1915/files/92455)
+///
+/// # Returns the ids or None if the link is invalid
+pub fn nxm_file_ids(url_str: &str) -> Option<(String, u32, u64)> {
+    let url = Url::parse(url_str).ok()?;
+    let domain = url.domain()?.to_string();
+    let segments: Vec<&str> = url.path_segments()?.collect();
+    if segments.len() < 4 || segments[0] != "mods" || segments[2] != "files" {
+        return None;
+    }
+    Some((domain, segments[1].parse().ok()?, segments[3].parse().ok()?))
+}
+
+/// Selects the file that should be installed for a given version.
+/// Only main files with the same version fit, if there are several the primary and then the newest one is used.
+///
+/// * `files` - The files of the mod
+/// * `version` - The version we want to install
+///
+/// # Returns the selected file or None if no file has the version
+pub fn select_update_file(files: &Vec<NexusFile>, version: &str) -> Option<NexusFile> {
+    files
+        .iter()
+        .filter(|file| file.category_name.as_deref() == Some("MAIN"))
+        .filter(|file| file.version.as_deref().map_or(false, |v| same_version(v, version)))
+        .max_by_key(|file| (file.is_primary, file.uploaded_timestamp))
+        .cloned()
+}
+
+/// Selects the newest main file of a mod, the primary file is preferred
+///
+/// * `files` - The files of the mod
+///
+/// # Returns the selected file or None if the mod has no main file
+pub fn select_latest_file(files: &Vec<NexusFile>) -> Option<NexusFile> {
+    files
+        .iter()
+        .filter(|file| file.category_name.as_deref() == Some("MAIN"))
+        .max_by_key(|file| (file.is_primary, file.uploaded_timestamp))
+        .cloned()
+}
+
+/// Checks if the version of a file is at least the minimum version. A version that isn't a
+/// semantic version can't be compared, so it is accepted and left to SMAPI's own check.
+///
+/// * `version` - The version of the file
+/// * `minimum_version` - The minimum version
+pub fn is_at_least(version: Option<&str>, minimum_version: &str) -> bool {
+    match (
+        version.and_then(|version| version.parse::<SemanticVersion>().ok()),
+        minimum_version.parse::<SemanticVersion>(),
+    ) {
+        (Some(version), Ok(minimum_version)) => version >= minimum_version,
+        _ => true,
+    }
+}
+
+/// Checks if two versions are the same. Nexus versions are free text (e.g. "1.2"), so they are compared
+/// as semantic versions and only compared as text if one of them isn't a semantic version.
+///
+/// * `a` - The first version
+/// * `b` - The second version
+fn same_version(a: &str, b: &str) -> bool {
+    match (a.parse::<SemanticVersion>(), b.parse::<SemanticVersion>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.trim().trim_start_matches('v') == b.trim().trim_start_matches('v'),
+    }
+}
+
+/// Extracts the Nexus mod id out of SMAPI update keys (e.g. "Nexus:1915")
+///
+/// * `update_keys` - The update keys of a manifest
+///
+/// # Returns the Nexus id or None if the mod has no Nexus update key
+pub fn nexus_id_from_update_keys(update_keys: &Vec<String>) -> Option<u32> {
+    update_keys.iter().find_map(|key| {
+        let (site, id) = key.split_once(':')?;
+        if !site.trim().eq_ignore_ascii_case("nexus") {
+            return None;
+        }
+        // Update keys may contain a subkey like "Nexus:1915@optional"
+        id.split('@').next()?.trim().parse::<u32>().ok()
+    })
+}
+
+/// Extracts the Nexus mod id out of a Nexus mod page url
+///
+/// * `url` - The url of the mod page (e.g. https://www.nexusmods.com/stardewvalley/mods/1915)
+///
+/// # Returns the Nexus id or None if the url is not a Nexus url
+pub fn nexus_id_from_url(url: &str) -> Option<u32> {
+    let url = Url::parse(url).ok()?;
+    if !url.domain()?.contains("nexusmods.com") {
+        return None;
+    }
+    let path_segments: Vec<&str> = url.path_segments()?.collect();
+    let index = path_segments.iter().position(|segment| *segment == "mods")?;
+    path_segments.get(index + 1)?.parse::<u32>().ok()
+}
+
+fn api_url(url_str: &str) -> Result<String, JunimoError> {
+    let url = parse_nxm(url_str)?;
     let path_segments: Vec<&str> = url
         .path_segments()
         .map(|c| c.collect())
         .unwrap_or_else(Vec::new);
+    let mod_id = path_segments.get(1).ok_or_else(|| invalid_link(url_str))?;
     let api_url = format!(
         "https://api.nexusmods.com/v1/games/{}/mods/{}.json",
-        url.domain().unwrap(),
-        path_segments[1]
+        url.domain().ok_or_else(|| invalid_link(url_str))?,
+        mod_id
     );
-    api_url
+    Ok(api_url)
+}
+
+fn parse_nxm(url_str: &str) -> Result<Url, JunimoError> {
+    Url::parse(url_str).map_err(|_| invalid_link(url_str))
+}
+
+fn invalid_link(url_str: &str) -> JunimoError {
+    JunimoError::Other(format!("{} isn't a valid nxm link", url_str))
 }
 
 #[cfg(test)]
@@ -95,17 +322,24 @@ mod tests {
     #[test]
     fn test_get_download_link() {
         let url_str = "nxm://stardewvalley/mods/1915/files/92455?key=7PKaqYlhW6z-RNUOLSq3uQ&expires=1715679746&user_id=66607686";
-        let result = get_download_link(url_str);
+        let result = get_download_link(url_str).unwrap();
         assert_eq!(result, "https://api.nexusmods.com/v1/games/stardewvalley/mods/1915/files/92455/download_link.json?key=7PKaqYlhW6z-RNUOLSq3uQ&expires=1715679746");
     }
 
     #[test]
     fn test_get_download_link_premium() {
         let url_str = "nxm://stardewvalley/mods/1915/files/92455";
-        let result = get_download_link(url_str);
+        let result = get_download_link(url_str).unwrap();
         assert_eq!(result, "https://api.nexusmods.com/v1/games/stardewvalley/mods/1915/files/92455/download_link.json");
     }
 
+    #[test]
+    fn test_get_download_link_invalid() {
+        assert!(get_download_link("not a link").is_err());
+        assert!(get_download_link("nxm://stardewvalley/mods/1915").is_err());
+        assert!(get_download_link("nxm://stardewvalley/mods/1915/files/92455?key=abc").is_ok());
+    }
+
     #[test]
     fn test_link_request() {
         let url_str = "nxm://stardewvalley/mods/1915/files/92455?key=7PKaqYlhW6z-RNUOLSq3uQ&expires=1715679746&user_id=66607686";
@@ -120,7 +354,8 @@ mod tests {
             domain.to_string().clone(),
             path_segments.clone(),
             query_pairs.clone(),
-        );
+        )
+        .unwrap();
 
         assert_eq!(path_segments, vec!["mods", "1915", "files", "92455"]);
         assert_eq!(query_pairs.count(), 3);
@@ -132,20 +367,82 @@ mod tests {
     async fn test_get_infos_some() {
         let url_str = "nxm://stardewvalley/mods/1915/files/92455";
         let result = get_infos(url_str).await;
-        assert_eq!(result.is_none(), true);
+        assert_eq!(result.is_err(), true);
     }
 
     #[tokio::test]
     async fn test_get_infos_none() {
         let url_str = "nxm://stardewvalley/mods/3/files/3";
         let result = get_infos(url_str).await;
-        assert_eq!(result.is_none(), true);
+        assert_eq!(result.is_err(), true);
+    }
+
+    #[test]
+    fn test_nexus_id_from_update_keys() {
+        let keys = vec!["GitHub:Pathoschild/SMAPI".to_string(), "Nexus:1915@main".to_string()];
+        assert_eq!(nexus_id_from_update_keys(&keys), Some(1915));
+        assert_eq!(nexus_id_from_update_keys(&vec!["ModDrop:123".to_string()]), None);
+    }
+
+    #[test]
+    fn test_nxm_file_ids() {
+        let url_str = "nxm://stardewvalley/mods/1915/files/92455?key=7PKaqYlhW6z-RNUOLSq3uQ&expires=1715679746";
+        assert_eq!(
+            nxm_file_ids(url_str),
+            Some(("stardewvalley".to_string(), 1915, 92455))
+        );
+        assert_eq!(nxm_file_ids("nxm://stardewvalley/collections/abc"), None);
+    }
+
+    #[test]
+    fn test_nexus_id_from_url() {
+        let url = "https://www.nexusmods.com/stardewvalley/mods/1915";
+        assert_eq!(nexus_id_from_url(url), Some(1915));
+        assert_eq!(nexus_id_from_url("https://github.com/Pathoschild/SMAPI"), None);
+    }
+
+    #[test]
+    fn test_select_update_file() {
+        let file = |id: u64, version: &str, category: &str, primary: bool, time: u64| NexusFile {
+            file_id: id,
+            name: format!("File {}", id),
+            version: Some(version.to_string()),
+            category_name: Some(category.to_string()),
+            is_primary: primary,
+            file_name: format!("file_{}.zip", id),
+            uploaded_timestamp: time,
+            size_in_bytes: None,
+            md5: None,
+        };
+        let files = vec![
+            file(1, "1.0.0", "OLD_VERSION", false, 1),
+            file(2, "1.1.0", "MAIN", false, 2),
+            file(3, "1.2.0", "MAIN", true, 3),
+            file(4, "1.2.0", "MAIN", false, 4),
+            file(5, "1.3", "MAIN", true, 5),
+            file(6, "preview", "MAIN", true, 6),
+        ];
+
+        assert_eq!(select_update_file(&files, "1.1.0").unwrap().file_id, 2);
+        assert_eq!(select_update_file(&files, "v1.2.0").unwrap().file_id, 3);
+        assert!(select_update_file(&files, "1.0.0").is_none());
+        assert!(select_update_file(&files, "2.0.0").is_none());
+        // Nexus versions don't have to be normalized like the ones of smapi.io
+        assert_eq!(select_update_file(&files, "1.3.0").unwrap().file_id, 5);
+        assert_eq!(select_update_file(&files, "preview").unwrap().file_id, 6);
+
+        assert_eq!(select_latest_file(&files).unwrap().file_id, 6);
+        assert!(select_latest_file(&files[..1].to_vec()).is_none());
+        assert!(is_at_least(Some("1.2"), "1.2.0"));
+        assert!(!is_at_least(Some("1.1.0"), "1.2.0"));
+        assert!(is_at_least(Some("preview"), "1.2.0"));
+        assert!(is_at_least(None, "1.2.0"));
     }
 
     #[test]
     fn test_api_url() {
         let url_str = "nxm://stardewvalley/mods/1915/files/92455?key=7PKaqYlhW6z-RNUOLSq3uQ&expires=1715679746&user_id=66607686";
-        let result = api_url(url_str);
+        let result = api_url(url_str).unwrap();
         assert_eq!(
             result,
             "https://api.nexusmods.com/v1/games/stardewvalley/mods/1915.json"
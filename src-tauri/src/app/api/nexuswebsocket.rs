@@ -1,3 +1,4 @@
+use crate::app::error::JunimoError;
 use crate::app::util::app_path;
 use futures_util::{SinkExt, StreamExt};
 use rand::{thread_rng, Rng};
@@ -62,14 +63,20 @@ fn create_uuid() -> String {
     )
 }
 
+/// Reads a file that was saved with bincode
+fn read_binary<T: serde::de::DeserializeOwned>(file: &str) -> Result<T, JunimoError> {
+    let mut file = File::open(app_path(file))?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)?;
+    Ok(bincode::deserialize(&buffer[..])?)
+}
+
 fn load_binary() -> WebsocketData {
     if app_path("connection.stp").exists() {
-        let mut file = File::open(app_path("connection.stp")).unwrap();
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).unwrap();
-        let data: WebsocketData = bincode::deserialize(&buffer[..]).unwrap();
-
-        return data;
+        // A broken connection file is replaced by a new connection
+        if let Ok(data) = read_binary("connection.stp") {
+            return data;
+        }
     }
 
     let data = WebsocketData {
@@ -80,37 +87,42 @@ fn load_binary() -> WebsocketData {
     data
 }
 
+/// Loads the Nexus api key
+///
+/// # Returns the key or an empty string, if the user isn't connected or the key file is broken
 pub fn load_key() -> String {
     if app_path("key.stp").exists() {
-        let mut file = File::open(app_path("key.stp")).unwrap();
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).unwrap();
-        let data: String = bincode::deserialize(&buffer[..]).unwrap();
-
-        return data;
+        match read_binary("key.stp") {
+            Ok(key) => return key,
+            Err(e) => println!("Failed to load the api key: {}", e),
+        }
     }
 
     "".to_string()
 }
 
-fn save_binary(data: &WebsocketData) {
-    let encoded: Vec<u8> = bincode::serialize(&data).unwrap();
-    let mut file = File::create(app_path("connection.stp")).unwrap();
-    file.write_all(&encoded).unwrap();
+fn save_binary(data: &WebsocketData) -> Result<(), JunimoError> {
+    let encoded: Vec<u8> = bincode::serialize(&data)?;
+    let mut file = File::create(app_path("connection.stp"))?;
+    file.write_all(&encoded)?;
+    Ok(())
 }
 
-pub fn save_key(key: String) {
-    let encoded: Vec<u8> = bincode::serialize(&key).unwrap();
-    let mut file = File::create(app_path("key.stp")).unwrap();
-    file.write_all(&encoded).unwrap();
+pub fn save_key(key: String) -> Result<(), JunimoError> {
+    let encoded: Vec<u8> = bincode::serialize(&key)?;
+    let mut file = File::create(app_path("key.stp"))?;
+    file.write_all(&encoded)?;
+    Ok(())
 }
 
-pub async fn connect_user(handle: tauri::AppHandle) {
+pub async fn connect_user(handle: tauri::AppHandle) -> Result<(), JunimoError> {
     let url = Url::parse("wss://sso.nexusmods.com").unwrap();
-    let (mut websocket, _response) = connect_async(url).await.expect("Failed to connect");
+    let (mut websocket, _response) = connect_async(url)
+        .await
+        .map_err(|e| JunimoError::Network(e.to_string()))?;
 
     let data = load_binary();
-    let json_string = serde_json::to_string(&data).unwrap();
+    let json_string = serde_json::to_string(&data)?;
     let sent_data = data.to_owned();
 
     let msg = Message::Text(json_string);
@@ -127,39 +139,35 @@ pub async fn connect_user(handle: tauri::AppHandle) {
         WebviewUrl::External(Url::parse(nexus_link.as_str()).unwrap()),
     )
     .title("Configure")
-    .build()
-    .unwrap();
+    .build()?;
 
-    receive_messages(&mut websocket, sent_data).await;
+    receive_messages(&mut websocket, sent_data).await
 }
 
 async fn receive_messages(
     websocket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
     mut data: WebsocketData,
-) {
+) -> Result<(), JunimoError> {
     while let Some(message) = websocket.next().await {
         match message {
             Ok(msg) => {
-                let msg_text = msg.to_text().unwrap();
+                let msg_text = msg.to_text().unwrap_or_default();
                 if msg_text.contains("connection_token") {
-                    let wrapper: WebsocketDataWrapper =
-                        serde_json::from_str(msg.to_text().unwrap()).unwrap();
+                    let wrapper: WebsocketDataWrapper = serde_json::from_str(msg_text)?;
                     data.token = Some(wrapper.data.connection_token);
-                    save_binary(&data);
+                    save_binary(&data)?;
                 } else if msg_text.contains("api_key") {
-                    let wrapper: ApiKeyWrapper =
-                        serde_json::from_str(msg.to_text().unwrap()).unwrap();
-                    save_key(wrapper.data.api_key);
+                    let wrapper: ApiKeyWrapper = serde_json::from_str(msg_text)?;
+                    save_key(wrapper.data.api_key)?;
 
                     let close_frame = Some(CloseFrame {
                         code: CloseCode::Normal,
                         reason: "Normal Closure".into(),
                     });
 
-                    websocket
-                        .send(Message::Close(close_frame))
-                        .await
-                        .expect("Couldn't close wws");
+                    if let Err(e) = websocket.send(Message::Close(close_frame)).await {
+                        eprintln!("Couldn't close the websocket: {}", e);
+                    }
                 } else {
                     println!("Received close frame from server.");
                     break;
@@ -171,4 +179,5 @@ async fn receive_messages(
             }
         }
     }
+    Ok(())
 }
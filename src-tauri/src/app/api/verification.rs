@@ -0,0 +1,233 @@
+use std::fs;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{command, AppHandle, Runtime};
+
+use crate::app::api::{mods_api, nexuswebsocket};
+use crate::app::console;
+use crate::app::error::JunimoError;
+use crate::app::models::nexus_file::{Md5SearchResult, NexusFile};
+use crate::app::utility::archives::ModArchive;
+use crate::app::utility::paths;
+
+/// A Nexus file a local archive belongs to
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ArchiveMatch {
+    pub mod_id: u32,
+    pub mod_name: Option<String>,
+    pub file_id: u64,
+    pub file_name: String,
+    pub version: Option<String>,
+}
+
+/// The result of verifying a local archive
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ArchiveVerification {
+    pub md5: String,
+    pub size: u64,
+    pub matches: Vec<ArchiveMatch>,
+}
+
+/// Tauri command to look up a local archive on Nexus by its md5 hash
+///
+/// * `path` - The path to the archive
+///
+/// # Returns the hash of the archive and every Nexus file with the same hash
+#[command]
+pub async fn verify_archive(path: String) -> Result<ArchiveVerification, JunimoError> {
+    if nexuswebsocket::load_key().is_empty() {
+        return Err(JunimoError::Other(
+            "No NexusMods API Key found. Please provide a key in settings.".to_string(),
+        ));
+    }
+
+    let path = PathBuf::from(path);
+    let md5 = file_md5(&path).map_err(JunimoError::Io)?;
+    let size = fs::metadata(&path)?.len();
+
+    let matches = mods_api::md5_search("stardewvalley", &md5)
+        .await
+        .map_err(JunimoError::Network)?
+        .into_iter()
+        .map(|result| ArchiveMatch {
+            mod_id: result.mod_info.mod_id,
+            mod_name: result.mod_info.name,
+            file_id: result.file_details.file_id,
+            file_name: result.file_details.name,
+            version: result.file_details.version,
+        })
+        .collect();
+
+    Ok(ArchiveVerification { md5, size, matches })
+}
+
+/// Checks a downloaded archive against the metadata of its Nexus file.
+/// If Nexus doesn't provide the md5 hash of the file, the hash is looked up with the md5 search instead.
+///
+/// * `app_handle` - Tauri Handle, warnings are shown in its console
+/// * `path` - The path to the downloaded archive
+/// * `domain` - The game domain on Nexus
+/// * `expected` - The Nexus file that was downloaded
+///
+/// # Returns Ok if the archive is intact or couldn't be checked, otherwise the reason of the mismatch
+pub async fn verify_download<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    path: &Path,
+    domain: &str,
+    expected: &NexusFile,
+) -> Result<(), String> {
+    let size = fs::metadata(path).map_err(|e| e.to_string())?.len();
+    check_size(size, expected.size_in_bytes)?;
+
+    let md5 = file_md5(path)?;
+    match &expected.md5 {
+        Some(expected_md5) => check_md5(&md5, expected_md5),
+        None => match mods_api::md5_search(domain, &md5).await {
+            Ok(results) => check_search_results(&results, expected.file_id),
+            // Nexus being unreachable shouldn't block the installation
+            Err(e) => {
+                console::add_line(
+                    app_handle,
+                    format!(
+                        "<span class=\"console-yellow\">[Junimo] {} couldn't be checked against Nexus ({}), it is installed without the check</span>",
+                        expected.file_name, e
+                    ),
+                );
+                Ok(())
+            }
+        },
+    }
+}
+
+/// Moves a broken archive into the quarantine folder, so it is neither installed nor resumed
+///
+/// * `path` - The path to the archive
+/// * `name` - The name of the mod
+///
+/// # Returns the new path of the archive
+pub fn quarantine(path: &Path, name: &str) -> Result<PathBuf, String> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let file_name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    // Downloads are saved as .zip no matter their format, so the format is detected by the content
+    let extension = match ModArchive::open(path) {
+        Ok(archive) => archive.format.extension().to_string(),
+        Err(_) => path
+            .extension()
+            .map_or("bin".to_string(), |extension| extension.to_string_lossy().to_string()),
+    };
+    let destination = paths::quarantine_path().join(format!("{}-{}.{}", file_name, timestamp, extension));
+
+    // Renaming fails across drives, so copy the file in that case
+    if fs::rename(path, &destination).is_err() {
+        fs::copy(path, &destination).map_err(|e| e.to_string())?;
+        let _ = fs::remove_file(path);
+    }
+    Ok(destination)
+}
+
+/// Calculates the md5 hash of a file without loading it into memory at once
+///
+/// * `path` - The path to the file
+///
+/// # Returns the hash as lowercase hex string
+pub fn file_md5(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    let mut context = md5::Context::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        context.consume(&buffer[..read]);
+    }
+
+    Ok(format!("{:x}", context.compute()))
+}
+
+/// Compares the size of an archive with the size Nexus reports
+fn check_size(size: u64, expected: Option<u64>) -> Result<(), String> {
+    match expected {
+        Some(expected) if expected != size => Err(format!(
+            "Archive has {} bytes, but Nexus reports {} bytes",
+            size, expected
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Compares the md5 hash of an archive with the hash Nexus reports
+fn check_md5(md5: &str, expected: &str) -> Result<(), String> {
+    if md5.eq_ignore_ascii_case(expected.trim()) {
+        Ok(())
+    } else {
+        Err(format!("Archive has the md5 hash {}, but Nexus reports {}", md5, expected))
+    }
+}
+
+/// Checks that the md5 search found the downloaded file
+fn check_search_results(results: &Vec<Md5SearchResult>, file_id: u64) -> Result<(), String> {
+    if results.iter().any(|result| result.file_details.file_id == file_id) {
+        Ok(())
+    } else {
+        Err("The md5 hash of the archive doesn't match the file on Nexus".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::app::models::nexus_file::{Md5FileDetails, Md5SearchMod};
+
+    use super::*;
+
+    #[test]
+    fn test_file_md5() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("mod.zip");
+        fs::write(&path, "Junimo").unwrap();
+
+        let md5 = file_md5(&path).unwrap();
+        assert_eq!(md5, format!("{:x}", md5::compute("Junimo")));
+        assert!(check_md5(&md5, &md5.to_uppercase()).is_ok());
+        assert!(check_md5(&md5, "d41d8cd98f00b204e9800998ecf8427e").is_err());
+    }
+
+    #[test]
+    fn test_checks() {
+        assert!(check_size(10, Some(10)).is_ok());
+        assert!(check_size(10, None).is_ok());
+        assert!(check_size(9, Some(10)).is_err());
+
+        let result = Md5SearchResult {
+            mod_info: Md5SearchMod {
+                mod_id: 1915,
+                name: Some("Content Patcher".to_string()),
+                version: None,
+                domain_name: "stardewvalley".to_string(),
+            },
+            file_details: Md5FileDetails {
+                file_id: 92455,
+                name: "Content Patcher".to_string(),
+                version: None,
+                md5: "abc".to_string(),
+                size_in_bytes: None,
+            },
+        };
+        assert!(check_search_results(&vec![result.clone()], 92455).is_ok());
+        assert!(check_search_results(&vec![result], 1).is_err());
+        assert!(check_search_results(&vec![], 92455).is_err());
+    }
+}
@@ -1,9 +1,15 @@
 use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 
+use crate::app::api::download_queue::DownloadQueue;
+use crate::app::mod_installation::PendingInstalls;
+use crate::app::state_store::StateStore;
+
 #[derive(Clone)]
 pub struct AppState {
-    pub stop_download: Arc<Mutex<bool>>,
+    pub downloads: DownloadQueue,
+    pub installs: PendingInstalls,
+    pub store: StateStore,
     pub stop_game: Arc<Mutex<bool>>,
 }
 
@@ -12,7 +18,9 @@ impl AppState {
         let (tx, rx) = mpsc::channel(32);
         (
             AppState {
-                stop_download: Arc::new(Mutex::new(false)),
+                downloads: DownloadQueue::default(),
+                installs: PendingInstalls::default(),
+                store: StateStore::default(),
                 stop_game: Arc::new(Mutex::new(true)),
             },
             rx,
@@ -1,4 +1,3 @@
-use std::fs::File;
 use std::io::{BufReader, Write};
 use std::path::{Path, PathBuf};
 use std::{env, fs};
@@ -10,10 +9,14 @@ use tauri::{command, Manager, Runtime, WebviewUrl, WebviewWindow, Window};
 use winreg::enums::HKEY_CLASSES_ROOT;
 #[cfg(target_os = "windows")]
 use winreg::RegKey;
+use crate::app::activation::ActivationStrategy;
 use crate::app::api::nexuswebsocket;
 
 use crate::app::app_state::AppState;
-use crate::app::utility::paths;
+use crate::app::error::JunimoError;
+use crate::app::game_detection;
+use crate::app::utility::extraction::ExtractionLimits;
+use crate::app::utility::{paths, persistence};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
@@ -26,6 +29,9 @@ pub struct Config {
     pub block_on_broken: Option<bool>,
     pub lang: Option<String>,
     pub keep_open: Option<bool>,
+    pub max_downloads: Option<usize>,
+    pub activation_strategy: Option<ActivationStrategy>,
+    pub extraction_limits: Option<ExtractionLimits>,
 }
 
 impl Config {
@@ -40,40 +46,79 @@ impl Config {
             block_on_broken: Some(true),
             lang: Some("en".to_string()),
             keep_open: Some(true),
+            max_downloads: Some(2),
+            activation_strategy: Some(ActivationStrategy::Link),
+            extraction_limits: Some(ExtractionLimits::default()),
         }
     }
 }
 
+/// Loads the config from a config.json file. A broken file is replaced by the default config,
+/// so the settings are asked for again.
+///
+/// * `path` - The path to the config.json file
+pub fn load_config(path: PathBuf) -> Config {
+    match persistence::read_json(&path, |data| Ok(serde_json::from_str(data)?)) {
+        Ok(config) => config,
+        Err(e @ JunimoError::Unsupported(_)) => {
+            // The file belongs to a newer version of Junimo, so it is kept
+            println!("{}", e);
+            Config::new(false, detected_game_path(), false)
+        }
+        Err(e) => {
+            // The broken file was moved aside, so the settings are asked for again
+            println!("{}", e);
+            // The config window is still opened, so the user can confirm the detected game
+            let config = Config::new(false, detected_game_path(), false);
+            if let Err(e) = save_config(&config, &path) {
+                println!("Failed to reset config.json: {}", e);
+            }
+            config
+        }
+    }
+}
+
+/// Gets the first Stardew Valley installation that was found on this computer
+///
+/// # Returns the path to the game or an empty string if no installation was found
+fn detected_game_path() -> String {
+    game_detection::detect()
+        .into_iter()
+        .next()
+        .map(|candidate| candidate.path)
+        .unwrap_or_default()
+}
+
+/// Gets the limits archives have to stay within while they are extracted
+pub fn extraction_limits() -> ExtractionLimits {
+    load_config(paths::config_path()).extraction_limits.unwrap_or_default()
+}
+
 /// Gets config from config.json file in appdata
 #[command]
-pub fn get_config(path: PathBuf) -> Config {
-    let data_raw = fs::read_to_string(path).unwrap();
-    let data = data_raw.as_str();
-    serde_json::from_str(data).unwrap()
+pub fn get_config(path: PathBuf) -> Result<Config, JunimoError> {
+    Ok(load_config(path))
 }
 
 /// Saves config into config.json file
-fn save_config(config: &Config, path: &PathBuf) {
-    let json = serde_json::to_string(&config).unwrap();
-    let mut file = File::create(path).expect("Fail");
-    file.write_all(json.as_bytes()).unwrap();
+fn save_config(config: &Config, path: &PathBuf) -> Result<(), JunimoError> {
+    persistence::write_json(path, config)
 }
 
 /// Open config window
 #[command]
-pub async fn open_config<R: Runtime>(handle: tauri::AppHandle<R>) {
+pub async fn open_config<R: Runtime>(handle: tauri::AppHandle<R>) -> Result<(), JunimoError> {
     #[cfg(target_os = "windows")]
     tauri::WebviewWindowBuilder::new(&handle, "Config", WebviewUrl::App("/config".into()))
         .title("Settings")
         .transparent(true)
-        .build()
-        .unwrap();
+        .build()?;
 
     #[cfg(target_os = "unix")]
     tauri::WebviewWindowBuilder::new(&handle, "Config", WebviewUrl::App("/config".into()))
         .title("Settings")
-        .build()
-        .unwrap();
+        .build()?;
+    Ok(())
 }
 
 #[command]
@@ -82,37 +127,38 @@ pub fn save_config_button<R: Runtime>(
     handle: tauri::AppHandle<R>,
     config: Config,
     path: PathBuf,
-) {
-    let old_config = get_config(paths::config_path());
+) -> Result<(), JunimoError> {
+    let old_config = load_config(paths::config_path());
 
-    if &old_config.lang != &config.lang {
-        &handle.emit("language_changed", &config.lang).unwrap();
+    save_config(&config, &path)?;
+    if old_config.lang != config.lang {
+        handle.emit("language_changed", &config.lang)?;
     }
 
-    save_config(&config, &path);
     if config.handle_nxm {
         let _nxm_result = register_nxm();
     }
-    window.close().expect("Couldn't close window!");
+    window.close()?;
+    Ok(())
 }
 
 pub fn init_config<R: Runtime>(
     handle: &tauri::AppHandle<R>,
     dir: &Path,
-) -> Result<Option<WebviewWindow<R>>, String> {
+) -> Result<Option<WebviewWindow<R>>, JunimoError> {
     let mut path = dir.to_owned();
-    fs::create_dir_all(&path).unwrap();
+    fs::create_dir_all(&path)?;
     path.push("config.json");
 
-    if !path.exists() {
-        let config = Config::new(false, "".to_string(), false);
-        save_config(&config, &path);
+    if !persistence::exists(&path) {
+        // The config window is still opened, so the user can confirm the detected game
+        let config = Config::new(false, detected_game_path(), false);
+        save_config(&config, &path)?;
 
         let window_result =
             tauri::WebviewWindowBuilder::new(handle, "Config", WebviewUrl::App("/config".into()))
                 .title("Configuration")
-                .build()
-                .unwrap();
+                .build()?;
         return Ok(Some(window_result));
     }
 
@@ -120,13 +166,13 @@ pub fn init_config<R: Runtime>(
 }
 
 #[command]
-pub fn load_api_key() -> String {
-    nexuswebsocket::load_key()
+pub fn load_api_key() -> Result<String, JunimoError> {
+    Ok(nexuswebsocket::load_key())
 }
 
 #[command]
-pub fn set_api_key(key: String) {
-    nexuswebsocket::save_key(key);
+pub fn set_api_key(key: String) -> Result<(), JunimoError> {
+    nexuswebsocket::save_key(key)
 }
 
 #[cfg(target_os = "windows")]
@@ -184,6 +230,8 @@ fn register_nxm() -> Result<(), String> {
 
 #[cfg(test)]
 mod tests {
+    use std::fs::File;
+
     use tauri::test::mock_builder;
     use tempfile::tempdir;
 
@@ -228,7 +276,7 @@ mod tests {
         let mut file = File::create(&config_path).expect("Fail");
         writeln!(file, "{}", serialized_payload).expect("Couldn't write");
 
-        let result = get_config(config_path.clone());
+        let result = load_config(config_path.clone());
         assert_eq!(result.init_app, expected_config.init_app);
     }
 
@@ -239,7 +287,7 @@ mod tests {
 
         let config = Config::new(false, "".to_string(), false);
 
-        save_config(&config, &config_path);
+        save_config(&config, &config_path).unwrap();
 
         assert_eq!(config_path.exists(), true);
     }
@@ -305,13 +353,13 @@ mod tests {
 
         let config = Config::new(false, "".to_string(), false);
 
-        save_config(&config, &config_path);
+        save_config(&config, &config_path).unwrap();
 
         let serialized_payload = serde_json::to_string(&config).unwrap();
         let mut file = File::create(&config_path).expect("Fail");
         writeln!(file, "{}", serialized_payload).expect("Couldn't write");
 
-        let result = get_config(config_path.clone());
+        let result = load_config(config_path.clone());
         assert_eq!(result.init_app, config.init_app);
     }
 
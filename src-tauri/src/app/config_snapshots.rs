@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::app::app_state::AppState;
+use crate::app::error::JunimoError;
+use crate::app::models::mod_info::ModInfo;
+use crate::app::profiles::ResolvedProfile;
+use crate::app::{console, mod_store};
+
+/// Writes the saved mod configs of a profile into the mod folders before the game starts.
+/// Mods without a saved config start with their default config, so no settings of other
+/// profiles leak into this one. Profiles that were never started keep the current files.
+///
+/// * `app_handle` - The app handle
+/// * `profile` - The profile that is about to be started
+/// * `mods_path` - The folder SMAPI loads the mods from
+pub fn restore_configs<R: Runtime>(app_handle: &AppHandle<R>, profile: &ResolvedProfile, mods_path: &Path) {
+    let configs = match &profile.configs {
+        Some(configs) => configs,
+        None => return,
+    };
+
+    for error in write_configs(mods_path, &profile.mods, configs) {
+        console::add_line(
+            app_handle,
+            format!(
+                "<span class=\"console-red\">[Junimo] Failed to restore config: {}</span>",
+                error
+            ),
+        );
+    }
+}
+
+/// Saves the current mod configs of all mods of a profile into the profile
+///
+/// * `app_handle` - The app handle
+/// * `profile_name` - The name of the profile that was played
+/// * `mods_path` - The folder SMAPI loaded the mods from
+pub fn capture_configs<R: Runtime>(app_handle: &AppHandle<R>, profile_name: &str, mods_path: &Path) {
+    let store = app_handle.state::<AppState>().store.clone();
+    let registry = store.mods();
+
+    let result = store.update_profiles(app_handle, |profiles| {
+        for profile in profiles.iter_mut() {
+            if profile.name == profile_name {
+                profile.configs = Some(read_configs(mods_path, &profile.resolve_mods(&registry)));
+            }
+        }
+    });
+    if let Err(e) = result {
+        console::error(app_handle, &e);
+    }
+}
+
+/// Saves a single mod config into the current profile, if the profile already saves its configs
+///
+/// * `app_handle` - The app handle
+/// * `mod_info` - The mod the config belongs to
+/// * `content` - The content of the config.json
+pub fn store_config<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    mod_info: &ModInfo,
+    content: &str,
+) -> Result<(), JunimoError> {
+    let store = app_handle.state::<AppState>().store.clone();
+    store.update_profiles(app_handle, |profiles| {
+        for profile in profiles.iter_mut() {
+            if !profile.currently || !profile.contains(mod_info) {
+                continue;
+            }
+            if let Some(configs) = profile.configs.as_mut() {
+                configs.insert(snapshot_key(mod_info), content.to_string());
+            }
+        }
+    })
+}
+
+/// Gets the folder of a mod SMAPI loads it from. That's either a link in the mods folder of a profile
+/// or the folder of the used version in the mod store.
+///
+/// * `mod_path` - The folder SMAPI loads the mods from
+/// * `mod_info` - The mod with the version the profile uses
+fn mod_dir(mod_path: &Path, mod_info: &ModInfo) -> PathBuf {
+    mod_store::find_version(mod_path, &mod_info.folder_name(), &mod_info.version)
+        .unwrap_or(mod_path.join(mod_info.folder_name()))
+}
+
+/// Gets the key a mod's config is saved under
+fn snapshot_key(mod_info: &ModInfo) -> String {
+    mod_info
+        .unique_id
+        .clone()
+        .unwrap_or_else(|| mod_info.name.clone())
+}
+
+/// Reads the config.json of every given mod
+///
+/// * `mod_path` - The folder that contains the active mods
+/// * `mods` - The mods to read the configs of
+///
+/// # Returns the configs by the mods unique id
+fn read_configs(mod_path: &Path, mods: &Vec<ModInfo>) -> HashMap<String, String> {
+    let mut configs = HashMap::new();
+
+    for mod_info in mods {
+        let config_path = mod_dir(mod_path, mod_info).join("config.json");
+        if let Ok(content) = fs::read_to_string(&config_path) {
+            configs.insert(snapshot_key(mod_info), content);
+        }
+    }
+
+    configs
+}
+
+/// Writes the saved config.json of every given mod and removes configs that weren't saved
+///
+/// * `mod_path` - The folder that contains the active mods
+/// * `mods` - The mods to write the configs of
+/// * `configs` - The saved configs by the mods unique id
+///
+/// # Returns the errors of all configs that couldn't be written
+fn write_configs(
+    mod_path: &Path,
+    mods: &Vec<ModInfo>,
+    configs: &HashMap<String, String>,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    for mod_info in mods {
+        let mod_dir = mod_dir(mod_path, mod_info);
+        if !mod_dir.exists() {
+            continue;
+        }
+
+        let config_path = mod_dir.join("config.json");
+        let result = match configs.get(&snapshot_key(mod_info)) {
+            Some(content) => fs::write(&config_path, content),
+            None if config_path.exists() => fs::remove_file(&config_path),
+            None => Ok(()),
+        };
+
+        if let Err(e) = result {
+            errors.push(format!("{} ({})", &mod_info.name, e));
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_read_and_write_configs() {
+        let tmp_dir = tempdir().unwrap();
+        let mod_path = tmp_dir.path();
+        let mods = vec![
+            ModInfo::for_test("First", Some("Test.First"), "1.0.0"),
+            ModInfo::for_test("Second", Some("Test.Second"), "1.0.0"),
+        ];
+
+        fs::create_dir_all(mod_path.join("Test.First")).unwrap();
+        fs::create_dir_all(mod_path.join("Test.Second")).unwrap();
+        fs::write(mod_path.join("Test.First").join("config.json"), "{\"Speed\": 2}").unwrap();
+
+        let configs = read_configs(mod_path, &mods);
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs.get("Test.First").unwrap(), "{\"Speed\": 2}");
+
+        // Another profile changed the configs in the meantime
+        fs::write(mod_path.join("Test.First").join("config.json"), "{\"Speed\": 5}").unwrap();
+        fs::write(mod_path.join("Test.Second").join("config.json"), "{}").unwrap();
+
+        let errors = write_configs(mod_path, &mods, &configs);
+        assert!(errors.is_empty());
+        assert_eq!(
+            fs::read_to_string(mod_path.join("Test.First").join("config.json")).unwrap(),
+            "{\"Speed\": 2}"
+        );
+        assert!(!mod_path.join("Test.Second").join("config.json").exists());
+    }
+}
@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use tauri::test::MockRuntime;
 use tauri::{AppHandle, Manager, Runtime};
 
+use crate::app::error::JunimoError;
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 struct Console {
     content: String,
@@ -33,6 +35,23 @@ pub fn modify_line<R: Runtime>(handle: &AppHandle<R>, content: String) {
     &handle.emit("console", console_content).unwrap();
 }
 
+/// Reports an error of a task that runs in the background. The error is added to the console
+/// and sent to the frontend as "notification", so the user sees it in a dialog.
+///
+/// * `handle` - The app handle
+/// * `error` - The error
+pub fn error<R: Runtime>(handle: &AppHandle<R>, error: &JunimoError) {
+    if error == &JunimoError::Cancelled {
+        add_line(handle, "<span class=\"console-gray\">[Junimo] Cancelled</span>".to_string());
+        return;
+    }
+
+    add_line(handle, format!("<span class=\"console-red\">[Junimo] {}</span>", error));
+    if let Err(e) = handle.emit("notification", error) {
+        println!("Error: {}", e);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,4 +106,19 @@ mod tests {
 
         modify_line(&handle, "Hello World".to_string());
     }
+
+    #[test]
+    fn test_error() {
+        let tauri_app = tauri::test::mock_app();
+        let handle = tauri_app.handle().clone();
+
+        let listener = tauri_app.listen("notification", |e| {
+            assert_eq!(
+                "{\"kind\":\"GameNotFound\",\"message\":\"Stardew Valley wasn't found. Please set the game path in the settings.\"}",
+                e.payload()
+            );
+        });
+
+        error(&handle, &JunimoError::GameNotFound);
+    }
 }
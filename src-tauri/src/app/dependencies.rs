@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tauri::{command, AppHandle, Manager, State};
+
+use crate::app::api::compatibility::{self, ModSource};
+use crate::app::api::downloader;
+use crate::app::app_state::AppState;
+use crate::app::error::JunimoError;
+use crate::app::models::mod_info::ModInfo;
+use crate::app::utility::browser;
+use crate::app::{console, profiles};
+use crate::app::utility::semantic_version::SemanticVersion;
+
+/// The state of a single dependency of a mod
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum DependencyState {
+    Satisfied,
+    Missing,
+    TooOld,
+}
+
+/// A dependency of a mod together with its resolved state
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResolvedDependency {
+    pub unique_id: String,
+    pub minimum_version: Option<String>,
+    pub installed_version: Option<String>,
+    pub is_required: bool,
+    pub state: DependencyState,
+}
+
+/// A mod in the dependency graph with all its resolved dependencies
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DependencyNode {
+    pub name: String,
+    pub unique_id: Option<String>,
+    pub version: String,
+    pub dependencies: Vec<ResolvedDependency>,
+}
+
+impl DependencyNode {
+    /// Gets all required dependencies that are missing or too old
+    pub fn unmet_requirements(&self) -> Vec<&ResolvedDependency> {
+        self.dependencies
+            .iter()
+            .filter(|dependency| dependency.is_required && dependency.state != DependencyState::Satisfied)
+            .collect()
+    }
+}
+
+/// Tauri command to get the dependency graph of a profile
+///
+/// * `name` - The name of the profile. If None, the current profile is used
+///
+/// # Returns a node with the resolved dependencies for every mod of the profile
+#[command]
+pub fn get_dependency_graph(
+    app_state: State<'_, AppState>,
+    name: Option<String>,
+) -> Result<Vec<DependencyNode>, JunimoError> {
+    let profiles = app_state.store.profiles();
+    let registry = app_state.store.mods();
+    let profile = profiles.into_iter().find(|profile| match &name {
+        Some(name) => &profile.name == name,
+        None => profile.currently,
+    });
+
+    match profile {
+        Some(profile) => Ok(resolve(&profile.resolve_mods(&registry))),
+        None => Ok(vec![]),
+    }
+}
+
+/// Tauri command to list the required mods that are missing in the current profile together with
+/// their download sources. Nothing is downloaded, so the list can be confirmed by the user first.
+///
+/// * `app_handle` - The app handle
+///
+/// # Returns the download source of every missing required mod
+#[command]
+pub async fn get_missing_dependencies(app_handle: AppHandle) -> Result<Vec<ModSource>, JunimoError> {
+    let store = app_handle.state::<AppState>().store.clone();
+    let profile = match store.profiles().into_iter().find(|profile| profile.currently) {
+        Some(profile) => profile,
+        None => return Ok(vec![]),
+    };
+
+    let mods = profile.resolve_mods(&store.mods());
+    let missing = missing_requirements(&mods);
+    if missing.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut sources = compatibility::get_mod_sources(missing.clone())
+        .await
+        .unwrap_or_else(|| {
+            missing
+                .iter()
+                .map(|unique_id| ModSource {
+                    unique_id: unique_id.clone(),
+                    ..Default::default()
+                })
+                .collect()
+        });
+
+    // Mods that are installed but not part of the profile don't need to be downloaded
+    let installed = store.mods();
+    for source in sources.iter_mut() {
+        source.installed = find_installed(&installed, &source.unique_id).is_some();
+        source.minimum_version = required_minimum_version(&mods, &source.unique_id);
+    }
+
+    Ok(sources)
+}
+
+/// Tauri command to install the confirmed missing dependencies and add them to the current profile.
+/// Mods without a Nexus or GitHub source are opened in the browser instead.
+///
+/// * `app_handle` - The app handle
+/// * `app_state` - The app state
+/// * `unique_ids` - The unique ids of the confirmed mods
+#[command]
+pub async fn install_missing_dependencies(
+    app_handle: AppHandle,
+    app_state: State<'_, AppState>,
+    unique_ids: Vec<String>,
+) -> Result<(), JunimoError> {
+    let sources = get_missing_dependencies(app_handle.clone()).await?;
+
+    for source in sources.into_iter().filter(|source| {
+        unique_ids
+            .iter()
+            .any(|unique_id| unique_id.eq_ignore_ascii_case(&source.unique_id))
+    }) {
+        let name = source.name.clone().unwrap_or(source.unique_id.clone());
+
+        if !source.installed {
+            match downloader::download_dependency(&app_handle, &source, app_state.inner().clone()).await {
+                Ok(_) => {}
+                Err(e) => {
+                    match &source.custom_source_url {
+                        Some(url) if source.nexus_id.is_none() && source.github_repo.is_none() => {
+                            browser::open_url(url);
+                            console::add_line(
+                                &app_handle,
+                                format!(
+                                    "<span class=\"console-yellow\">[Junimo] {} has to be downloaded manually from {}</span>",
+                                    name, url
+                                ),
+                            );
+                        }
+                        _ => console::add_line(
+                            &app_handle,
+                            format!(
+                                "<span class=\"console-red\">[Junimo] Failed to install {}: {}</span>",
+                                name, e
+                            ),
+                        ),
+                    }
+                    continue;
+                }
+            }
+        }
+
+        match find_installed(&app_state.store.mods(), &source.unique_id) {
+            Some(mod_info) => {
+                profiles::add_to_current_profile(&app_handle, &mod_info)?;
+                console::add_line(
+                    &app_handle,
+                    format!(
+                        "<span class=\"console-green\">[Junimo] Added {} to the current profile</span>",
+                        &mod_info.name
+                    ),
+                );
+            }
+            None => console::add_line(
+                &app_handle,
+                format!(
+                    "<span class=\"console-red\">[Junimo] {} was installed, but its manifest doesn't contain {}</span>",
+                    name, &source.unique_id
+                ),
+            ),
+        }
+    }
+
+    app_handle.emit("reload", true)?;
+    Ok(())
+}
+
+/// Collects the unique ids of all required mods that are missing, without duplicates
+///
+/// * `mods` - The mods that are used together
+fn missing_requirements(mods: &Vec<ModInfo>) -> Vec<String> {
+    let mut missing: Vec<String> = Vec::new();
+
+    for node in resolve(mods) {
+        for dependency in node.unmet_requirements() {
+            if dependency.state == DependencyState::Missing
+                && !missing
+                    .iter()
+                    .any(|unique_id| unique_id.eq_ignore_ascii_case(&dependency.unique_id))
+            {
+                missing.push(dependency.unique_id.clone());
+            }
+        }
+    }
+
+    missing
+}
+
+/// Gets the highest minimum version the given mods require of a mod
+///
+/// * `mods` - The mods that are used together
+/// * `unique_id` - The unique id of the required mod
+///
+/// # Returns the minimum version or None if no mod requires a version
+fn required_minimum_version(mods: &Vec<ModInfo>, unique_id: &str) -> Option<String> {
+    mods.iter()
+        .flat_map(|mod_info| mod_info.dependencies.iter().flatten())
+        .filter(|dependency| {
+            dependency.is_required.unwrap_or(true) && dependency.unique_id.eq_ignore_ascii_case(unique_id)
+        })
+        .filter_map(|dependency| {
+            let minimum_version = dependency.minimum_version.as_ref()?;
+            Some((minimum_version.parse::<SemanticVersion>().ok()?, minimum_version.clone()))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, minimum_version)| minimum_version)
+}
+
+/// Finds an installed mod by its unique id
+fn find_installed(mods: &Vec<ModInfo>, unique_id: &str) -> Option<ModInfo> {
+    mods.iter()
+        .find(|mod_info| {
+            mod_info
+                .unique_id
+                .as_ref()
+                .is_some_and(|id| id.eq_ignore_ascii_case(unique_id))
+        })
+        .cloned()
+}
+
+/// Resolves the dependencies of the given mods against each other.
+/// Unique ids are compared case-insensitively, versions by SMAPI's semantic version rules.
+///
+/// * `mods` - The mods that are installed together
+///
+/// # Returns a node for every mod, in the same order as the given mods
+pub fn resolve(mods: &Vec<ModInfo>) -> Vec<DependencyNode> {
+    let installed: HashMap<String, &ModInfo> = mods
+        .iter()
+        .filter_map(|mod_info| {
+            let unique_id = mod_info.unique_id.as_ref()?;
+            Some((unique_id.to_lowercase(), mod_info))
+        })
+        .collect();
+
+    mods.iter()
+        .map(|mod_info| DependencyNode {
+            name: mod_info.name.clone(),
+            unique_id: mod_info.unique_id.clone(),
+            version: mod_info.version.clone(),
+            dependencies: mod_info
+                .dependencies
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|dependency| {
+                    let installed_mod = installed.get(&dependency.unique_id.to_lowercase());
+                    let installed_version = installed_mod.map(|m| m.version.clone());
+                    let state = match &installed_version {
+                        None => DependencyState::Missing,
+                        Some(version) => {
+                            if is_too_old(version, &dependency.minimum_version) {
+                                DependencyState::TooOld
+                            } else {
+                                DependencyState::Satisfied
+                            }
+                        }
+                    };
+
+                    ResolvedDependency {
+                        unique_id: dependency.unique_id,
+                        minimum_version: dependency.minimum_version,
+                        installed_version,
+                        is_required: dependency.is_required.unwrap_or(true),
+                        state,
+                    }
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Checks if an installed version is older than the minimum version.
+/// Versions that can't be parsed are treated as fine, so a typo in a manifest doesn't block a mod.
+///
+/// * `installed` - The installed version
+/// * `minimum` - The minimum version of the dependency
+fn is_too_old(installed: &str, minimum: &Option<String>) -> bool {
+    let minimum = match minimum {
+        Some(minimum) if !minimum.trim().is_empty() => minimum,
+        _ => return false,
+    };
+
+    match (
+        installed.parse::<SemanticVersion>(),
+        minimum.parse::<SemanticVersion>(),
+    ) {
+        (Ok(installed), Ok(minimum)) => installed < minimum,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::mods::Dependency;
+
+    use super::*;
+
+    fn dependency(unique_id: &str, minimum_version: Option<&str>, is_required: Option<bool>) -> Dependency {
+        Dependency {
+            unique_id: unique_id.to_string(),
+            minimum_version: minimum_version.map(|v| v.to_string()),
+            is_required,
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mods = vec![
+            ModInfo::for_test("Test.Pack", Some("Test.Pack"), "1.0.0").with_dependencies(vec![
+                dependency("pathoschild.contentpatcher", Some("2.0.0"), None),
+                dependency("Test.Framework", Some("1.5.0-beta.2"), Some(true)),
+                dependency("Test.Missing", None, Some(false)),
+            ]),
+            ModInfo::for_test("Pathoschild.ContentPatcher", Some("Pathoschild.ContentPatcher"), "2.1.0"),
+            ModInfo::for_test("Test.Framework", Some("Test.Framework"), "1.5.0-beta.1"),
+        ];
+
+        let nodes = resolve(&mods);
+        let states: Vec<DependencyState> = nodes[0]
+            .dependencies
+            .iter()
+            .map(|dependency| dependency.state.clone())
+            .collect();
+
+        assert_eq!(
+            states,
+            vec![
+                DependencyState::Satisfied,
+                DependencyState::TooOld,
+                DependencyState::Missing
+            ]
+        );
+        assert_eq!(nodes[0].unmet_requirements().len(), 1);
+        assert!(nodes[1].dependencies.is_empty());
+    }
+
+    #[test]
+    fn test_missing_requirements() {
+        let mods = vec![
+            ModInfo::for_test("Test.First", Some("Test.First"), "1.0.0").with_dependencies(vec![
+                dependency("Test.Framework", None, None),
+                dependency("Test.Optional", None, Some(false)),
+                dependency("Test.Installed", Some("2.0.0"), None),
+            ]),
+            ModInfo::for_test("Test.Second", Some("Test.Second"), "1.0.0")
+                .with_dependencies(vec![dependency("test.framework", None, None)]),
+            ModInfo::for_test("Test.Installed", Some("Test.Installed"), "1.0.0"),
+        ];
+
+        assert_eq!(missing_requirements(&mods), vec!["Test.Framework"]);
+        assert_eq!(required_minimum_version(&mods, "test.installed"), Some("2.0.0".to_string()));
+        assert_eq!(required_minimum_version(&mods, "Test.Framework"), None);
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt;
+use std::io;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::app::mod_config_editor::ConfigError;
+
+/// Every error a Tauri command can return. The frontend gets the kind of the error
+/// together with a message that tells the user what to do about it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JunimoError {
+    /// Reading or writing a file or folder failed
+    Io(String),
+    /// A json file couldn't be read or written
+    Json(String),
+    /// The manifest.json of a mod is missing or broken
+    Manifest { path: String, message: String },
+    /// A request to Nexus, GitHub or smapi.io failed
+    Network(String),
+    /// An archive couldn't be read or unpacked
+    Archive(String),
+    /// The values of a mod config are invalid, every error points to the key it belongs to
+    InvalidConfig(Vec<ConfigError>),
+    /// The game folder isn't set or doesn't contain Stardew Valley
+    GameNotFound,
+    /// A mod, profile, download or install plan doesn't exist (anymore)
+    NotFound(String),
+    /// A file was saved by a newer version of Junimo
+    Unsupported(String),
+    /// The user cancelled the action
+    Cancelled,
+    Other(String),
+}
+
+impl JunimoError {
+    /// Gets the name of the variant, so the frontend can react to the kind of error
+    pub fn kind(&self) -> &'static str {
+        match self {
+            JunimoError::Io(_) => "Io",
+            JunimoError::Json(_) => "Json",
+            JunimoError::Manifest { .. } => "Manifest",
+            JunimoError::Network(_) => "Network",
+            JunimoError::Archive(_) => "Archive",
+            JunimoError::InvalidConfig(_) => "InvalidConfig",
+            JunimoError::GameNotFound => "GameNotFound",
+            JunimoError::NotFound(_) => "NotFound",
+            JunimoError::Unsupported(_) => "Unsupported",
+            JunimoError::Cancelled => "Cancelled",
+            JunimoError::Other(_) => "Other",
+        }
+    }
+}
+
+impl fmt::Display for JunimoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JunimoError::Io(message) => write!(
+                f,
+                "{}. Make sure the file isn't opened by another program and Junimo is allowed to write to its folder.",
+                message
+            ),
+            JunimoError::Json(message) => write!(f, "Couldn't read the file: {}", message),
+            JunimoError::Manifest { path, message } => write!(
+                f,
+                "The manifest.json of {} is broken ({}). Ask the mod author for a fixed version.",
+                path, message
+            ),
+            JunimoError::Network(message) => write!(
+                f,
+                "The request failed ({}). Check your internet connection and try again.",
+                message
+            ),
+            JunimoError::Archive(message) => write!(
+                f,
+                "The archive couldn't be unpacked ({}). Try to download it again.",
+                message
+            ),
+            JunimoError::InvalidConfig(errors) => {
+                let errors: Vec<String> = errors
+                    .iter()
+                    .map(|error| match error.path.is_empty() {
+                        true => error.message.clone(),
+                        false => format!("{}: {}", error.path, error.message),
+                    })
+                    .collect();
+                write!(f, "The config couldn't be saved: {}", errors.join(", "))
+            }
+            JunimoError::GameNotFound => write!(
+                f,
+                "Stardew Valley wasn't found. Please set the game path in the settings."
+            ),
+            JunimoError::NotFound(what) => write!(f, "{} wasn't found", what),
+            JunimoError::Unsupported(message) => write!(
+                f,
+                "{}. It was saved by a newer version of Junimo, please update Junimo.",
+                message
+            ),
+            JunimoError::Cancelled => write!(f, "Cancelled"),
+            JunimoError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for JunimoError {}
+
+impl Serialize for JunimoError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("JunimoError", 3)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        // The config editor shows the errors next to the keys they belong to
+        if let JunimoError::InvalidConfig(errors) = self {
+            state.serialize_field("errors", errors)?;
+        }
+        state.end()
+    }
+}
+
+impl From<io::Error> for JunimoError {
+    fn from(error: io::Error) -> Self {
+        JunimoError::Io(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for JunimoError {
+    fn from(error: serde_json::Error) -> Self {
+        JunimoError::Json(error.to_string())
+    }
+}
+
+impl From<reqwest::Error> for JunimoError {
+    fn from(error: reqwest::Error) -> Self {
+        JunimoError::Network(error.to_string())
+    }
+}
+
+impl From<zip::result::ZipError> for JunimoError {
+    fn from(error: zip::result::ZipError) -> Self {
+        JunimoError::Archive(error.to_string())
+    }
+}
+
+impl From<bincode::Error> for JunimoError {
+    fn from(error: bincode::Error) -> Self {
+        JunimoError::Io(error.to_string())
+    }
+}
+
+impl From<tauri::Error> for JunimoError {
+    fn from(error: tauri::Error) -> Self {
+        JunimoError::Other(error.to_string())
+    }
+}
+
+impl From<String> for JunimoError {
+    fn from(message: String) -> Self {
+        JunimoError::Other(message)
+    }
+}
+
+impl From<&str> for JunimoError {
+    fn from(message: &str) -> Self {
+        JunimoError::Other(message.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize() {
+        let error = JunimoError::NotFound("Profile Test".to_string());
+        assert_eq!(
+            serde_json::to_string(&error).unwrap(),
+            "{\"kind\":\"NotFound\",\"message\":\"Profile Test wasn't found\"}"
+        );
+    }
+
+    #[test]
+    fn test_conversions() {
+        let error: JunimoError = io::Error::new(io::ErrorKind::PermissionDenied, "denied").into();
+        assert_eq!(error.kind(), "Io");
+        assert!(error.to_string().starts_with("denied."));
+
+        let error: JunimoError = serde_json::from_str::<u32>("{").unwrap_err().into();
+        assert_eq!(error.kind(), "Json");
+
+        let error: JunimoError = "Something went wrong".into();
+        assert_eq!(error, JunimoError::Other("Something went wrong".to_string()));
+    }
+}
@@ -9,39 +9,39 @@ use tauri::{command, Manager, Runtime, WebviewUrl, Window};
 use zip::write::SimpleFileOptions;
 use zip::ZipWriter;
 
+use crate::app::app_state::AppState;
+use crate::app::error::JunimoError;
 use crate::app::models::mod_info::ModInfo;
-use crate::app::profiles::Profile;
 use crate::app::utility::{paths, zips};
-use crate::app::{console, mods, profiles};
+use crate::app::{console, profiles};
 
 #[command]
-pub async fn open_export<R: Runtime>(handle: tauri::AppHandle<R>) {
+pub async fn open_export<R: Runtime>(handle: tauri::AppHandle<R>) -> Result<(), JunimoError> {
     #[cfg(target_os = "windows")]
     tauri::WebviewWindowBuilder::new(&handle, "Exporter", WebviewUrl::App("/exporter".into()))
         .title("Export")
         .min_inner_size(600.0, 350.0)
         .inner_size(600.0, 350.0)
         .transparent(true)
-        .build()
-        .unwrap();
+        .build()?;
 
     #[cfg(target_os = "unix")]
     tauri::WebviewWindowBuilder::new(&handle, "Exporter", WebviewUrl::App("/exporter".into()))
         .title("Export")
         .min_inner_size(600.0, 350.0)
         .inner_size(600.0, 350.0)
-        .build()
-        .unwrap();
+        .build()?;
+    Ok(())
 }
 
 #[command]
-pub fn select_export_dir() -> String {
+pub fn select_export_dir() -> Result<String, JunimoError> {
     let file = FileDialog::new().set_directory(".").pick_folder();
 
     if let Some(path) = file {
-        path.to_string_lossy().to_string()
+        Ok(path.to_string_lossy().to_string())
     } else {
-        "".to_string()
+        Ok("".to_string())
     }
 }
 
@@ -51,22 +51,24 @@ pub fn export_profile<R: Runtime>(
     handle: tauri::AppHandle<R>,
     name: String,
     path: String,
-) -> bool {
+) -> Result<bool, JunimoError> {
     let handle_clone = handle.clone();
     let window_clone = window.clone();
     let name_clone = name.clone();
     let path_clone = path.clone();
 
     thread::spawn(move || {
-        if name == "All Profiles" {
-            export_all(handle_clone, path_clone);
-            window_clone.close().unwrap();
+        let result = if name == "All Profiles" {
+            export_all(&handle_clone, path_clone)
         } else {
-            export_one(handle_clone, name_clone, path_clone);
-            window_clone.close().unwrap();
+            export_one(&handle_clone, name_clone, path_clone)
+        };
+        if let Err(e) = result {
+            console::error(&handle_clone, &e);
         }
+        let _ = window_clone.close();
     });
-    true
+    Ok(true)
 }
 
 fn export(
@@ -74,25 +76,19 @@ fn export(
     mods: &Vec<ModInfo>,
     profile_path: &PathBuf,
     mod_path: Option<PathBuf>,
-) -> Result<(), String> {
-    let zip_file = File::create(zip_path);
-
-    if zip_file.is_err() {
-        return Err(zip_file.err().unwrap().to_string());
-    }
-
-    let zip_file = zip_file.unwrap();
+) -> Result<(), JunimoError> {
+    let zip_file = File::create(zip_path)?;
     let mut zip = ZipWriter::new(zip_file);
 
     for mod_info in mods {
         let mut mod_path = paths::mod_path();
-        let mod_paths = &mod_path.join(&mod_info.name);
-        let mod_path_dot = &mod_path.join(format!(".{}", &mod_info.name));
+        let mod_paths = &mod_path.join(mod_info.folder_name());
+        let mod_path_dot = &mod_path.join(format!(".{}", mod_info.folder_name()));
 
         if mod_paths.exists() {
-            zips::zip_mods(&mut zip, mod_paths);
+            zips::zip_mods(&mut zip, mod_paths)?;
         } else if mod_path_dot.exists() {
-            zips::zip_mods(&mut zip, mod_path_dot);
+            zips::zip_mods(&mut zip, mod_path_dot)?;
         }
     }
 
@@ -100,98 +96,70 @@ fn export(
     let to_mods_path = PathBuf::from("mods.json");
     let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
 
-    zip.start_file(to_profile_path.to_string_lossy(), options)
-        .unwrap();
-    let mut profile_file = File::open(profile_path).unwrap();
-    io::copy(&mut profile_file, &mut zip).unwrap();
+    zip.start_file(to_profile_path.to_string_lossy(), options)?;
+    let mut profile_file = File::open(profile_path)?;
+    io::copy(&mut profile_file, &mut zip)?;
 
-    if mod_path.is_some() {
-        zip.start_file(to_mods_path.to_string_lossy(), options)
-            .unwrap();
-        let mut mods_file = File::open(mod_path.unwrap()).unwrap();
-        io::copy(&mut mods_file, &mut zip).unwrap();
+    if let Some(mod_path) = mod_path {
+        zip.start_file(to_mods_path.to_string_lossy(), options)?;
+        let mut mods_file = File::open(mod_path)?;
+        io::copy(&mut mods_file, &mut zip)?;
     }
 
-    return match zip.finish() {
-        Ok(_) => Ok(()),
-        Err(e) => Err(e.to_string()),
-    };
+    zip.finish()?;
+    Ok(())
 }
 
-fn export_one<R: Runtime>(handle: tauri::AppHandle<R>, name: String, path: String) {
-    let profiles = profiles::get_profiles(paths::profile_path());
-    let mut profile: Profile = Profile {
-        name: "None".to_string(),
-        mods: vec![],
-        currently: false,
-    };
-
-    for prof in profiles {
-        if prof.name == name {
-            profile = prof;
-        }
-    }
+fn export_one<R: Runtime>(handle: &tauri::AppHandle<R>, name: String, path: String) -> Result<(), JunimoError> {
+    let store = handle.state::<AppState>().store.clone();
+    let profile = store
+        .profiles()
+        .into_iter()
+        .find(|profile| profile.name == name)
+        .ok_or(JunimoError::NotFound(format!("The profile {}", name)))?;
 
     let export_path = Path::new(&path).join(format!("export_{}.zip", profile.name));
 
-    let profiles: Vec<Profile> = vec![profile.clone()];
-    let profile_json = serde_json::to_string(&profiles).unwrap();
-    let mut temp_path = paths::temp_path().join(&name);
-    fs::create_dir_all(&temp_path).unwrap();
+    // The profile only references its mods, so their infos are exported alongside it
+    let mods = profile.resolve_mods(&store.mods());
+    let temp_path = paths::temp_path().join(&name);
+    fs::create_dir_all(&temp_path)?;
     let temp_file_path = temp_path.join("profile.json");
-    fs::write(&temp_file_path, profile_json).unwrap();
-
-    match export(&export_path, &profile.mods, &temp_file_path, None) {
-        Ok(_) => {
-            fs::remove_dir_all(&temp_path).unwrap();
-            console::add_line(
-                &handle,
-                format!(
-                    "<span class=\"console-green\">[Junimo] Exported profile {}</span>",
-                    profile.name
-                ),
-            );
-        }
-        Err(e) => {
-            console::add_line(
-                &handle,
-                format!(
-                    "<span class=\"console-red\">[Junimo] Error exporting all profiles and mods: {}</span>",
-                    e
-                ),
-            );
-        }
-    }
+    let temp_mods_path = temp_path.join("mods.json");
+    profiles::save_profiles(&vec![profile.clone()], &temp_file_path)?;
+    fs::write(&temp_mods_path, serde_json::to_string(&mods)?)?;
+
+    let result = export(&export_path, &mods, &temp_file_path, Some(temp_mods_path));
+    let _ = fs::remove_dir_all(&temp_path);
+    result?;
+
+    console::add_line(
+        handle,
+        format!(
+            "<span class=\"console-green\">[Junimo] Exported profile {}</span>",
+            profile.name
+        ),
+    );
+    Ok(())
 }
 
-fn export_all<R: Runtime>(handle: tauri::AppHandle<R>, path: String) {
+fn export_all<R: Runtime>(handle: &tauri::AppHandle<R>, path: String) -> Result<(), JunimoError> {
     let export_path = Path::new(&path).join("export_all.zip");
     let profile_file = paths::appdata_path().join("profile.json");
     let mods_file = paths::appdata_path().join("mods.json");
 
-    match export(
+    export(
         &export_path,
-        &mods::get_all_mods(),
+        &handle.state::<AppState>().store.mods(),
         &profile_file,
         Some(mods_file),
-    ) {
-        Ok(_) => {
-            console::add_line(
-                &handle,
-                "<span class=\"console-green\">[Junimo] Exported all profiles and mods</span>"
-                    .to_string(),
-            );
-        }
-        Err(e) => {
-            console::add_line(
-                &handle,
-                format!(
-                    "<span class=\"console-red\">[Junimo] Error exporting all profiles and mods: {}</span>",
-                    e
-                ),
-            );
-        }
-    };
+    )?;
+
+    console::add_line(
+        handle,
+        "<span class=\"console-green\">[Junimo] Exported all profiles and mods</span>".to_string(),
+    );
+    Ok(())
 }
 
 #[cfg(test)]
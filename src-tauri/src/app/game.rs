@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
@@ -11,22 +11,25 @@ use regex::Regex;
 use sysinfo::System;
 use tauri::{command, AppHandle, Manager, State};
 
+use crate::app::activation::{self, ActivationStrategy};
 use crate::app::app_state::AppState;
+use crate::app::error::JunimoError;
 use crate::app::models::mod_info::ModInfo;
 use crate::app::utility::paths;
-use crate::app::{config, console, profiles};
+use crate::app::profiles::ResolvedProfile;
+use crate::app::{config, config_snapshots, console, dependencies, load_order, mod_store, profiles, smapi_manager};
 
 /// Starts the game through the frontend
 ///
 /// * `app_handle` - The app handle
 /// * `app_state` - The app state
 ///
-/// # Returns Command result
+/// # Returns Command result. Failures to start the game are reported as notification instead.
 #[command]
 pub async fn start_game(
     app_handle: AppHandle,
     app_state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> Result<(), JunimoError> {
     console::empty_line(&app_handle);
     console::add_line(
         &app_handle,
@@ -37,14 +40,8 @@ pub async fn start_game(
     match init_game(app_handle.clone(), app_state.stop_game.clone()).await {
         Ok(_) => {}
         Err(e) => {
-            app_handle.emit("close", true).unwrap();
-            console::add_line(
-                &app_handle,
-                format!(
-                    "<span style=\"color: #c22f2f\">[Junimo] Failed to start game: {}</span>",
-                    e
-                ),
-            );
+            app_handle.emit("close", true)?;
+            console::error(&app_handle, &e);
         }
     }
     Ok(())
@@ -59,43 +56,38 @@ pub async fn start_game(
 async fn init_game(
     original_app_handle: AppHandle,
     original_stop_game: Arc<Mutex<bool>>,
-) -> Result<(), String> {
+) -> Result<(), JunimoError> {
     let app_handle = original_app_handle.clone();
     let stop_game = original_stop_game.clone();
 
     // Spawn a new thread to initialize the game without blocking the main thread
     let spawn_result = tokio::spawn(async move {
-        let config = config::get_config(paths::config_path());
-        let profile = profiles::get_current_profile(paths::profile_path()).await;
-        let mods = profile.mods;
-        let mod_path = paths::mod_path().display().to_string();
-        let directories = get_directories(&mod_path).unwrap();
-
-        // Puts all mod names into a HashSet
-        let mod_names: HashSet<String> = mods
-            .iter()
-            .filter_map(|mod_info| Some(mod_info.name.clone()))
-            .collect();
+        let config = config::load_config(paths::config_path());
+        let profile = profiles::get_current_profile(app_handle.clone(), paths::profile_path()).await?;
+        let mods = profile.mods.clone();
 
-        // Puts all directories to remove into a Vec
-        let dirs_to_remove = directories
+        // Puts the folder names of all mods of the profile together with the version they use into a HashMap
+        let mod_folders: HashMap<String, String> = mods
             .iter()
-            .filter(|dir| !mod_names.contains(*dir) && !dir.contains("."))
-            .collect::<Vec<_>>();
+            .map(|mod_info| (mod_info.folder_name(), mod_info.version.clone()))
+            .collect();
 
-        // Puts all directories to add into a Vec
-        let mods_to_add = directories
-            .iter()
-            .filter(|dir| {
-                mod_names.contains(&dir.replace(".", "").to_string()) && dir.contains(".")
-            })
-            .collect::<Vec<_>>();
+        let smapi_mods_path = match config.activation_strategy.clone().unwrap_or_default() {
+            ActivationStrategy::Link => link_mods(&app_handle, &profile.name, &mod_folders),
+            ActivationStrategy::Rename => None,
+        };
+        let smapi_mods_path = match smapi_mods_path {
+            Some(path) => path,
+            None => {
+                rename_mods(&app_handle, &mod_folders)?;
+                paths::mod_path()
+            }
+        };
 
-        // Uninstall and install mods
-        uninstall_mods(&app_handle, &mod_path, dirs_to_remove);
-        install_missing_mods(&app_handle, &mod_path, mods_to_add);
+        // Restore the mod configs that were saved for this profile
+        config_snapshots::restore_configs(&app_handle, &profile, &smapi_mods_path);
 
-        let config = config::get_config(paths::config_path());
+        let config = config::load_config(paths::config_path());
 
         // Check if there are any missing dependencies
         let any_missing_mods = any_missing_dependencies(&mods);
@@ -104,7 +96,9 @@ async fn init_game(
                 || (config.block_on_missing_requirements.is_some()
                     && config.block_on_missing_requirements.unwrap()))
         {
-            return Err("Missing requirements detected. Please check your mods.".to_string());
+            return Err(JunimoError::Other(
+                "Missing requirements detected. Please check your mods.".to_string(),
+            ));
         }
 
         // Check if there are any broken mods
@@ -117,66 +111,161 @@ async fn init_game(
             && (config.block_on_broken.is_none()
                 || (config.block_on_broken.is_some() && config.block_on_broken.unwrap()))
         {
-            return Err(
+            return Err(JunimoError::Other(
                 "Some of your currently installed mods are broken. Please remove or update them."
                     .to_string(),
-            );
+            ));
         }
 
-        let smapi_result = start_smapi(app_handle, &stop_game.clone());
-        return match smapi_result {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e),
-        };
+        report_load_order(&app_handle, &mods);
+        report_smapi_version(&app_handle, &profile);
+
+        start_smapi(app_handle, &stop_game.clone(), profile.name, smapi_mods_path)
     })
-    .await
-    .unwrap();
+    .await;
 
-    return match spawn_result {
-        Ok(_) => Ok(()),
-        Err(e) => Err(e.to_string()),
-    };
+    match spawn_result {
+        Ok(result) => result,
+        Err(e) => Err(JunimoError::Other(e.to_string())),
+    }
 }
 
-/// Checks if there are any missing dependencies
+/// Checks if there are any missing or outdated required dependencies
 ///
 /// * `mods` - The list of mods to check
 ///
 /// # Returns true if there are any missing dependencies, false otherwise
 pub fn any_missing_dependencies(mods: &Vec<ModInfo>) -> bool {
-    let cloned_mods = mods.clone();
-    let mut new_modinfo = Vec::new();
-
-    for mut mod_info in mods.clone() {
-        // If the mod has no dependencies, skip the check and add it to the new_modinfo
-        if mod_info.dependencies.is_none() {
-            new_modinfo.push(mod_info.clone());
-            continue;
-        }
+    dependencies::resolve(mods)
+        .iter()
+        .any(|node| !node.unmet_requirements().is_empty())
+}
 
-        match &mod_info.dependencies {
-            Some(dependencies) => {
-                for dependency in dependencies {
-                    // If the dependency was found, skip the check and add it to the new_modinfo
-                    if cloned_mods
-                        .iter()
-                        .any(|mod_info| mod_info.unique_id == Some(dependency.unique_id.clone()))
-                    {
-                        continue;
-                    }
-                    // If the dependency was not found, and it is required, return true
-                    if dependency.is_required == None || dependency.is_required == Some(true) {
-                        return true;
-                    } else {
-                        continue;
-                    }
-                }
-                new_modinfo.push(mod_info.clone());
+/// Reports every mod SMAPI is going to skip because of its load order, before the game is started
+///
+/// * `app_handle` - The app handle
+/// * `mods` - The mods of the started profile
+fn report_load_order(app_handle: &AppHandle, mods: &Vec<ModInfo>) {
+    let load_order = load_order::analyze(mods);
+
+    for diagnostic in load_order.diagnostics.iter() {
+        console::add_line(
+            app_handle,
+            format!(
+                "<span style=\"color: #c22f2f\">[Junimo] {}</span>",
+                &diagnostic.message
+            ),
+        );
+    }
+
+    app_handle.emit("load-order", &load_order).unwrap();
+}
+
+/// Warns if the profile is pinned to another SMAPI version than the installed one
+///
+/// * `app_handle` - The app handle
+/// * `profile` - The started profile
+fn report_smapi_version(app_handle: &AppHandle, profile: &ResolvedProfile) {
+    let pinned = match &profile.smapi_version {
+        Some(pinned) => pinned,
+        None => return,
+    };
+
+    let installed = smapi_manager::installed_version();
+    if !smapi_manager::version_matches(pinned, installed.as_deref()) {
+        console::add_line(
+            app_handle,
+            format!(
+                "<span style=\"color: #c22f2f\">[Junimo] The profile {} is pinned to SMAPI {}, but {} is installed. Install the pinned version through the SMAPI window.</span>",
+                profile.name,
+                pinned,
+                installed.unwrap_or_else(|| "no SMAPI version".to_string())
+            ),
+        );
+    }
+}
+
+/// Builds the mods folder of a profile out of links to the installed mods
+///
+/// * `app_handle` - The app handle
+/// * `profile_name` - The name of the started profile
+/// * `mod_folders` - The folder names of the mods of the profile and the versions they use
+///
+/// # Returns the mods folder of the profile or None if the mods have to be renamed instead
+fn link_mods(
+    app_handle: &AppHandle,
+    profile_name: &str,
+    mod_folders: &HashMap<String, String>,
+) -> Option<PathBuf> {
+    let target = paths::profile_mods_path(profile_name);
+    let mods: Vec<(String, String)> = mod_folders.clone().into_iter().collect();
+
+    match activation::link_profile(&paths::mod_path(), &target, &mods) {
+        Ok(missing) => {
+            for folder in missing {
+                console::add_line(
+                    app_handle,
+                    format!(
+                        "<span style=\"color: #c22f2f\">[Junimo] {} is part of the profile, but isn't installed</span>",
+                        folder
+                    ),
+                );
             }
-            None => {}
+            Some(target)
+        }
+        Err(e) => {
+            console::add_line(
+                app_handle,
+                format!(
+                    "<span style=\"color: #c22f2f\">[Junimo] Failed to link the mods of the profile ({}), renaming the mod folders instead</span>",
+                    e
+                ),
+            );
+            None
         }
     }
-    false
+}
+
+/// Activates the mods of a profile by renaming the folders of all other mods and versions to start with a dot
+///
+/// * `app_handle` - The app handle
+/// * `mod_folders` - The folder names of the mods of the profile and the versions they use
+fn rename_mods(app_handle: &AppHandle, mod_folders: &HashMap<String, String>) -> Result<(), JunimoError> {
+    let mod_path = paths::mod_path().display().to_string();
+    let directories = get_directories(&mod_path)?;
+
+    // Puts all active directories that aren't part of the profile into a Vec
+    let dirs_to_remove = directories
+        .iter()
+        .filter(|dir| !dir.starts_with('.') && !mod_folders.contains_key(*dir))
+        .collect::<Vec<_>>();
+
+    // Puts all inactive directories of the profile into a Vec
+    let mods_to_add = directories
+        .iter()
+        .filter(|dir| match dir.strip_prefix('.') {
+            Some(folder) => mod_folders.contains_key(folder),
+            None => false,
+        })
+        .collect::<Vec<_>>();
+
+    // Uninstall and install mods
+    uninstall_mods(app_handle, &mod_path, dirs_to_remove);
+    install_missing_mods(app_handle, &mod_path, mods_to_add);
+
+    // Only the version the profile uses may stay visible to SMAPI
+    for (folder, version) in mod_folders {
+        if let Err(e) = mod_store::select_version(&paths::mod_path(), folder, version) {
+            console::add_line(
+                app_handle,
+                format!(
+                    "<span style=\"color: #c22f2f\">[Junimo] Failed to select version {} of {} ({})</span>",
+                    version, folder, e
+                ),
+            );
+        }
+    }
+    Ok(())
 }
 
 /// Gets all mod directories
@@ -238,18 +327,21 @@ fn uninstall_mods(app_handle: &AppHandle, mod_path: &String, dir_to_remove: Vec<
 /// * `mods_to_add` - The mods to add
 fn install_missing_mods(app_handle: &AppHandle, mod_path: &String, mods_to_add: Vec<&String>) {
     for dir in mods_to_add {
+        // Only the leading dot marks an inactive mod, unique ids contain dots themselves
+        let folder = dir.strip_prefix('.').unwrap_or(dir);
         let dir_path = PathBuf::from(mod_path).join(dir);
-        let new_path = PathBuf::from(mod_path).join(dir.replace(".", ""));
-        if new_path.exists() {
-            fs::remove_dir_all(&new_path).unwrap();
-        }
-        match fs::rename(&dir_path, &new_path) {
+        let new_path = PathBuf::from(mod_path).join(folder);
+        let result = match new_path.exists() {
+            true => fs::remove_dir_all(&new_path),
+            false => Ok(()),
+        };
+        match result.and_then(|_| fs::rename(&dir_path, &new_path)) {
             Ok(_) => {
                 console::add_line(
                     &app_handle,
                     format!(
                         "<span style=\"color: #2fb565\">[Junimo] Installed {}</span>",
-                        &dir.replace(".", "")
+                        folder
                     ),
                 );
             }
@@ -258,7 +350,7 @@ fn install_missing_mods(app_handle: &AppHandle, mod_path: &String, mods_to_add:
                     &app_handle,
                     format!(
                         "<span style=\"color: #c22f2f\">[Junimo] Failed to install {} ({})</span>",
-                        &dir.replace(".", ""),
+                        folder,
                         e.to_string()
                     ),
                 );
@@ -271,30 +363,37 @@ fn install_missing_mods(app_handle: &AppHandle, mod_path: &String, mods_to_add:
 ///
 /// * `app_handle` - The app handle
 /// * `app_state` - The app state
-fn start_smapi(app_handle: AppHandle, app_state: &Arc<Mutex<bool>>) -> Result<(), String> {
+/// * `profile_name` - The name of the started profile to save the mod configs into
+/// * `mods_path` - The folder SMAPI loads the mods from
+fn start_smapi(
+    app_handle: AppHandle,
+    app_state: &Arc<Mutex<bool>>,
+    profile_name: String,
+    mods_path: PathBuf,
+) -> Result<(), JunimoError> {
     let stop_signal = app_state.clone();
 
     // Set the environment variable for the mods path
     let key = "SMAPI_MODS_PATH";
-    env::set_var(key, paths::mod_path().display().to_string());
+    env::set_var(key, mods_path.display().to_string());
 
     let game_path = paths::get_game_path();
 
     if !game_path.exists() || game_path == paths::mod_path() {
-        return Err("Game path not found. Please check your settings.".to_string());
+        return Err(JunimoError::GameNotFound);
     }
 
     #[cfg(target_os = "windows")]
     if !game_path.clone().join("StardewModdingAPI.exe").exists() {
-        return Err(
+        return Err(JunimoError::Other(
             "SMAPI was not found! Please install SMAPI before starting the game.".to_string(),
-        );
+        ));
     }
     #[cfg(not(target_os = "windows"))]
     if !game_path.clone().join("StardewModdingAPI.dll").exists() {
-        return Err(
+        return Err(JunimoError::Other(
             "SMAPI was not found! Please install SMAPI before starting the game.".to_string(),
-        );
+        ));
     }
 
     // Get the path to the SMAPI executable. On Windows it is a .exe file, on other platforms it is a .dll file
@@ -314,23 +413,28 @@ fn start_smapi(app_handle: AppHandle, app_state: &Arc<Mutex<bool>>) -> Result<()
             pixel_width: 0,
             pixel_height: 0,
         })
-        .unwrap();
+        .map_err(|e| JunimoError::Other(format!("Couldn't open a terminal for SMAPI: {}", e)))?;
 
     // Spawn the SMAPI executable
     let cmd = CommandBuilder::new(game_path);
-    let mut child = pair.slave.spawn_command(cmd).unwrap();
+    let mut child = pair
+        .slave
+        .spawn_command(cmd)
+        .map_err(|e| JunimoError::Other(format!("Couldn't start SMAPI: {}", e)))?;
 
     // Read the console output into Junimo's console
-    read_console(&pair, app_handle.clone());
+    read_console(&pair, app_handle.clone())?;
 
     // Spawn a new thread to check if the game should be stopped.
-    // If the game should be stopped, kill the child process and close the PTY
+    // If the game should be stopped, kill the child process, close the PTY and save the mod configs
     thread::spawn(move || loop {
         if set_stop_game(&stop_signal, &child) {
-            child.kill().unwrap();
+            // The game might already have exited on its own
+            let _ = child.kill();
             drop(child);
             drop(pair.slave);
             drop(pair.master);
+            config_snapshots::capture_configs(&app_handle, &profile_name, &mods_path);
             app_handle.clone().emit("close", true).unwrap();
             break;
         }
@@ -345,9 +449,12 @@ fn start_smapi(app_handle: AppHandle, app_state: &Arc<Mutex<bool>>) -> Result<()
 ///
 /// * `pair` - The PTY pair we created
 /// * `app_handle` - The app handle
-fn read_console(pair: &PtyPair, app_handle: AppHandle) {
+fn read_console(pair: &PtyPair, app_handle: AppHandle) -> Result<(), JunimoError> {
     // Create a new reader and read the console output line by line
-    let reader = pair.master.try_clone_reader().unwrap();
+    let reader = pair
+        .master
+        .try_clone_reader()
+        .map_err(|e| JunimoError::Other(format!("Couldn't read the output of SMAPI: {}", e)))?;
     let mut reader = BufReader::new(reader);
     let mut lines = reader.lines();
 
@@ -366,7 +473,8 @@ fn read_console(pair: &PtyPair, app_handle: AppHandle) {
                 // Check if the line was not the title and add it to Junimo's console
                 if !clean_line.is_empty() {
                     // Convert the ANSI console output to HTML
-                    let html_line = ansi_to_html::convert(&clean_line.to_string()).unwrap();
+                    let html_line =
+                        ansi_to_html::convert(&clean_line.to_string()).unwrap_or(clean_line.to_string());
 
                     // Make anything between square brackets bold
                     let mut bolded = html_line.replace("[", "<b>[");
@@ -384,6 +492,7 @@ fn read_console(pair: &PtyPair, app_handle: AppHandle) {
             }
         }
     });
+    Ok(())
 }
 
 /// Sets the stop game signal to stop SMAPI and the game
@@ -429,7 +538,8 @@ fn set_stop_game(stop_signal: &Arc<Mutex<bool>>, child: &Box<dyn Child + Send +
 ///
 /// * `app_state` - The app state
 #[command]
-pub fn stop_game(app_state: State<'_, AppState>) {
-    let mut signal = app_state.stop_game.lock().unwrap();
+pub fn stop_game(app_state: State<'_, AppState>) -> Result<(), JunimoError> {
+    let mut signal = app_state.stop_game.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
     *signal = false;
+    Ok(())
 }
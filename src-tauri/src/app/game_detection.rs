@@ -0,0 +1,422 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::command;
+
+#[cfg(target_os = "windows")]
+use winreg::enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+#[cfg(target_os = "windows")]
+use winreg::RegKey;
+
+use crate::app::error::JunimoError;
+use crate::app::utility::version_extractor::{self, GAME_FILES};
+
+/// The Steam app id of Stardew Valley
+const STEAM_APP_ID: &str = "413150";
+/// The GOG product id of Stardew Valley
+#[cfg(target_os = "windows")]
+const GOG_PRODUCT_ID: &str = "1453375253";
+
+/// The store a Stardew Valley installation was found through
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum GameSource {
+    Steam,
+    Gog,
+    Xbox,
+    /// A path chosen by the user
+    Manual,
+}
+
+/// A folder that may contain Stardew Valley
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GameCandidate {
+    pub path: String,
+    pub source: GameSource,
+    /// The version of the game or None if it couldn't be read
+    pub version: Option<String>,
+    /// Whether the folder contains the game
+    pub valid: bool,
+}
+
+impl GameCandidate {
+    /// Checks a folder and reads the version of the game in it
+    ///
+    /// * `path` - The folder to check
+    /// * `source` - The store the folder was found through
+    pub fn new(path: &Path, source: GameSource) -> Self {
+        let game_file = GAME_FILES.iter().map(|file| path.join(file)).find(|file| file.is_file());
+        GameCandidate {
+            path: path.to_string_lossy().to_string(),
+            source,
+            version: game_file.as_deref().and_then(version_extractor::get_file_version),
+            valid: game_file.is_some(),
+        }
+    }
+}
+
+/// A value of Valve's KeyValues format, that is used by libraryfolders.vdf and the app manifests
+#[derive(Debug, Clone, PartialEq)]
+pub enum Vdf {
+    Value(String),
+    Object(HashMap<String, Vdf>),
+}
+
+impl Vdf {
+    /// Gets a child of an object. Keys are case-insensitive, like in Steam.
+    pub fn get(&self, key: &str) -> Option<&Vdf> {
+        match self {
+            Vdf::Object(children) => children
+                .iter()
+                .find(|(child, _)| child.eq_ignore_ascii_case(key))
+                .map(|(_, value)| value),
+            Vdf::Value(_) => None,
+        }
+    }
+
+    /// Gets the text of a value
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Vdf::Value(value) => Some(value),
+            Vdf::Object(_) => None,
+        }
+    }
+}
+
+/// Parses a file in Valve's KeyValues format
+///
+/// * `content` - The content of the file
+///
+/// # Returns the root object with all keys of the file
+pub fn parse_vdf(content: &str) -> Vdf {
+    let tokens = vdf_tokens(content);
+    let mut position = 0;
+    parse_vdf_object(&tokens, &mut position)
+}
+
+/// A token of the KeyValues format
+#[derive(Debug, Clone, PartialEq)]
+enum VdfToken {
+    Text(String),
+    Open,
+    Close,
+}
+
+/// Splits the content of a KeyValues file into quoted texts and braces. Comments are skipped.
+fn vdf_tokens(content: &str) -> Vec<VdfToken> {
+    let mut tokens = Vec::new();
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => tokens.push(VdfToken::Open),
+            '}' => tokens.push(VdfToken::Close),
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.next().is_some_and(|c| c != '\n') {}
+            }
+            '"' => {
+                let mut text = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some('n') => text.push('\n'),
+                            Some('t') => text.push('\t'),
+                            Some(escaped) => text.push(escaped),
+                            None => break,
+                        },
+                        _ => text.push(c),
+                    }
+                }
+                tokens.push(VdfToken::Text(text));
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+/// Parses key value pairs until the object is closed or the tokens end
+fn parse_vdf_object(tokens: &[VdfToken], position: &mut usize) -> Vdf {
+    let mut children = HashMap::new();
+
+    while *position < tokens.len() {
+        let key = match &tokens[*position] {
+            VdfToken::Text(key) => key.clone(),
+            VdfToken::Close => {
+                *position += 1;
+                break;
+            }
+            VdfToken::Open => {
+                *position += 1;
+                continue;
+            }
+        };
+        *position += 1;
+
+        match tokens.get(*position) {
+            Some(VdfToken::Text(value)) => {
+                children.insert(key, Vdf::Value(value.clone()));
+                *position += 1;
+            }
+            Some(VdfToken::Open) => {
+                *position += 1;
+                children.insert(key, parse_vdf_object(tokens, position));
+            }
+            _ => {}
+        }
+    }
+    Vdf::Object(children)
+}
+
+/// Gets all Steam libraries of a Steam installation out of its libraryfolders.vdf
+///
+/// * `steam_path` - The folder Steam is installed in
+///
+/// # Returns the folders of the libraries, starting with the Steam folder itself
+pub fn steam_libraries(steam_path: &Path) -> Vec<PathBuf> {
+    let mut libraries = vec![steam_path.to_path_buf()];
+    let content = match fs::read_to_string(steam_path.join("steamapps").join("libraryfolders.vdf")) {
+        Ok(content) => content,
+        Err(_) => return libraries,
+    };
+
+    let vdf = parse_vdf(&content);
+    let folders = match vdf.get("libraryfolders").or_else(|| vdf.get("LibraryFolders")) {
+        Some(Vdf::Object(folders)) => folders,
+        _ => return libraries,
+    };
+
+    // Newer files contain an object with the path for every library, older ones only the path
+    let mut entries: Vec<(&String, &Vdf)> = folders.iter().collect();
+    entries.sort_by_key(|(key, _)| key.parse::<u32>().unwrap_or(u32::MAX));
+    for (_, folder) in entries {
+        let path = match folder {
+            Vdf::Object(_) => folder.get("path").and_then(Vdf::as_str),
+            Vdf::Value(path) => Some(path.as_str()),
+        };
+        if let Some(path) = path {
+            let path = PathBuf::from(path);
+            if !libraries.contains(&path) && path.is_absolute() {
+                libraries.push(path);
+            }
+        }
+    }
+    libraries
+}
+
+/// Gets the game folder inside of a Steam library by its appmanifest_413150.acf
+///
+/// * `library` - The folder of the Steam library
+///
+/// # Returns the game folder or None if the game isn't installed in the library
+pub fn steam_game_path(library: &Path) -> Option<PathBuf> {
+    let steamapps = library.join("steamapps");
+    let manifest = fs::read_to_string(steamapps.join(format!("appmanifest_{}.acf", STEAM_APP_ID))).ok()?;
+    let install_dir = parse_vdf(&manifest)
+        .get("AppState")?
+        .get("installdir")?
+        .as_str()?
+        .to_string();
+
+    let path = steamapps.join("common").join(install_dir);
+    // On macOS the game is an app bundle
+    let bundle = path.join("Contents").join("MacOS");
+    Some(if bundle.exists() { bundle } else { path })
+}
+
+/// Gets the folders Steam may be installed in
+fn steam_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+
+    #[cfg(target_os = "windows")]
+    {
+        let keys = [
+            (HKEY_CURRENT_USER, "Software\\Valve\\Steam", "SteamPath"),
+            (HKEY_LOCAL_MACHINE, "SOFTWARE\\WOW6432Node\\Valve\\Steam", "InstallPath"),
+            (HKEY_LOCAL_MACHINE, "SOFTWARE\\Valve\\Steam", "InstallPath"),
+        ];
+        for (hive, key, value) in keys {
+            if let Ok(path) = RegKey::predef(hive).open_subkey(key).and_then(|key| key.get_value::<String, _>(value)) {
+                roots.push(PathBuf::from(path));
+            }
+        }
+        roots.push(PathBuf::from("C:\\Program Files (x86)\\Steam"));
+    }
+
+    if let Some(home) = dirs::home_dir() {
+        #[cfg(target_os = "linux")]
+        {
+            roots.push(home.join(".steam").join("steam"));
+            roots.push(home.join(".local").join("share").join("Steam"));
+            // Flatpak and Snap keep Steam inside of their sandbox
+            roots.push(home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"));
+            roots.push(home.join("snap/steam/common/.local/share/Steam"));
+        }
+        #[cfg(target_os = "macos")]
+        roots.push(home.join("Library").join("Application Support").join("Steam"));
+        #[cfg(target_os = "windows")]
+        let _ = home;
+    }
+    roots
+}
+
+/// Gets the folders GOG and the Xbox app may have installed the game in
+fn other_candidates() -> Vec<(PathBuf, GameSource)> {
+    let mut candidates = Vec::new();
+
+    #[cfg(target_os = "windows")]
+    {
+        for key in [
+            format!("SOFTWARE\\WOW6432Node\\GOG.com\\Games\\{}", GOG_PRODUCT_ID),
+            format!("SOFTWARE\\GOG.com\\Games\\{}", GOG_PRODUCT_ID),
+        ] {
+            if let Ok(path) = RegKey::predef(HKEY_LOCAL_MACHINE)
+                .open_subkey(&key)
+                .and_then(|key| key.get_value::<String, _>("path"))
+            {
+                candidates.push((PathBuf::from(path), GameSource::Gog));
+            }
+        }
+        candidates.push((PathBuf::from("C:\\GOG Games\\Stardew Valley"), GameSource::Gog));
+        candidates.push((
+            PathBuf::from("C:\\Program Files (x86)\\GOG Galaxy\\Games\\Stardew Valley"),
+            GameSource::Gog,
+        ));
+        candidates.push((PathBuf::from("C:\\XboxGames\\Stardew Valley\\Content"), GameSource::Xbox));
+        candidates.push((
+            PathBuf::from("C:\\Program Files\\ModifiableWindowsApps\\Stardew Valley"),
+            GameSource::Xbox,
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    if let Some(home) = dirs::home_dir() {
+        candidates.push((home.join("GOG Games").join("Stardew Valley").join("game"), GameSource::Gog));
+    }
+
+    #[cfg(target_os = "macos")]
+    candidates.push((PathBuf::from("/Applications/Stardew Valley.app/Contents/MacOS"), GameSource::Gog));
+
+    candidates
+}
+
+/// Searches all known places for Stardew Valley installations
+///
+/// # Returns every folder that contains the game, each folder only once
+pub fn detect() -> Vec<GameCandidate> {
+    let mut paths: Vec<(PathBuf, GameSource)> = Vec::new();
+    for root in steam_roots().iter().filter(|root| root.exists()) {
+        for library in steam_libraries(root) {
+            if let Some(path) = steam_game_path(&library) {
+                paths.push((path, GameSource::Steam));
+            }
+        }
+    }
+    paths.extend(other_candidates());
+
+    let mut seen = Vec::new();
+    let mut candidates = Vec::new();
+    for (path, source) in paths {
+        // ~/.steam/steam is usually a link to ~/.local/share/Steam
+        let resolved = fs::canonicalize(&path).unwrap_or(path.clone());
+        if seen.contains(&resolved) {
+            continue;
+        }
+        seen.push(resolved);
+
+        let candidate = GameCandidate::new(&path, source);
+        if candidate.valid {
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+/// Checks that a folder contains Stardew Valley
+///
+/// * `path` - The folder to check
+///
+/// # Returns the checked folder or an error that tells which files are missing
+pub fn validate(path: &Path) -> Result<GameCandidate, JunimoError> {
+    let candidate = GameCandidate::new(path, GameSource::Manual);
+    if !candidate.valid {
+        return Err(JunimoError::NotFound(format!(
+            "Stardew Valley ({}) in {}",
+            GAME_FILES.join(" or "),
+            path.display()
+        )));
+    }
+    Ok(candidate)
+}
+
+/// Tauri command to find all Stardew Valley installations
+#[command]
+pub fn detect_games() -> Result<Vec<GameCandidate>, JunimoError> {
+    Ok(detect())
+}
+
+/// Tauri command to check a game folder chosen by the user
+///
+/// * `path` - The chosen folder
+#[command]
+pub fn validate_game_path(path: String) -> Result<GameCandidate, JunimoError> {
+    validate(Path::new(&path))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_vdf() {
+        let vdf = parse_vdf(
+            "\"libraryfolders\"\n{\n\t// comment\n\t\"0\"\n\t{\n\t\t\"path\"\t\t\"C:\\\\Program Files (x86)\\\\Steam\"\n\t\t\"apps\"\n\t\t{\n\t\t\t\"413150\"\t\t\"123\"\n\t\t}\n\t}\n}",
+        );
+        let library = vdf.get("libraryfolders").unwrap().get("0").unwrap();
+        assert_eq!(library.get("path").unwrap().as_str(), Some("C:\\Program Files (x86)\\Steam"));
+        assert_eq!(library.get("apps").unwrap().get("413150").unwrap().as_str(), Some("123"));
+        assert_eq!(vdf.get("missing"), None);
+    }
+
+    #[test]
+    fn test_steam_detection() {
+        let tmp_dir = tempdir().unwrap();
+        let steam = tmp_dir.path().join("Steam");
+        let library = tmp_dir.path().join("Library");
+        fs::create_dir_all(steam.join("steamapps")).unwrap();
+        fs::create_dir_all(library.join("steamapps").join("common").join("Stardew Valley")).unwrap();
+
+        // An old library entry and a new one that point to the same library
+        fs::write(
+            steam.join("steamapps").join("libraryfolders.vdf"),
+            format!(
+                "\"LibraryFolders\"\n{{\n\t\"TimeNextStatsReport\"\t\"0\"\n\t\"1\"\t\"{0}\"\n\t\"2\"\n\t{{\n\t\t\"path\"\t\"{0}\"\n\t}}\n}}",
+                library.to_string_lossy().replace('\\', "\\\\")
+            ),
+        )
+        .unwrap();
+        fs::write(
+            library.join("steamapps").join("appmanifest_413150.acf"),
+            "\"AppState\"\n{\n\t\"appid\"\t\t\"413150\"\n\t\"installdir\"\t\t\"Stardew Valley\"\n}",
+        )
+        .unwrap();
+
+        let libraries = steam_libraries(&steam);
+        assert_eq!(libraries, vec![steam.clone(), library.clone()]);
+        assert_eq!(steam_game_path(&steam), None);
+
+        let game_path = steam_game_path(&library).unwrap();
+        assert_eq!(game_path, library.join("steamapps").join("common").join("Stardew Valley"));
+
+        // The folder only becomes valid with the game in it
+        assert!(validate(&game_path).is_err());
+        fs::write(game_path.join("Stardew Valley.dll"), "not a real dll").unwrap();
+        let candidate = validate(&game_path).unwrap();
+        assert!(candidate.valid);
+        assert_eq!(candidate.version, None);
+    }
+}
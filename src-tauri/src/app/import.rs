@@ -4,46 +4,50 @@ use std::{fs, thread};
 use rfd::FileDialog;
 use tauri::{command, Manager, WebviewUrl, Window};
 
-use crate::app::console;
+use crate::app::app_state::AppState;
+use crate::app::error::JunimoError;
+use crate::app::models::mod_info::ModInfo;
+use crate::app::profiles::Profile;
+use crate::app::utility::zips::ImportedMetadata;
 use crate::app::utility::{paths, zips};
+use crate::app::{config, console, mod_installation};
 
 /// Opens the import window
 ///
 /// * `handle` - The app handle of the Tauri application
 #[command]
-pub async fn open_import(handle: tauri::AppHandle) {
+pub async fn open_import(handle: tauri::AppHandle) -> Result<(), JunimoError> {
     #[cfg(target_os = "windows")]
     tauri::WebviewWindowBuilder::new(&handle, "Importer", WebviewUrl::App("/importer".into()))
         .title("Import")
         .min_inner_size(600.0, 350.0)
         .inner_size(600.0, 350.0)
         .transparent(true)
-        .build()
-        .unwrap();
+        .build()?;
 
     #[cfg(target_os = "unix")]
     tauri::WebviewWindowBuilder::new(&handle, "Importer", WebviewUrl::App("/importer".into()))
         .title("Import")
         .min_inner_size(600.0, 350.0)
         .inner_size(600.0, 350.0)
-        .build()
-        .unwrap();
+        .build()?;
+    Ok(())
 }
 
 /// Opens a file dialog to select a directory to import
 ///
 /// # Returns the path of the selected directory, else an empty string
 #[command]
-pub fn select_import_dir() -> String {
+pub fn select_import_dir() -> Result<String, JunimoError> {
     let file = FileDialog::new()
         .add_filter("zip", &["zip"])
         .set_directory("/")
         .pick_file();
 
     if let Some(path) = file {
-        path.to_string_lossy().to_string()
+        Ok(path.to_string_lossy().to_string())
     } else {
-        "".to_string()
+        Ok("".to_string())
     }
 }
 
@@ -54,32 +58,15 @@ pub fn select_import_dir() -> String {
 /// * `path` - The path of the zip file to import
 /// * `all` - Whether the import is an "all profiles" import
 #[command]
-pub fn import_profile(window: Window, handle: tauri::AppHandle, path: &str, all: bool) {
+pub fn import_profile(window: Window, handle: tauri::AppHandle, path: &str, all: bool) -> Result<(), JunimoError> {
     let path = path.clone().to_string();
     let all = all.clone();
     let window_clone = window.clone();
 
     // Spawn a new thread to import the profile in the background
     thread::spawn(move || {
-        let path_as_buff = Path::new(&path);
-        let file = fs::File::open(&path_as_buff).unwrap();
-        let mut file_name = path_as_buff
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
-        file_name = file_name.replace(".zip", "");
-
-        let zip_archive = zip::ZipArchive::new(file).unwrap();
-        let import_result = zips::import_zip(
-            zip_archive,
-            &paths::appdata_path(),
-            &paths::temp_path(),
-            all,
-        );
-        match import_result {
-            Ok(_) => {
+        match import(&handle, &path, all) {
+            Ok(file_name) => {
                 console::add_line(
                     &handle,
                     format!(
@@ -88,18 +75,66 @@ pub fn import_profile(window: Window, handle: tauri::AppHandle, path: &str, all:
                     ),
                 );
             }
-            Err(e) => {
-                console::add_line(
-                    &handle,
-                    format!(
-                        "<span class=\"console-green\">[Junimo] Failed to import profile {}: {}</span>",
-                        file_name,
-                        e
-                    ),
-                );
-            }
+            Err(e) => console::error(&handle, &e),
         }
-        &handle.emit("reload", false).unwrap();
-        window_clone.close().unwrap();
+        let _ = handle.emit("reload", false);
+        let _ = window_clone.close();
     });
+    Ok(())
+}
+
+/// Imports the profiles and mods of an exported zip file
+///
+/// * `handle` - The app handle of the Tauri application
+/// * `path` - The path of the zip file to import
+/// * `all` - Whether the import is an "all profiles" import
+///
+/// # Returns the name of the imported file
+fn import(handle: &tauri::AppHandle, path: &str, all: bool) -> Result<String, JunimoError> {
+    let path_as_buff = Path::new(path);
+    let file = fs::File::open(&path_as_buff)?;
+    let file_name = path_as_buff
+        .file_name()
+        .map(|name| name.to_string_lossy().replace(".zip", ""))
+        .unwrap_or_default();
+
+    let zip_archive = zip::ZipArchive::new(file)?;
+    let metadata = zips::import_zip(
+        zip_archive,
+        &paths::appdata_path(),
+        &paths::temp_path(),
+        all,
+        config::extraction_limits(),
+    )?;
+
+    let store = handle.state::<AppState>().store.clone();
+    store.update(handle, |mods, profiles| apply_import(metadata, all, mods, profiles))?;
+    Ok(file_name)
+}
+
+/// Adds the imported profiles and mod infos to the installed mods and the profiles
+///
+/// * `metadata` - The profiles and mod infos of the imported zip file
+/// * `all` - Whether the import replaces all existing profiles
+/// * `mods` - The installed mods
+/// * `profiles` - The existing profiles
+fn apply_import(metadata: ImportedMetadata, all: bool, mods: &mut Vec<ModInfo>, profiles: &mut Vec<Profile>) {
+    if all {
+        if !metadata.mods.is_empty() {
+            *mods = metadata.mods;
+        }
+        if !metadata.profiles.is_empty() {
+            *profiles = metadata.profiles;
+        }
+        return;
+    }
+
+    // Adds the infos of the profile's mods to the mod registry
+    for mod_info in metadata.mods.iter() {
+        mod_installation::insert_mod_info(mods, mod_info);
+    }
+    if let Some(loaded_profile) = metadata.profiles.into_iter().next() {
+        profiles.retain(|profile| profile.name != loaded_profile.name);
+        profiles.push(loaded_profile);
+    }
 }
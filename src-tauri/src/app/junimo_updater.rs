@@ -1,7 +1,9 @@
 use tauri::{command, WebviewUrl};
 
+use crate::app::error::JunimoError;
+
 #[command]
-pub async fn open_updater(handle: tauri::AppHandle) {
+pub async fn open_updater(handle: tauri::AppHandle) -> Result<(), JunimoError> {
     #[cfg(target_os = "windows")]
     tauri::WebviewWindowBuilder::new(&handle, "Updater", WebviewUrl::App("/updater".into()))
         .title("Updater")
@@ -9,8 +11,7 @@ pub async fn open_updater(handle: tauri::AppHandle) {
         .maximizable(false)
         .inner_size(700.0, 350.0)
         .transparent(true)
-        .build()
-        .unwrap();
+        .build()?;
 
     #[cfg(target_os = "unix")]
     tauri::WebviewWindowBuilder::new(&handle, "Updater", WebviewUrl::App("/updater".into()))
@@ -18,6 +19,6 @@ pub async fn open_updater(handle: tauri::AppHandle) {
         .resizable(false)
         .maximizable(false)
         .inner_size(600.0, 350.0)
-        .build()
-        .unwrap();
+        .build()?;
+    Ok(())
 }
\ No newline at end of file
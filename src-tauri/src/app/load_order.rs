@@ -0,0 +1,445 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+use tauri::{command, State};
+
+use crate::app::app_state::AppState;
+use crate::app::error::JunimoError;
+use crate::app::models::mod_info::ModInfo;
+
+/// The kind of problem SMAPI would run into while loading the mods
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum DiagnosticKind {
+    SelfReference,
+    Cycle,
+    DuplicateId,
+    /// The mod needs a mod that SMAPI skips, so it is skipped as well
+    SkippedDependency,
+}
+
+/// A problem that makes SMAPI skip the affected mods
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LoadOrderDiagnostic {
+    pub kind: DiagnosticKind,
+    pub mods: Vec<String>,
+    pub message: String,
+}
+
+/// The order SMAPI loads the mods of a profile in, together with all problems that were found
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LoadOrder {
+    /// Every mod by name. The mods SMAPI skips come last, each of them has a diagnostic.
+    pub order: Vec<String>,
+    pub diagnostics: Vec<LoadOrderDiagnostic>,
+}
+
+/// Tauri command to get the load order of a profile
+///
+/// * `name` - The name of the profile. If None, the current profile is used
+///
+/// # Returns the load order and the diagnostics of the profile
+#[command]
+pub fn get_load_order(app_state: State<'_, AppState>, name: Option<String>) -> Result<LoadOrder, JunimoError> {
+    let profiles = app_state.store.profiles();
+    let registry = app_state.store.mods();
+    let profile = profiles.into_iter().find(|profile| match &name {
+        Some(name) => &profile.name == name,
+        None => profile.currently,
+    });
+
+    match profile {
+        Some(profile) => Ok(analyze(&profile.resolve_mods(&registry))),
+        None => Ok(LoadOrder {
+            order: vec![],
+            diagnostics: vec![],
+        }),
+    }
+}
+
+/// Sorts mods so every mod loads after its dependencies and reports self-references,
+/// circular dependencies, unique ids that are used by more than one mod and mods that need a skipped mod.
+/// Mods without a dependency between them are sorted by name.
+///
+/// * `mods` - The mods that are used together
+///
+/// # Returns the load order by mod name and all diagnostics
+pub fn analyze(mods: &Vec<ModInfo>) -> LoadOrder {
+    let mut diagnostics: Vec<LoadOrderDiagnostic> = Vec::new();
+
+    let mut by_key: HashMap<String, Vec<&ModInfo>> = HashMap::new();
+    for mod_info in mods {
+        if let Some(unique_id) = &mod_info.unique_id {
+            by_key.entry(unique_id.to_lowercase()).or_default().push(mod_info);
+        }
+    }
+
+    // SMAPI refuses to load any mod of a duplicated id, so none of them becomes part of the graph
+    let mut duplicate_ids: Vec<(&String, &Vec<&ModInfo>)> =
+        by_key.iter().filter(|(_, mods)| mods.len() > 1).collect();
+    duplicate_ids.sort_by(|a, b| a.0.cmp(b.0));
+    let mut skipped: Vec<&ModInfo> = Vec::new();
+    for (unique_id, duplicates) in duplicate_ids {
+        let names: Vec<String> = duplicates.iter().map(|mod_info| mod_info.name.clone()).collect();
+        diagnostics.push(LoadOrderDiagnostic {
+            kind: DiagnosticKind::DuplicateId,
+            message: format!(
+                "{} all use the unique id {}, so SMAPI will skip them",
+                names.join(", "),
+                unique_id
+            ),
+            mods: names,
+        });
+        skipped.extend(duplicates.iter().copied());
+    }
+
+    let is_duplicate = |key: &String| by_key.get(key).map_or(false, |mods| mods.len() > 1);
+    let nodes: Vec<&ModInfo> = mods
+        .iter()
+        .filter(|mod_info| {
+            mod_info.unique_id.as_ref().map_or(true, |id| !is_duplicate(&id.to_lowercase()))
+        })
+        .collect();
+    let by_id: HashMap<String, usize> = nodes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, mod_info)| Some((mod_info.unique_id.as_ref()?.to_lowercase(), index)))
+        .collect();
+
+    // requires[i] contains every installed mod that mod i needs to load after, required[i] only the ones
+    // it can't load without. Optional dependencies change the order, but SMAPI loads a mod without them.
+    let mut requires: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    let mut required_by: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    for (index, mod_info) in nodes.iter().enumerate() {
+        let own_id = mod_info.unique_id.as_ref().map(|id| id.to_lowercase());
+        for dependency in mod_info.dependencies.iter().flatten() {
+            let dependency_id = dependency.unique_id.to_lowercase();
+            if own_id.as_ref() == Some(&dependency_id) {
+                diagnostics.push(LoadOrderDiagnostic {
+                    kind: DiagnosticKind::SelfReference,
+                    mods: vec![mod_info.name.clone()],
+                    message: format!("{} lists itself as a dependency", &mod_info.name),
+                });
+                continue;
+            }
+            if let Some(&required) = by_id.get(&dependency_id) {
+                if !requires[index].contains(&required) {
+                    requires[index].push(required);
+                }
+                if dependency.is_required.unwrap_or(true) && !required_by[index].contains(&required) {
+                    required_by[index].push(required);
+                }
+            }
+        }
+    }
+
+    let (order, remaining) = sort_topologically(&nodes, &requires);
+
+    let mut is_skipped: Vec<bool> = vec![false; nodes.len()];
+    for cycle in find_cycles(&nodes, &requires, &remaining) {
+        let names: Vec<String> = cycle.iter().map(|&i| nodes[i].name.clone()).collect();
+        let mut path = names.clone();
+        path.push(names[0].clone());
+        diagnostics.push(LoadOrderDiagnostic {
+            kind: DiagnosticKind::Cycle,
+            message: format!("Circular dependency: {}", path.join(" -> ")),
+            mods: names,
+        });
+        for index in cycle {
+            is_skipped[index] = true;
+        }
+    }
+
+    // A mod that can't load without a skipped mod is skipped as well, until no further mod is skipped
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &index in order.iter().chain(remaining.iter()) {
+            if is_skipped[index] {
+                continue;
+            }
+            let skipped_dependency = nodes[index]
+                .dependencies
+                .iter()
+                .flatten()
+                .find(|dependency| {
+                    dependency.is_required.unwrap_or(true)
+                        && is_duplicate(&dependency.unique_id.to_lowercase())
+                })
+                .map(|dependency| dependency.unique_id.clone())
+                .or_else(|| {
+                    required_by[index]
+                        .iter()
+                        .find(|&&required| is_skipped[required])
+                        .map(|&required| nodes[required].name.clone())
+                });
+            if let Some(dependency) = skipped_dependency {
+                is_skipped[index] = true;
+                changed = true;
+                diagnostics.push(LoadOrderDiagnostic {
+                    kind: DiagnosticKind::SkippedDependency,
+                    mods: vec![nodes[index].name.clone()],
+                    message: format!(
+                        "{} needs {}, which SMAPI skips, so it is skipped as well",
+                        nodes[index].name, dependency
+                    ),
+                });
+            }
+        }
+    }
+
+    // The loaded mods are sorted again without the skipped ones, so mods that only optionally
+    // depend on a skipped mod get their place as well
+    let loaded_requires: Vec<Vec<usize>> = requires
+        .iter()
+        .enumerate()
+        .map(|(index, required)| {
+            if is_skipped[index] {
+                return Vec::new();
+            }
+            required.iter().copied().filter(|&i| !is_skipped[i]).collect()
+        })
+        .collect();
+    let (loaded, _) = sort_topologically(&nodes, &loaded_requires);
+
+    // Every skipped mod is appended, so the order still contains every mod
+    let mut names: Vec<String> = loaded
+        .iter()
+        .filter(|&&i| !is_skipped[i])
+        .map(|&i| nodes[i].name.clone())
+        .collect();
+    skipped.extend((0..nodes.len()).filter(|&i| is_skipped[i]).map(|i| nodes[i]));
+    skipped.sort_by_key(|mod_info| mod_info.name.to_lowercase());
+    names.extend(skipped.iter().map(|mod_info| mod_info.name.clone()));
+
+    LoadOrder {
+        order: names,
+        diagnostics,
+    }
+}
+
+/// Sorts the nodes with Kahn's algorithm, picking the alphabetically first mod whenever there's a choice
+///
+/// # Returns the sorted nodes and the nodes that couldn't be sorted because they are part of a cycle
+fn sort_topologically(nodes: &Vec<&ModInfo>, requires: &Vec<Vec<usize>>) -> (Vec<usize>, Vec<usize>) {
+    let mut pending: Vec<usize> = requires.iter().map(|required| required.len()).collect();
+    let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    for (index, required) in requires.iter().enumerate() {
+        for &dependency in required {
+            dependents[dependency].push(index);
+        }
+    }
+
+    let sort_key = |index: usize| Reverse((nodes[index].name.to_lowercase(), index));
+    let mut ready: BinaryHeap<Reverse<(String, usize)>> = (0..nodes.len())
+        .filter(|&index| pending[index] == 0)
+        .map(sort_key)
+        .collect();
+
+    let mut order: Vec<usize> = Vec::new();
+    while let Some(Reverse((_, index))) = ready.pop() {
+        order.push(index);
+        for &dependent in &dependents[index] {
+            pending[dependent] -= 1;
+            if pending[dependent] == 0 {
+                ready.push(sort_key(dependent));
+            }
+        }
+    }
+
+    let mut remaining: Vec<usize> = (0..nodes.len()).filter(|&i| pending[i] > 0).collect();
+    remaining.sort_by_key(|&index| nodes[index].name.to_lowercase());
+    (order, remaining)
+}
+
+/// Finds one cycle for every group of mods that depend on each other in a circle.
+/// Mods that only depend on a cycle without being part of it are ignored.
+///
+/// * `nodes` - The mods of the graph
+/// * `requires` - The dependencies of every mod
+/// * `remaining` - The mods that couldn't be sorted
+///
+/// # Returns the cycles, each starting with its alphabetically first mod
+fn find_cycles(nodes: &Vec<&ModInfo>, requires: &Vec<Vec<usize>>, remaining: &Vec<usize>) -> Vec<Vec<usize>> {
+    let mut cycles: Vec<Vec<usize>> = Vec::new();
+    let mut covered: Vec<bool> = vec![false; nodes.len()];
+
+    for &start in remaining {
+        if covered[start] {
+            continue;
+        }
+
+        // Breadth first search for the shortest way back to the start
+        let mut parent: HashMap<usize, usize> = HashMap::new();
+        let mut queue: VecDeque<usize> = VecDeque::from([start]);
+        let mut last: Option<usize> = None;
+        while let Some(current) = queue.pop_front() {
+            if requires[current].contains(&start) {
+                last = Some(current);
+                break;
+            }
+            for &next in &requires[current] {
+                if next != start && !parent.contains_key(&next) {
+                    parent.insert(next, current);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        let mut current = match last {
+            Some(last) => last,
+            None => continue,
+        };
+        let mut cycle = vec![current];
+        while current != start {
+            current = parent[&current];
+            cycle.push(current);
+        }
+        cycle.reverse();
+
+        for &index in &cycle {
+            covered[index] = true;
+        }
+        cycles.push(cycle);
+    }
+
+    cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::mods::Dependency;
+
+    use super::*;
+
+    fn dependencies(required: Vec<&str>, optional: Vec<&str>) -> Vec<Dependency> {
+        required
+            .into_iter()
+            .map(|unique_id| (unique_id, None))
+            .chain(optional.into_iter().map(|unique_id| (unique_id, Some(false))))
+            .map(|(unique_id, is_required)| Dependency {
+                unique_id: unique_id.to_string(),
+                minimum_version: None,
+                is_required,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_load_order() {
+        let mods = vec![
+            ModInfo::for_test("Alpha Pack", Some("Test.Alpha"), "1.0.0")
+                .with_dependencies(dependencies(vec!["test.framework", "Test.Missing"], vec![])),
+            ModInfo::for_test("Beta", Some("Test.Beta"), "1.0.0"),
+            ModInfo::for_test("Framework", Some("Test.Framework"), "1.0.0")
+                .with_dependencies(dependencies(vec!["Test.Core"], vec![])),
+            ModInfo::for_test("Core", Some("Test.Core"), "1.0.0"),
+        ];
+
+        let load_order = analyze(&mods);
+        assert_eq!(load_order.order, vec!["Beta", "Core", "Framework", "Alpha Pack"]);
+        assert!(load_order.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let mods = vec![
+            ModInfo::for_test("First", Some("Test.First"), "1.0.0")
+                .with_dependencies(dependencies(vec!["Test.Second"], vec![])),
+            ModInfo::for_test("Second", Some("Test.Second"), "1.0.0")
+                .with_dependencies(dependencies(vec!["Test.Third"], vec![])),
+            ModInfo::for_test("Third", Some("Test.Third"), "1.0.0")
+                .with_dependencies(dependencies(vec!["Test.First"], vec![])),
+            ModInfo::for_test("Dependent", Some("Test.Dependent"), "1.0.0")
+                .with_dependencies(dependencies(vec!["Test.Second"], vec![])),
+            ModInfo::for_test("After Dependent", Some("Test.AfterDependent"), "1.0.0")
+                .with_dependencies(dependencies(vec!["Test.Dependent"], vec![])),
+            ModInfo::for_test("Selfish", Some("Test.Selfish"), "1.0.0")
+                .with_dependencies(dependencies(vec!["test.selfish"], vec![])),
+            ModInfo::for_test("Copy", Some("Test.Copy"), "1.0.0"),
+            ModInfo::for_test("Other Copy", Some("TEST.COPY"), "1.0.0"),
+            ModInfo::for_test("Needs Copy", Some("Test.NeedsCopy"), "1.0.0")
+                .with_dependencies(dependencies(vec!["Test.Copy"], vec![])),
+        ];
+
+        let load_order = analyze(&mods);
+        assert_eq!(
+            load_order.order,
+            vec![
+                "Selfish",
+                "After Dependent",
+                "Copy",
+                "Dependent",
+                "First",
+                "Needs Copy",
+                "Other Copy",
+                "Second",
+                "Third"
+            ]
+        );
+
+        let kinds: Vec<DiagnosticKind> = load_order
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.kind.clone())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DiagnosticKind::DuplicateId,
+                DiagnosticKind::SelfReference,
+                DiagnosticKind::Cycle,
+                DiagnosticKind::SkippedDependency,
+                DiagnosticKind::SkippedDependency,
+                DiagnosticKind::SkippedDependency
+            ]
+        );
+        assert_eq!(load_order.diagnostics[0].mods, vec!["Copy", "Other Copy"]);
+        assert_eq!(
+            load_order.diagnostics[2].message,
+            "Circular dependency: First -> Second -> Third -> First"
+        );
+
+        // Every mod SMAPI skips has a diagnostic
+        let skipped: Vec<String> = load_order
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.kind != DiagnosticKind::SelfReference)
+            .flat_map(|diagnostic| diagnostic.mods.clone())
+            .collect();
+        assert_eq!(skipped.len(), load_order.order.len() - 1);
+        assert_eq!(
+            load_order.diagnostics[3].message,
+            "Needs Copy needs Test.Copy, which SMAPI skips, so it is skipped as well"
+        );
+    }
+
+    #[test]
+    fn test_optional_dependencies() {
+        let mods = vec![
+            ModInfo::for_test("Addon", Some("Test.Addon"), "1.0.0")
+                .with_dependencies(dependencies(vec![], vec!["Test.Copy", "Test.First"])),
+            ModInfo::for_test("Copy", Some("Test.Copy"), "1.0.0"),
+            ModInfo::for_test("Other Copy", Some("Test.Copy"), "1.0.0"),
+            ModInfo::for_test("First", Some("Test.First"), "1.0.0")
+                .with_dependencies(dependencies(vec!["Test.Second"], vec![])),
+            ModInfo::for_test("Second", Some("Test.Second"), "1.0.0")
+                .with_dependencies(dependencies(vec![], vec!["Test.First"])),
+            ModInfo::for_test("Late", Some("Test.Late"), "1.0.0")
+                .with_dependencies(dependencies(vec![], vec!["Test.Addon"])),
+        ];
+
+        // SMAPI still loads mods whose optional dependencies are skipped
+        let load_order = analyze(&mods);
+        assert_eq!(
+            load_order.order,
+            vec!["Addon", "Late", "Copy", "First", "Other Copy", "Second"]
+        );
+        let kinds: Vec<DiagnosticKind> = load_order
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.kind.clone())
+            .collect();
+        assert_eq!(kinds, vec![DiagnosticKind::DuplicateId, DiagnosticKind::Cycle]);
+    }
+}
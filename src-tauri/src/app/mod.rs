@@ -1,16 +1,29 @@
+pub mod activation;
 pub mod api;
 pub mod app_state;
 pub mod config;
+pub mod config_snapshots;
 pub mod console;
+pub mod dependencies;
+pub mod error;
 pub mod export;
 pub mod game;
+pub mod game_detection;
 pub mod import;
-mod mod_installation;
+pub mod load_order;
+pub mod mod_adoption;
+pub mod mod_config_editor;
+pub mod mod_identity;
+pub mod mod_installation;
+mod mod_layout;
+pub mod mod_store;
 mod models;
 pub mod mods;
 pub mod profiles;
+pub mod state_store;
 pub mod user;
 pub mod util;
 pub mod utility;
 pub mod smapi;
+pub mod smapi_manager;
 pub mod junimo_updater;
@@ -0,0 +1,349 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{command, AppHandle, Manager};
+use walkdir::WalkDir;
+
+use crate::app::api::compatibility;
+use crate::app::app_state::AppState;
+use crate::app::error::JunimoError;
+use crate::app::models::mod_info::ModInfo;
+use crate::app::mods::Manifest;
+use crate::app::profiles::{ModReference, Profile};
+use crate::app::utility::paths;
+use crate::app::{config, console, mod_installation, mod_store};
+
+/// A mod folder that was found while scanning an existing mods folder
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FoundMod {
+    pub path: PathBuf,
+    pub manifest: Manifest,
+    pub group: Option<String>,
+}
+
+/// Tauri command to adopt all mods of an existing Stardew Valley mods folder into Junimo
+///
+/// * `app_handle` - The app handle
+/// * `path` - The folder to scan. If None, the Mods folder of the game is used
+/// * `link` - Whether the mods should be linked instead of copied
+/// * `profile_name` - If set, a new profile with exactly the adopted mods is created
+///
+/// # Returns all adopted mods
+#[command]
+pub async fn adopt_mods_folder(
+    app_handle: AppHandle,
+    path: Option<String>,
+    link: bool,
+    profile_name: Option<String>,
+) -> Result<Vec<ModInfo>, JunimoError> {
+    let source = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let game_path = paths::get_game_path();
+            if game_path == paths::appdata_path() {
+                return Err(JunimoError::GameNotFound);
+            }
+            game_path.join("Mods")
+        }
+    };
+
+    if !source.is_dir() {
+        return Err(JunimoError::NotFound(format!("The folder {}", source.display())));
+    }
+
+    console::add_line(
+        &app_handle,
+        format!(
+            "<span class=\"console-green\">[Junimo] Scanning {} for mods</span>",
+            source.display()
+        ),
+    );
+
+    let (found_mods, errors) = find_mods(&source);
+    for error in errors {
+        console::add_line(
+            &app_handle,
+            format!("<span class=\"console-red\">[Junimo] Skipped mod: {}</span>", error),
+        );
+    }
+
+    let store = app_handle.state::<AppState>().store.clone();
+    let registry = store.mods();
+    let mut adopted_mods: Vec<ModInfo> = Vec::new();
+    let mut references: Vec<ModReference> = Vec::new();
+    for found_mod in found_mods {
+        let mod_info = mod_installation::mod_info_from_manifest(found_mod.manifest, found_mod.group);
+        match adopt_mod_folder(&found_mod.path, &mod_info.folder_name(), &mod_info.version, link) {
+            Ok(true) => {
+                references.push(ModReference::new(&mod_info));
+                adopted_mods.push(mod_info);
+            }
+            Ok(false) => {
+                let (mod_info, reference) = installed_mod(&registry, mod_info);
+                references.push(reference);
+                adopted_mods.push(mod_info);
+            }
+            Err(e) => {
+                console::add_line(
+                    &app_handle,
+                    format!(
+                        "<span class=\"console-red\">[Junimo] Failed to adopt {}: {}</span>",
+                        &mod_info.name, e
+                    ),
+                );
+            }
+        }
+    }
+
+    // Check all adopted mods for compatibility in a single request
+    let config = config::load_config(paths::config_path());
+    if !adopted_mods.is_empty()
+        && (config.activate_broken.is_none() || config.activate_broken.unwrap())
+    {
+        match compatibility::get_compability(adopted_mods.clone()).await {
+            Some(compatibility) => adopted_mods = compatibility,
+            None => {}
+        }
+    }
+
+    store.update(&app_handle, |mods, profiles| {
+        for mod_info in adopted_mods.iter() {
+            mod_installation::insert_mod_info(mods, mod_info);
+        }
+
+        if let Some(profile_name) = profile_name {
+            profiles.retain(|profile| profile.name != profile_name);
+            profiles.push(Profile {
+                name: profile_name,
+                mods: references,
+                currently: false,
+                configs: None,
+                smapi_version: None,
+            });
+        }
+    })?;
+
+    console::add_line(
+        &app_handle,
+        format!(
+            "<span class=\"console-green\">[Junimo] Adopted {} mod(s)</span>",
+            adopted_mods.len()
+        ),
+    );
+    app_handle.emit("reload", true)?;
+
+    Ok(adopted_mods)
+}
+
+/// Finds every mod folder inside a folder. A mod folder is a folder that contains a manifest.json.
+/// Folders starting with a dot are ignored, just like SMAPI does.
+///
+/// * `source` - The folder to scan
+///
+/// # Returns all found mods and the errors of manifests that couldn't be read
+pub fn find_mods(source: &Path) -> (Vec<FoundMod>, Vec<JunimoError>) {
+    let mut found_mods: Vec<FoundMod> = Vec::new();
+    let mut errors: Vec<JunimoError> = Vec::new();
+
+    let mut it = WalkDir::new(source).min_depth(1).into_iter();
+    while let Some(entry) = it.next() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            it.skip_current_dir();
+            continue;
+        }
+
+        let manifest_path = entry.path().join("manifest.json");
+        if !manifest_path.exists() {
+            continue;
+        }
+
+        // Mods inside of a mod folder belong to the mod itself
+        it.skip_current_dir();
+
+        match mod_installation::get_manifest(&manifest_path) {
+            Ok(manifest) => {
+                // Mods that are nested in another folder are part of a group
+                let group = match entry.path().parent() {
+                    Some(parent) if parent != source => parent
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string()),
+                    _ => None,
+                };
+
+                found_mods.push(FoundMod {
+                    path: entry.path().to_path_buf(),
+                    manifest,
+                    group,
+                });
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+
+    (found_mods, errors)
+}
+
+/// Gets the registry entry of a mod whose version is already installed, together with its reference
+/// for the profile. The reference is pinned to the scanned version if another version is the installed one.
+///
+/// * `registry` - The installed mods
+/// * `mod_info` - The mod info of the scanned mod
+///
+/// # Returns the mod info and the reference of the mod
+fn installed_mod(registry: &Vec<ModInfo>, mod_info: ModInfo) -> (ModInfo, ModReference) {
+    match registry.iter().find(|installed| **installed == mod_info) {
+        Some(installed) => {
+            let mut reference = ModReference::new(installed);
+            if installed.version != mod_info.version {
+                reference.version = Some(mod_info.version.clone());
+            }
+            (installed.clone(), reference)
+        }
+        None => {
+            let reference = ModReference::new(&mod_info);
+            (mod_info, reference)
+        }
+    }
+}
+
+/// Copies or links a mod folder into Junimo's mods folder as an inactive mod.
+/// A version that is already installed is kept as it is.
+///
+/// * `source` - The mod folder to adopt
+/// * `folder_name` - The name of the mods folder in Junimo
+/// * `version` - The version of the mod, every version gets a folder of its own
+/// * `link` - Whether the folder should be linked instead of copied
+///
+/// # Returns whether the folder was copied or linked, false if the version was already installed
+fn adopt_mod_folder(source: &Path, folder_name: &str, version: &str, link: bool) -> Result<bool, String> {
+    let mod_path = paths::mod_path();
+    if mod_store::find_version(&mod_path, folder_name, version).is_some() {
+        return Ok(false);
+    }
+
+    let mod_dir = mod_store::mod_dir(&mod_path, folder_name);
+    fs::create_dir_all(&mod_dir).map_err(|e| e.to_string())?;
+    let destination = mod_dir.join(mod_store::version_folder(version));
+
+    if link {
+        #[cfg(unix)]
+        {
+            return std::os::unix::fs::symlink(source, &destination)
+                .map(|_| true)
+                .map_err(|e| e.to_string());
+        }
+
+        // Windows only allows symlinks with developer mode enabled, so fall back to copying
+        #[cfg(windows)]
+        {
+            if std::os::windows::fs::symlink_dir(source, &destination).is_ok() {
+                return Ok(true);
+            }
+        }
+    }
+
+    copy_dir(source, &destination).map(|_| true)
+}
+
+/// Recursively copies a folder
+///
+/// * `source` - The folder to copy
+/// * `destination` - The path of the copy
+///
+/// # Returns a Result with the success state
+fn copy_dir(source: &Path, destination: &Path) -> Result<(), String> {
+    for entry in WalkDir::new(source).into_iter().filter_map(|e| e.ok()) {
+        let relative = entry
+            .path()
+            .strip_prefix(source)
+            .map_err(|e| e.to_string())?;
+        let target = destination.join(relative);
+
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&target).map_err(|e| e.to_string())?;
+        } else {
+            fs::copy(entry.path(), &target).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn write_manifest(dir: &Path, name: &str, unique_id: &str) {
+        fs::create_dir_all(dir).unwrap();
+        let manifest = format!(
+            "{{\n  // Comments are allowed\n  \"Name\": \"{}\",\n  \"Author\": \"Test\",\n  \"Version\": \"1.0.0\",\n  \"UniqueID\": \"{}\"\n}}",
+            name, unique_id
+        );
+        fs::write(dir.join("manifest.json"), manifest).unwrap();
+    }
+
+    #[test]
+    fn test_find_mods() {
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path();
+
+        write_manifest(&root.join("Single"), "Single", "Test.Single");
+        write_manifest(&root.join("Group").join("[CP] First"), "First", "Test.First");
+        write_manifest(&root.join("Group").join("[CP] Second"), "Second", "Test.Second");
+        write_manifest(&root.join(".Disabled"), "Disabled", "Test.Disabled");
+        fs::create_dir_all(root.join("Broken")).unwrap();
+        fs::write(root.join("Broken").join("manifest.json"), "{ not json").unwrap();
+
+        let (mut found_mods, errors) = find_mods(root);
+        found_mods.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+
+        let names: Vec<String> = found_mods.iter().map(|m| m.manifest.name.clone()).collect();
+        assert_eq!(names, vec!["First", "Second", "Single"]);
+        assert_eq!(found_mods[0].group, Some("Group".to_string()));
+        assert_eq!(found_mods[2].group, None);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn test_copy_dir() {
+        let tmp_dir = tempdir().unwrap();
+        let source = tmp_dir.path().join("source");
+        write_manifest(&source.join("assets"), "Nested", "Test.Nested");
+
+        let destination = tmp_dir.path().join("destination");
+        copy_dir(&source, &destination).unwrap();
+
+        assert!(destination.join("assets").join("manifest.json").exists());
+    }
+
+    #[test]
+    fn test_installed_mod() {
+        let registry = vec![ModInfo::for_test("Installed", Some("Test.Installed"), "2.0.0")];
+
+        // An installed mod keeps its registry entry, an older version is pinned
+        let (mod_info, reference) =
+            installed_mod(&registry, ModInfo::for_test("Scanned", Some("test.installed"), "2.0.0"));
+        assert_eq!(mod_info.name, "Installed");
+        assert_eq!(reference.version, None);
+
+        let (mod_info, reference) =
+            installed_mod(&registry, ModInfo::for_test("Scanned", Some("Test.Installed"), "1.0.0"));
+        assert_eq!(mod_info.version, "2.0.0");
+        assert_eq!(reference.version, Some("1.0.0".to_string()));
+
+        let (mod_info, reference) = installed_mod(&registry, ModInfo::for_test("Other", Some("Test.Other"), "1.0.0"));
+        assert_eq!(mod_info.name, "Other");
+        assert_eq!(reference.unique_id, "Test.Other");
+    }
+}
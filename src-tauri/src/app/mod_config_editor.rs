@@ -0,0 +1,350 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{command, AppHandle, Manager, State};
+
+use crate::app::app_state::AppState;
+use crate::app::config_snapshots;
+use crate::app::error::JunimoError;
+use crate::app::mod_store;
+use crate::app::models::mod_info::ModInfo;
+use crate::app::utility::paths;
+
+/// A mod that has a config.json which can be edited
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModConfigEntry {
+    pub name: String,
+    pub unique_id: Option<String>,
+    pub path: PathBuf,
+}
+
+/// The parsed config.json of a mod
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModConfig {
+    pub name: String,
+    pub unique_id: Option<String>,
+    pub path: PathBuf,
+    pub root: ConfigValue,
+}
+
+/// A value of a config.json with its inferred type
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum ConfigValue {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<ConfigValue>),
+    Object(Vec<ConfigField>),
+}
+
+/// A named field of a config object
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConfigField {
+    pub key: String,
+    pub value: ConfigValue,
+}
+
+/// An error that occurred while reading, validating or writing a config
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    pub path: String,
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(path: &str, message: String) -> Self {
+        ConfigError {
+            path: path.to_string(),
+            message,
+        }
+    }
+}
+
+/// Tauri command to list every installed mod that has a config.json
+///
+/// # Returns the mods with a config file
+#[command]
+pub fn list_mod_configs(app_state: State<'_, AppState>) -> Result<Vec<ModConfigEntry>, JunimoError> {
+    let mut entries: Vec<ModConfigEntry> = app_state
+        .store
+        .mods()
+        .into_iter()
+        .filter_map(|mod_info| {
+            let path = config_path(&mod_info)?;
+            Some(ModConfigEntry {
+                name: mod_info.name,
+                unique_id: mod_info.unique_id,
+                path,
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// Tauri command to read the config.json of a mod
+///
+/// * `name` - The name of the mod
+///
+/// # Returns the typed config or the errors that occurred while reading it
+#[command]
+pub fn get_mod_config(app_state: State<'_, AppState>, name: &str) -> Result<ModConfig, JunimoError> {
+    let (mod_info, path) = find_config(&app_state.store.mods(), name).map_err(JunimoError::InvalidConfig)?;
+    let value = read_config(&path).map_err(|e| JunimoError::InvalidConfig(vec![e]))?;
+
+    Ok(ModConfig {
+        name: mod_info.name,
+        unique_id: mod_info.unique_id,
+        path,
+        root: infer_value(&value),
+    })
+}
+
+/// Tauri command to write edited values into the config.json of a mod.
+/// Keys that aren't part of the edit are kept as they are.
+///
+/// * `app_handle` - The app handle
+/// * `name` - The name of the mod
+/// * `values` - The edited values as json
+///
+/// # Returns the updated config or all validation errors
+#[command]
+pub fn save_mod_config(app_handle: AppHandle, name: &str, values: Value) -> Result<ModConfig, JunimoError> {
+    let (mod_info, path) =
+        find_config(&app_handle.state::<AppState>().store.mods(), name).map_err(JunimoError::InvalidConfig)?;
+    let original = read_config(&path).map_err(|e| JunimoError::InvalidConfig(vec![e]))?;
+
+    let merged = merge_values(&original, &values, "").map_err(JunimoError::InvalidConfig)?;
+    let content = serde_json::to_string_pretty(&merged)?;
+    fs::write(&path, &content)?;
+
+    // Keep the saved config of the current profile in sync, so it isn't restored on the next start
+    config_snapshots::store_config(&app_handle, &mod_info, &content)?;
+
+    Ok(ModConfig {
+        name: mod_info.name,
+        unique_id: mod_info.unique_id,
+        path,
+        root: infer_value(&merged),
+    })
+}
+
+/// Gets the path to the config.json of an installed mod
+///
+/// * `mod_info` - The mod
+///
+/// # Returns the path or None if the mod has no config
+fn config_path(mod_info: &ModInfo) -> Option<PathBuf> {
+    mod_store::find_version(&paths::mod_path(), &mod_info.folder_name(), &mod_info.version)
+        .map(|dir| dir.join("config.json"))
+        .filter(|path| path.exists())
+}
+
+/// Finds an installed mod by its unique id or name together with its config path
+///
+/// * `mods` - The installed mods
+/// * `name` - The unique id or name of the mod
+fn find_config(mods: &Vec<ModInfo>, name: &str) -> Result<(ModInfo, PathBuf), Vec<ConfigError>> {
+    let mod_info = mods
+        .iter()
+        .cloned()
+        .find(|mod_info| mod_info.has_key(name) || mod_info.name == name)
+        .ok_or(vec![ConfigError::new("", format!("Mod {} is not installed", name))])?;
+    let path = config_path(&mod_info)
+        .ok_or(vec![ConfigError::new("", format!("{} has no config.json", name))])?;
+    Ok((mod_info, path))
+}
+
+/// Reads a config.json the same comment tolerant way manifests are read
+///
+/// * `path` - The path to the config file
+///
+/// # Returns the parsed json or an error
+fn read_config(path: &PathBuf) -> Result<Value, ConfigError> {
+    let mut content = fs::read_to_string(path).map_err(|e| ConfigError::new("", e.to_string()))?;
+    parse_config(&mut content)
+}
+
+/// Parses the content of a config.json while ignoring comments
+fn parse_config(content: &mut String) -> Result<Value, ConfigError> {
+    json_strip_comments::strip(content).map_err(|e| ConfigError::new("", e.to_string()))?;
+    serde_json::from_str(content).map_err(|e| {
+        ConfigError::new(
+            "",
+            format!("Invalid json at line {} column {}: {}", e.line(), e.column(), e),
+        )
+    })
+}
+
+/// Converts a json value into a typed config value
+///
+/// * `value` - The json value
+///
+/// # Returns the config value with its inferred type
+pub fn infer_value(value: &Value) -> ConfigValue {
+    match value {
+        Value::Null => ConfigValue::Null,
+        Value::Bool(b) => ConfigValue::Boolean(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => ConfigValue::Integer(i),
+            None => ConfigValue::Float(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => ConfigValue::String(s.clone()),
+        Value::Array(values) => ConfigValue::Array(values.iter().map(infer_value).collect()),
+        Value::Object(map) => ConfigValue::Object(
+            map.iter()
+                .map(|(key, value)| ConfigField {
+                    key: key.clone(),
+                    value: infer_value(value),
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Merges edited values into the original config. Every edited value must have the type of the
+/// value it replaces, keys that only exist in the original are kept.
+///
+/// * `original` - The original config value
+/// * `edit` - The edited value
+/// * `path` - The path of the value inside the config
+///
+/// # Returns the merged value or all validation errors
+pub fn merge_values(original: &Value, edit: &Value, path: &str) -> Result<Value, Vec<ConfigError>> {
+    match (original, edit) {
+        (Value::Object(original_map), Value::Object(edit_map)) => {
+            let mut merged: Map<String, Value> = original_map.clone();
+            let mut errors: Vec<ConfigError> = Vec::new();
+
+            for (key, edit_value) in edit_map {
+                let field_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+
+                match original_map.get(key) {
+                    Some(original_value) => {
+                        match merge_values(original_value, edit_value, &field_path) {
+                            Ok(value) => {
+                                merged.insert(key.clone(), value);
+                            }
+                            Err(mut e) => errors.append(&mut e),
+                        }
+                    }
+                    None => errors.push(ConfigError::new(
+                        &field_path,
+                        "Unknown setting".to_string(),
+                    )),
+                }
+            }
+
+            if errors.is_empty() {
+                Ok(Value::Object(merged))
+            } else {
+                Err(errors)
+            }
+        }
+        // Null values have no type to check against
+        (Value::Null, _) | (_, Value::Null) => Ok(edit.clone()),
+        (Value::Number(original_number), Value::Number(edit_number)) => {
+            if original_number.is_i64() && !edit_number.is_i64() {
+                Err(vec![ConfigError::new(path, "Expected a whole number".to_string())])
+            } else {
+                Ok(edit.clone())
+            }
+        }
+        (Value::Bool(_), Value::Bool(_))
+        | (Value::String(_), Value::String(_))
+        | (Value::Array(_), Value::Array(_)) => Ok(edit.clone()),
+        _ => Err(vec![ConfigError::new(
+            path,
+            format!("Expected {}", type_name(original)),
+        )]),
+    }
+}
+
+/// Gets a readable name of a json type
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a text",
+        Value::Array(_) => "a list",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_config_with_comments() {
+        let mut content = "{\n  // Speed of the player\n  \"Speed\": 2, /* fast */\n  \"Enabled\": true\n}".to_string();
+        let value = parse_config(&mut content).unwrap();
+        assert_eq!(value, json!({"Speed": 2, "Enabled": true}));
+
+        let mut broken = "{ \"Speed\": }".to_string();
+        assert!(parse_config(&mut broken).is_err());
+    }
+
+    #[test]
+    fn test_infer_value() {
+        let value = json!({"Speed": 2, "Scale": 1.5, "Key": "F5", "Enabled": true, "List": [1]});
+        let inferred = infer_value(&value);
+
+        assert_eq!(
+            inferred,
+            ConfigValue::Object(vec![
+                ConfigField {
+                    key: "Speed".to_string(),
+                    value: ConfigValue::Integer(2)
+                },
+                ConfigField {
+                    key: "Scale".to_string(),
+                    value: ConfigValue::Float(1.5)
+                },
+                ConfigField {
+                    key: "Key".to_string(),
+                    value: ConfigValue::String("F5".to_string())
+                },
+                ConfigField {
+                    key: "Enabled".to_string(),
+                    value: ConfigValue::Boolean(true)
+                },
+                ConfigField {
+                    key: "List".to_string(),
+                    value: ConfigValue::Array(vec![ConfigValue::Integer(1)])
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_merge_values_keeps_unknown_keys() {
+        let original = json!({"Speed": 2, "Hidden": {"A": 1, "B": "x"}, "Key": "F5"});
+        let edit = json!({"Speed": 4, "Hidden": {"A": 3}});
+
+        let merged = merge_values(&original, &edit, "").unwrap();
+        assert_eq!(merged, json!({"Speed": 4, "Hidden": {"A": 3, "B": "x"}, "Key": "F5"}));
+    }
+
+    #[test]
+    fn test_merge_values_reports_errors() {
+        let original = json!({"Speed": 2, "Hidden": {"A": 1}, "Key": "F5"});
+        let edit = json!({"Speed": 2.5, "Hidden": {"A": "x"}, "Missing": true});
+
+        let errors = merge_values(&original, &edit, "").unwrap_err();
+        let paths: Vec<String> = errors.iter().map(|e| e.path.clone()).collect();
+        assert_eq!(paths, vec!["Speed", "Hidden.A", "Missing"]);
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::app::app_state::AppState;
+use crate::app::mod_installation;
+use crate::app::models::mod_info::ModInfo;
+use crate::app::profiles::{ModReference, Profile};
+use crate::app::utility::paths;
+
+/// Migrates mods that were identified by their name to be identified by their unique id.
+/// Mod folders named after the mod are renamed after its unique id, and mods.json and profile.json
+/// are rewritten, so every mod is only listed once. Mods that were migrated already are left untouched.
+///
+/// * `app_handle` - The app handle
+pub fn migrate_mod_identity<R: Runtime>(app_handle: &AppHandle<R>) {
+    let store = app_handle.state::<AppState>().store.clone();
+    let result = store.update(app_handle, |mods, profiles| {
+        *mods = migrate_mods(mods.clone(), &paths::mod_path());
+        *profiles = migrate_profiles(profiles.clone(), mods);
+    });
+    if let Err(e) = result {
+        println!("Failed to migrate the mods to their unique ids: {}", e);
+    }
+}
+
+/// Moves the mods into their unique id folders and merges entries of the same mod
+///
+/// * `mods` - The mods of the mods.json file
+/// * `mod_path` - The folder the mods are installed in
+///
+/// # Returns the migrated mods
+fn migrate_mods(mods: Vec<ModInfo>, mod_path: &Path) -> Vec<ModInfo> {
+    let mut migrated: Vec<ModInfo> = Vec::new();
+
+    for mut mod_info in mods {
+        // Older mods might not know their unique id yet, so read it from their manifest
+        if mod_info.unique_id.as_ref().map_or(true, |id| id.is_empty()) {
+            let manifest = [mod_path.join(&mod_info.name), mod_path.join(format!(".{}", &mod_info.name))]
+                .into_iter()
+                .map(|dir| dir.join("manifest.json"))
+                .find(|path| path.exists())
+                .and_then(|path| mod_installation::get_manifest(&path).ok());
+            if let Some(manifest) = manifest {
+                mod_info.unique_id = Some(manifest.unique_id);
+            }
+        }
+
+        rename_folder(mod_path, &mod_info.name, &mod_info.folder_name());
+        rename_folder(
+            mod_path,
+            &format!(".{}", &mod_info.name),
+            &format!(".{}", mod_info.folder_name()),
+        );
+
+        // A mod that was renamed by an update is listed twice, the later entry is the newer one
+        match migrated.iter().position(|existing| existing == &mod_info) {
+            Some(index) => migrated[index] = mod_info,
+            None => migrated.push(mod_info),
+        }
+    }
+
+    migrated
+}
+
+/// Renames a legacy mod folder, if it exists and the new folder doesn't
+fn rename_folder(mod_path: &Path, old: &str, new: &str) {
+    let old_path = mod_path.join(old);
+    let new_path = mod_path.join(new);
+    if old != new && old_path.is_dir() && !new_path.exists() {
+        let _ = fs::rename(&old_path, &new_path);
+    }
+}
+
+/// Points the mod references of every profile to the migrated mods and removes duplicates.
+/// References that still use the name of a mod are changed to its unique id.
+///
+/// * `profiles` - The profiles of the profile.json file
+/// * `mods` - The migrated mods
+///
+/// # Returns the migrated profiles
+fn migrate_profiles(profiles: Vec<Profile>, mods: &Vec<ModInfo>) -> Vec<Profile> {
+    profiles
+        .into_iter()
+        .map(|mut profile| {
+            let mut seen: HashSet<String> = HashSet::new();
+            profile.mods = profile
+                .mods
+                .into_iter()
+                .map(|mut reference| {
+                    let mod_info = mods
+                        .iter()
+                        .find(|mod_info| reference.is_mod(mod_info))
+                        .or_else(|| mods.iter().find(|mod_info| mod_info.name == reference.unique_id));
+                    if let Some(mod_info) = mod_info {
+                        reference.unique_id = ModReference::new(mod_info).unique_id;
+                    }
+                    reference
+                })
+                .filter(|reference| seen.insert(reference.unique_id.to_lowercase()))
+                .collect();
+            profile
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_identity() {
+        let first = ModInfo::for_test("Same Name", Some("Author.First"), "1.0.0");
+        let second = ModInfo::for_test("Same Name", Some("Author.Second"), "1.0.0");
+        let renamed = ModInfo::for_test("New Name", Some("author.first"), "2.0.0");
+
+        assert_ne!(first, second);
+        assert_eq!(first, renamed);
+        assert!(renamed.has_key("Author.First"));
+        assert_eq!(first.folder_name(), "Author.First");
+        assert_eq!(ModInfo::for_test("Legacy", None, "1.0.0").folder_name(), "Legacy");
+    }
+
+    #[test]
+    fn test_migrate_mods() {
+        let tmp_dir = tempdir().unwrap();
+        let mod_path = tmp_dir.path();
+        fs::create_dir_all(mod_path.join("Content Patcher")).unwrap();
+        fs::create_dir_all(mod_path.join(".Old Name")).unwrap();
+        fs::create_dir_all(mod_path.join(".No Id")).unwrap();
+        fs::write(
+            mod_path.join(".No Id").join("manifest.json"),
+            "{\"Name\": \"No Id\", \"UniqueID\": \"Author.NoId\", \"Version\": \"1.0.0\"}",
+        )
+        .unwrap();
+
+        let mods = migrate_mods(
+            vec![
+                ModInfo::for_test("Content Patcher", Some("Pathoschild.ContentPatcher"), "2.0.0"),
+                ModInfo::for_test("Old Name", Some("Author.Renamed"), "1.0.0"),
+                ModInfo::for_test("No Id", None, "1.0.0"),
+                ModInfo::for_test("New Name", Some("Author.Renamed"), "2.0.0"),
+            ],
+            mod_path,
+        );
+
+        assert_eq!(mods.len(), 3);
+        assert_eq!(mods[1].name, "New Name");
+        assert_eq!(mods[2].unique_id, Some("Author.NoId".to_string()));
+        assert!(mod_path.join("Pathoschild.ContentPatcher").is_dir());
+        assert!(mod_path.join(".Author.Renamed").is_dir());
+        assert!(mod_path.join(".Author.NoId").is_dir());
+        assert!(!mod_path.join("Content Patcher").exists());
+    }
+
+    #[test]
+    fn test_migrate_profiles() {
+        let mods = vec![
+            ModInfo::for_test("Content Patcher", Some("Pathoschild.ContentPatcher"), "2.0.0"),
+            ModInfo::for_test("New Name", Some("Author.Renamed"), "2.0.0"),
+        ];
+        let profiles = vec![Profile {
+            name: "Default".to_string(),
+            mods: vec![
+                ModReference::new(&ModInfo::for_test("Content Patcher", None, "2.0.0")),
+                ModReference::new(&ModInfo::for_test("Old Name", Some("author.renamed"), "1.0.0")),
+                ModReference::new(&ModInfo::for_test(
+                    "Content Patcher",
+                    Some("Pathoschild.ContentPatcher"),
+                    "2.0.0"
+                )),
+            ],
+            currently: true,
+            configs: None,
+            smapi_version: None,
+        }];
+
+        let migrated = migrate_profiles(profiles, &mods);
+        let ids: Vec<String> = migrated[0].mods.iter().map(|m| m.unique_id.clone()).collect();
+        assert_eq!(ids, vec!["Pathoschild.ContentPatcher", "Author.Renamed"]);
+        assert_eq!(migrated[0].resolve_mods(&mods)[1].name, "New Name");
+    }
+}
@@ -1,26 +1,119 @@
+use std::collections::HashMap;
+use std::fs;
 use std::fs::File;
 use std::io::Read;
-use std::path::{Path, PathBuf};
-use std::{fs, io};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Manager};
-use walkdir::WalkDir;
-use zip::ZipArchive;
 
 use crate::app::api::compatibility;
+use crate::app::app_state::AppState;
+use crate::app::error::JunimoError;
 use crate::app::models::mod_info::ModInfo;
-use crate::app::mods::{get_all_mods, save_mods, Dependency, Manifest};
+use crate::app::mods::{Dependency, Manifest};
+use crate::app::utility::archives::ModArchive;
 use crate::app::utility::{paths, zips};
-use crate::app::{config, console};
+use crate::app::mod_layout::ModLayoutEntry;
+use crate::app::{config, console, mod_layout, mod_store};
+
+/// How long an install plan waits for the selection of the user before it is discarded
+const PLAN_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+/// What happened to an archive that was handed to the installation
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstallOutcome {
+    /// The mods of the archive were installed
+    Installed,
+    /// The archive contains more than one mod, the plan with this id waits for the user to select the mods
+    AwaitingSelection(u64),
+}
+
+/// A mod that was found in an archive and can be selected for the installation
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlannedMod {
+    /// The folder of the mod inside the archive, used to select the mod
+    pub path: String,
+    pub name: String,
+    pub unique_id: String,
+    pub version: String,
+    pub dependencies: Vec<Dependency>,
+    /// The folder in the mods folder the mod is installed into, named after its unique id and version
+    pub target_folder: String,
+    pub group: Option<String>,
+}
+
+/// The mods of an unpacked archive that wait to be installed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InstallPlan {
+    pub id: u64,
+    pub archive: String,
+    pub mods: Vec<PlannedMod>,
+}
+
+/// An unpacked archive whose plan wasn't applied yet
+pub struct PendingInstall {
+    destination: PathBuf,
+    layout: Vec<ModLayoutEntry>,
+    created: Instant,
+}
+
+/// All planned installations. It is shared through the app state, so every clone works on the same plans.
+#[derive(Clone, Default)]
+pub struct PendingInstalls {
+    state: Arc<Mutex<(u64, HashMap<u64, PendingInstall>)>>,
+}
+
+impl PendingInstalls {
+    /// Reserves the id of a new plan
+    fn reserve(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.0 += 1;
+        state.0
+    }
+
+    fn insert(&self, id: u64, pending: PendingInstall) {
+        self.state.lock().unwrap().1.insert(id, pending);
+    }
+
+    /// Removes a plan, so it can only be applied or cancelled once
+    fn take(&self, id: u64) -> Option<PendingInstall> {
+        self.state.lock().unwrap().1.remove(&id)
+    }
 
-/// Start the installation of a mod
+    /// Removes every plan that waited longer than the lifetime for the selection of the user
+    ///
+    /// # Returns the ids and unpacked archives of the removed plans
+    fn take_expired(&self, lifetime: Duration) -> Vec<(u64, PendingInstall)> {
+        let mut state = self.state.lock().unwrap();
+        let expired: Vec<u64> = state
+            .1
+            .iter()
+            .filter(|(_, pending)| pending.created.elapsed() > lifetime)
+            .map(|(id, _)| *id)
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|id| state.1.remove(&id).map(|pending| (id, pending)))
+            .collect()
+    }
+}
+
+/// Start the installation of a mod. Archives with a single mod or the wanted mod are installed right away,
+/// for archives with more mods the plan is sent to the frontend, so the user can choose which mods to install.
 ///
 /// * `app_handle` - The handle to the Tauri app
-/// * `path` - The path to the zip of the mod file
+/// * `path` - The path to the archive of the mod file
+/// * `unique_id` - The unique id of the mod that should be installed out of the archive, None to let the user choose
 ///
-/// # Returns a Result with the success state
-pub async fn start_installation(app_handle: AppHandle, path: &PathBuf) -> Result<(), String> {
-    let cloned_handle = app_handle.clone();
+/// # Returns whether the mods were installed or wait for the selection of the user
+pub async fn start_installation(
+    app_handle: AppHandle,
+    path: &PathBuf,
+    unique_id: Option<String>,
+) -> Result<InstallOutcome, JunimoError> {
     let path_clone = path.clone();
     // Start the installation in a new thread to prevent the UI from freezing
     tokio::spawn(async move {
@@ -29,241 +122,292 @@ pub async fn start_installation(app_handle: AppHandle, path: &PathBuf) -> Result
             "<span class=\"console-green\">[Junimo] Started to add mod</span>".to_string(),
         );
 
-        let zip_file_path = path_clone;
-        let output_folder_path = paths::temp_path();
-        let to_path = format!(
-            "{}/{}",
-            output_folder_path.display(),
-            zip_file_path.file_name().unwrap().to_str().unwrap()
-        );
+        let plan = match plan_installation(&app_handle, &path_clone) {
+            Ok(plan) => plan,
+            Err(error) => {
+                console::error(&app_handle, &error);
+                app_handle.emit("reload", false)?;
+                return Err(error);
+            }
+        };
 
-        if zip_file_path
-            .clone()
-            .to_string_lossy()
-            .to_string()
-            .replace("\\", "/")
-            != PathBuf::from(&to_path)
-                .to_string_lossy()
-                .to_string()
-                .replace("\\", "/")
-        {
-            fs::copy(&zip_file_path, &to_path).unwrap();
+        match automatic_selection(&plan, unique_id.as_deref()) {
+            Ok(Some(selected)) => {
+                apply_installation(&app_handle, plan.id, &selected).await?;
+                Ok(InstallOutcome::Installed)
+            }
+            Ok(None) => {
+                app_handle.emit("install-plan", &plan)?;
+                Ok(InstallOutcome::AwaitingSelection(plan.id))
+            }
+            Err(error) => {
+                discard_plan(&app_handle, plan.id);
+                console::error(&app_handle, &error);
+                app_handle.emit("reload", false)?;
+                Err(error)
+            }
         }
-        let copied_zip_file = File::open(&to_path).unwrap();
-        let zip_archive = ZipArchive::new(copied_zip_file).unwrap();
-
-        extract_mod(&cloned_handle, zip_archive, &output_folder_path).await;
     })
     .await
-    .unwrap();
+    .map_err(|e| JunimoError::Other(e.to_string()))?
+}
 
-    Ok(())
+/// Selects the mods of a plan that can be installed without asking the user
+///
+/// * `plan` - The plan of the installation
+/// * `unique_id` - The unique id of the wanted mod, None if the user chooses the mods
+///
+/// # Returns the paths of the selected mods, None if the user has to choose or an error if the wanted mod isn't in the archive
+fn automatic_selection(
+    plan: &InstallPlan,
+    unique_id: Option<&str>,
+) -> Result<Option<Vec<String>>, JunimoError> {
+    if plan.mods.len() == 1 {
+        return Ok(Some(vec![plan.mods[0].path.clone()]));
+    }
+
+    match unique_id {
+        Some(unique_id) => plan
+            .mods
+            .iter()
+            .find(|planned| planned.unique_id.eq_ignore_ascii_case(unique_id))
+            .map(|planned| Some(vec![planned.path.clone()]))
+            .ok_or(JunimoError::NotFound(format!(
+                "{} in {}",
+                unique_id, plan.archive
+            ))),
+        None => Ok(None),
+    }
 }
 
-/// Extracts the mod from the zip file into the temp folder
+/// Unpacks an archive into the temp folder and finds all mods in it without touching the mods folder
 ///
 /// * `app_handle` - The handle to the Tauri app
-/// * `archive` - The mods zip archive to unpack
-/// * `destination` - The destination path to unpack the mod to
-async fn extract_mod<R: io::Read + io::Seek>(
-    app_handle: &AppHandle,
-    mut archive: ZipArchive<R>,
-    destination: &Path,
-) {
-    let mut main_dir = "".to_string();
-    let max = archive.len();
-    let mut depth = 0;
-
-    console::add_line(&app_handle, install_progress(&0, &max).to_string());
-
-    // Unpack mods zip file
-    match zips::unpack_zip(app_handle, archive, destination, max) {
-        Ok(main) => {
-            main_dir = main;
-        }
-        Err(error) => {
-            console::modify_line(
-                &app_handle,
-                format!(
-                    "<span class=\"console-red\">[Junimo] Failed to install mod: {}</span>",
-                    error
-                ),
-            );
-            &app_handle.emit("reload", false).unwrap();
-            return;
-        }
+/// * `path` - The path to the archive of the mod file
+///
+/// # Returns the plan of the installation
+pub fn plan_installation(app_handle: &AppHandle, path: &PathBuf) -> Result<InstallPlan, JunimoError> {
+    let file_name = path
+        .file_name()
+        .ok_or(JunimoError::Archive("Invalid archive path".to_string()))?
+        .to_string_lossy()
+        .to_string();
+    let to_path = paths::temp_path().join(&file_name);
+
+    if path.to_string_lossy().replace("\\", "/") != to_path.to_string_lossy().replace("\\", "/") {
+        fs::copy(&path, &to_path)?;
+    }
+    let archive = ModArchive::open(&to_path).map_err(JunimoError::Archive)?;
+
+    // Every archive gets its own folder, so leftovers of other installations can't be mistaken for mods
+    let installs = app_handle.state::<AppState>().installs.clone();
+    discard_expired_plans(app_handle, &installs);
+    let id = installs.reserve();
+    let destination = paths::temp_path().join(format!("install-{}", id));
+    if destination.exists() {
+        fs::remove_dir_all(&destination)?;
     }
 
-    let outpath = destination.join(main_dir);
-    let walkdir = WalkDir::new(&outpath);
-    let it = walkdir.into_iter();
-    let mut group_name = None;
-
-    // Go through unpacked zip and find the manifest.json file. If file depth is over 3 then it's a group
-    for entry in it.filter_map(|e| e.ok()) {
-        if entry.file_name().to_string_lossy() == "manifest.json" {
-            let replaced = entry.path().to_string_lossy().replace("\\", "/");
-            let split = replaced.split('/').collect::<Vec<&str>>();
-            let temp_path_depth = paths::temp_path()
-                .to_string_lossy()
-                .replace("\\", "/")
-                .split("/")
-                .collect::<Vec<&str>>()
-                .len();
-
-            // Calculate the depth of the current path
-            let temp_depth = split.clone().len() - temp_path_depth.clone();
-
-            // If the depth is 0, set the depth to the current depth.
-            // Else if the depth is higher than the current depth, set the depth to the current depth and remove group name.
-            if &depth == &0 {
-                depth = temp_depth;
-            } else if &depth > &temp_depth {
-                depth = temp_depth;
-                group_name = None;
-                continue;
-            }
+    console::add_line(&app_handle, install_progress(&0, &1).to_string());
 
-            if &depth < &3 {
-                continue;
-            }
-            group_name = Some(split[temp_path_depth + 1].to_string());
-            continue;
+    let layout = zips::unpack_archive(app_handle, &archive, &destination, config::extraction_limits())
+        .map_err(JunimoError::Archive)
+        .and_then(|_| mod_layout::analyze_layout(&destination));
+    let layout = match layout {
+        Ok(layout) => layout,
+        Err(error) => {
+            let _ = fs::remove_dir_all(&destination);
+            return Err(error);
         }
-    }
+    };
 
-    let mut directories: Vec<PathBuf> = Vec::new();
+    report_layout(app_handle, &layout);
 
-    // If depth is more than 3 extract all directories in the unpacked zip
-    if depth < 3 {
-        let dir = outpath.clone();
-        directories = vec![dir];
-    } else {
-        directories = list_dirs_in_directory(&outpath);
-    }
+    let mods = layout
+        .iter()
+        .map(|entry| PlannedMod {
+            path: selection_path(entry),
+            name: entry.manifest.name.clone(),
+            unique_id: entry.manifest.unique_id.clone(),
+            version: entry.manifest.version.to_detailed(),
+            dependencies: mod_info_from_manifest(entry.manifest.clone(), None)
+                .dependencies
+                .unwrap_or_default(),
+            target_folder: entry.target_folder(),
+            group: entry.group.clone(),
+        })
+        .collect();
+
+    installs.insert(
+        id,
+        PendingInstall {
+            destination,
+            layout,
+            created: Instant::now(),
+        },
+    );
 
-    match install_mods(&directories, &outpath, &depth, group_name).await {
-        Ok(_) => {}
-        Err(error) => {
-            console::modify_line(
+    Ok(InstallPlan {
+        id,
+        archive: file_name,
+        mods,
+    })
+}
+
+/// Installs the selected mods of a plan and removes the unpacked archive
+///
+/// * `app_handle` - The handle to the Tauri app
+/// * `id` - The id of the plan
+/// * `selected` - The paths of the mods inside the archive that should be installed
+///
+/// # Returns a Result with the success state
+pub async fn apply_installation(
+    app_handle: &AppHandle,
+    id: u64,
+    selected: &Vec<String>,
+) -> Result<(), JunimoError> {
+    let pending = app_handle
+        .state::<AppState>()
+        .installs
+        .take(id)
+        .ok_or(JunimoError::NotFound("The installation".to_string()))?;
+
+    let layout: Vec<ModLayoutEntry> = pending
+        .layout
+        .into_iter()
+        .filter(|entry| selected.contains(&selection_path(entry)))
+        .collect();
+
+    let result = install_mods(app_handle, &layout).await;
+    let _ = fs::remove_dir_all(&pending.destination);
+
+    match &result {
+        Ok(_) => {
+            console::add_line(
                 &app_handle,
                 format!(
-                    "<span class=\"console-red\">[Junimo] Failed to install mod: {}</span>",
-                    error
+                    "<span class=\"console-green\">[Junimo] {} mod(s) installed</span>",
+                    layout.len()
                 ),
             );
-            &app_handle.emit("reload", false).unwrap();
-            return;
+            app_handle.emit("reload", true)?;
+        }
+        Err(error) => {
+            console::error(&app_handle, error);
+            app_handle.emit("reload", false)?;
         }
     }
-
-    console::modify_line(
-        &app_handle,
-        format!("<span class=\"console-green\">[Junimo] Mod installed</span>"),
-    );
-    &app_handle.emit("reload", true).unwrap();
+    result
 }
 
-/// Starts the installation of the mod
+/// Discards a plan and removes the unpacked archive
 ///
-/// * `directories` - The directories of the mod that should be installed
-/// * `outpath` - The path to install the mod to
-/// * `depth` - The depth of the current mod
-/// * `group_name` - The name of the group. None if it's not a group
+/// * `app_handle` - The handle to the Tauri app
+/// * `id` - The id of the plan
+pub fn cancel_installation(app_handle: &AppHandle, id: u64) {
+    if discard_plan(app_handle, id) {
+        console::add_line(
+            &app_handle,
+            "<span class=\"console-gray\">[Junimo] Installation cancelled</span>".to_string(),
+        );
+    }
+}
+
+/// Removes a plan and its unpacked archive
 ///
-/// # Returns a Result with the success state
-async fn install_mods(
-    directories: &Vec<PathBuf>,
-    outpath: &PathBuf,
-    depth: &usize,
-    group_name: Option<String>,
-) -> Result<(), String> {
-    let mut manifest: Option<Manifest> = None;
-
-    // Go through the directories and install the mods
-    for dir in directories {
-        let mut new_dir_name = "".to_string();
-
-        // Find the manifest.json file in the directory and add the mod to our mods file
-        for entry in fs::read_dir(&dir).unwrap() {
-            match entry {
-                Ok(entry) => {
-                    if entry
-                        .file_name()
-                        .to_string_lossy()
-                        .contains("manifest.json")
-                    {
-                        manifest = Some(get_manifest(&entry.path()));
-                        new_dir_name = add_mod_through_manifest(
-                            manifest.clone().unwrap().clone(),
-                            group_name.clone(),
-                        )
-                        .await;
-                    }
-                }
-                Err(_) => {
-                    continue;
-                }
-            }
+/// # Returns whether the plan existed
+fn discard_plan(app_handle: &AppHandle, id: u64) -> bool {
+    match app_handle.state::<AppState>().installs.take(id) {
+        Some(pending) => {
+            let _ = fs::remove_dir_all(&pending.destination);
+            true
         }
+        None => false,
+    }
+}
 
-        // Rename the directory to the mod name
-        let mut renamed_dir = outpath.clone();
-        if depth < &3 {
-            renamed_dir.pop();
-        }
-        renamed_dir.push(&new_dir_name);
-        let rename_result = fs::rename(&dir, &renamed_dir).ok();
-        if rename_result.is_none() {
-            return Err("Failed to rename directory".to_string());
+/// Removes the plans nobody selected mods for and fails the downloads that waited for them
+///
+/// * `app_handle` - The handle to the Tauri app
+/// * `installs` - The planned installations
+fn discard_expired_plans(app_handle: &AppHandle, installs: &PendingInstalls) {
+    let state = app_handle.state::<AppState>();
+    for (id, pending) in installs.take_expired(PLAN_LIFETIME) {
+        let _ = fs::remove_dir_all(&pending.destination);
+        if let Some(download) = state
+            .downloads
+            .finish_selection(id, Err("Nobody selected the mods to install".to_string()))
+        {
+            let _ = app_handle.emit("download", &download);
         }
+    }
+}
 
-        let game_path = paths::mod_path().join(format!(".{}", &manifest.clone().unwrap().name));
-
-        // If the mod directory in game path already exists, remove it
-        if &game_path.exists() == &true {
-            fs::remove_dir_all(&game_path).unwrap();
+/// Removes the unpacked archives of plans that were left behind when Junimo was closed.
+/// Plans only live in memory, so after a start every install folder is a leftover.
+pub fn clean_up_plans() {
+    let entries = match fs::read_dir(paths::temp_path()) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with("install-") && entry.path().is_dir() {
+            let _ = fs::remove_dir_all(entry.path());
         }
+    }
+}
 
-        // Move the renamed directory to the game path
-        let move_result = fs::rename(&renamed_dir, &game_path);
+/// Gets the path of a mod inside the archive with forward slashes
+fn selection_path(entry: &ModLayoutEntry) -> String {
+    entry.relative_path.to_string_lossy().replace("\\", "/")
+}
 
-        match move_result {
-            Ok(_) => {
-                // If the temp directory still exists, remove it
-                if renamed_dir.clone().exists() {
-                    fs::remove_dir_all(&renamed_dir).unwrap();
-                }
-            }
-            Err(_) => {
-                return Err("Failed to move directory".to_string());
-            }
-        }
+/// Prints the mods that were found in an archive and where they will be installed to
+///
+/// * `app_handle` - The handle to the Tauri app
+/// * `layout` - The mods of the archive
+fn report_layout(app_handle: &AppHandle, layout: &Vec<ModLayoutEntry>) {
+    console::modify_line(
+        &app_handle,
+        format!(
+            "<span class=\"console-gray\">[Junimo] Found {} mod(s) in the archive</span>",
+            layout.len()
+        ),
+    );
+    for entry in layout {
+        let source = match entry.relative_path.as_os_str().is_empty() {
+            true => "archive root".to_string(),
+            false => selection_path(entry),
+        };
+        console::add_line(
+            &app_handle,
+            format!(
+                "<span class=\"console-gray\">[Junimo] {} {} ({}) -> {}</span>",
+                &entry.manifest.name,
+                &entry.manifest.version.to_detailed(),
+                source,
+                entry.target_folder()
+            ),
+        );
     }
-
-    Ok(())
 }
 
-/// Lists all subdirectories in unpacked dir
+/// Starts the installation of the mods
 ///
-/// * `path` - The path to the directory
+/// * `app_handle` - The handle to the Tauri app
+/// * `layout` - The mods found in the unpacked archive
 ///
-/// # Returns a vector with all subdirectories
-fn list_dirs_in_directory(path: &Path) -> Vec<PathBuf> {
-    let mut paths: Vec<PathBuf> = Vec::new();
-    if path.is_dir() {
-        for entry in fs::read_dir(path).unwrap() {
-            let entry = entry;
-            if entry.is_err() {
-                continue;
-            }
-            let path = entry.unwrap().path();
-            if path.is_dir() {
-                paths.push(path);
-            }
-        }
+/// # Returns a Result with the success state
+async fn install_mods(app_handle: &AppHandle, layout: &Vec<ModLayoutEntry>) -> Result<(), JunimoError> {
+    for entry in layout {
+        // Every version gets a folder of its own, so profiles can keep using older versions.
+        // The mod is only registered once its folder is in place.
+        let version = entry.manifest.version.to_detailed();
+        mod_store::install_version(&paths::mod_path(), &entry.folder(), &version, &entry.path)?;
+        add_mod_through_manifest(app_handle, entry.manifest.clone(), entry.group.clone()).await?;
     }
-    paths
+
+    Ok(())
 }
 
 /// Calculates the percentage of the current installation progress
@@ -306,19 +450,24 @@ pub fn install_progress(amount: &usize, max: &usize) -> String {
 ///
 /// * `path` - The path to the manifest file
 ///
-/// # Returns the mods manifest file
-pub fn get_manifest(path: &PathBuf) -> Manifest {
-    let mut file = File::open(path).unwrap();
+/// # Returns the mods manifest file or an error if it couldn't be read or parsed
+pub fn get_manifest(path: &PathBuf) -> Result<Manifest, JunimoError> {
+    let manifest_error = |message: String| JunimoError::Manifest {
+        path: path.display().to_string(),
+        message,
+    };
+
+    let mut file = File::open(path).map_err(|e| manifest_error(e.to_string()))?;
     let mut output = String::new();
-    file.read_to_string(&mut output).unwrap();
+    file.read_to_string(&mut output).map_err(|e| manifest_error(e.to_string()))?;
     output = output.replace("UniqueId", "UniqueID");
     output = output.replace("Authour", "Author");
-    json_strip_comments::strip(&mut output).unwrap();
+    json_strip_comments::strip(&mut output).map_err(|e| manifest_error(e.to_string()))?;
     match extract_json(&output) {
         Some(json) => output = json,
         None => println!("No JSON found"),
     }
-    serde_json::from_str(&output).unwrap()
+    serde_json::from_str(&output).map_err(|e| manifest_error(e.to_string()))
 }
 
 /// Extracts the JSON from the manifest file
@@ -338,11 +487,42 @@ fn extract_json(input: &str) -> Option<String> {
 
 /// Adds a mod through the manifest file
 ///
+/// * `app_handle` - The handle to the Tauri app
 /// * `manifest` - The mods manifest file
 /// * `group_name` - The name of the group. None if it's not a group
 ///
 /// # Returns the name of the mod
-async fn add_mod_through_manifest(manifest: Manifest, group_name: Option<String>) -> String {
+async fn add_mod_through_manifest(
+    app_handle: &AppHandle,
+    manifest: Manifest,
+    group_name: Option<String>,
+) -> Result<String, JunimoError> {
+    let mut new_mod = mod_info_from_manifest(manifest, group_name);
+
+    // Check for compatibilities and update the mod info
+    let config = config::load_config(paths::config_path());
+    if config.activate_broken.is_none() || config.activate_broken.unwrap() {
+        match compatibility::get_compability(vec![new_mod.clone()]).await {
+            Some(compatibility) => {
+                new_mod = compatibility[0].clone();
+            }
+            None => {}
+        }
+    }
+
+    // Insert the mod info into the mods file
+    let store = app_handle.state::<AppState>().store.clone();
+    store.update_mods(app_handle, |mods| insert_mod_info(mods, &new_mod))?;
+    Ok(new_mod.name)
+}
+
+/// Creates the mod info of a mod out of its manifest file
+///
+/// * `manifest` - The mods manifest file
+/// * `group_name` - The name of the group. None if it's not a group
+///
+/// # Returns the mod info
+pub fn mod_info_from_manifest(manifest: Manifest, group_name: Option<String>) -> ModInfo {
     let mut dependencies: Vec<Dependency> = Vec::new();
 
     // Select the dependencies from the manifest file
@@ -364,7 +544,7 @@ async fn add_mod_through_manifest(manifest: Manifest, group_name: Option<String>
     }
 
     // Create mod info for the mod
-    let mut new_mod = ModInfo {
+    ModInfo {
         name: manifest.name,
         summary: manifest.description.clone(),
         description: manifest.description,
@@ -383,8 +563,8 @@ async fn add_mod_through_manifest(manifest: Manifest, group_name: Option<String>
         created_time: "".to_owned(),
         updated_timestamp: 0,
         updated_time: "".to_owned(),
-        author: manifest.author.clone().unwrap(),
-        uploaded_by: manifest.author.unwrap(),
+        author: manifest.author.clone().unwrap_or_default(),
+        uploaded_by: manifest.author.unwrap_or_default(),
         uploaded_users_profile_url: "".to_owned(),
         contains_adult_content: false,
         status: "".to_owned(),
@@ -394,47 +574,66 @@ async fn add_mod_through_manifest(manifest: Manifest, group_name: Option<String>
         dependencies: Some(dependencies),
         group: group_name,
         is_broken: None,
-    };
-
-    // Check for compatibilities and update the mod info
-    let config = config::get_config(paths::config_path());
-    if config.activate_broken.is_none() || config.activate_broken.unwrap() {
-        match compatibility::get_compability(vec![new_mod.clone()]).await {
-            Some(compatibility) => {
-                new_mod = compatibility[0].clone();
-            }
-            None => {}
-        }
+        update_keys: manifest.update_keys,
+        update_version: None,
+        update_url: None,
     }
-
-    // Insert the mod info into the mods file
-    insert_mod_info(&new_mod);
-    new_mod.name
 }
 
-/// Inserts the mod info into the mods file. If the mod already exists, update it
+/// Inserts the mod info into the installed mods. If the mod already exists, update it.
+/// Mods are matched by their unique id, so a renamed mod replaces its old entry.
 ///
+/// * `mod_list` - The installed mods
 /// * `infos` - The mod info
-pub fn insert_mod_info(infos: &ModInfo) {
-    let mut mod_list = get_all_mods();
+pub fn insert_mod_info(mod_list: &mut Vec<ModInfo>, infos: &ModInfo) {
+    match mod_list.iter().position(|mod_info| mod_info == infos) {
+        Some(index) => mod_list[index] = infos.clone(),
+        None => mod_list.push(infos.clone()),
+    }
+}
 
-    if mod_list
-        .iter()
-        .any(|mod_info| mod_info.name == infos.name && mod_info.version == infos.version)
-    {
-        return;
-    } else if mod_list
-        .iter()
-        .any(|mod_info| mod_info.name == infos.name && mod_info.version != infos.version)
-    {
-        let index = mod_list
-            .iter()
-            .position(|mod_info| mod_info.name == infos.name)
-            .unwrap();
-        mod_list[index] = infos.clone();
-        save_mods(mod_list);
-    } else {
-        mod_list.push(infos.clone());
-        save_mods(mod_list);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn planned(unique_id: &str) -> PlannedMod {
+        PlannedMod {
+            path: unique_id.to_string(),
+            name: unique_id.to_string(),
+            unique_id: unique_id.to_string(),
+            version: "1.0.0".to_string(),
+            dependencies: vec![],
+            target_folder: unique_id.to_string(),
+            group: None,
+        }
+    }
+
+    #[test]
+    fn test_automatic_selection() {
+        let plan = InstallPlan {
+            id: 1,
+            archive: "Pack.zip".to_string(),
+            mods: vec![planned("Author.Core"), planned("Author.Addon")],
+        };
+
+        assert_eq!(automatic_selection(&plan, None).unwrap(), None);
+        assert_eq!(
+            automatic_selection(&plan, Some("author.addon")).unwrap(),
+            Some(vec!["Author.Addon".to_string()])
+        );
+        assert_eq!(
+            automatic_selection(&plan, Some("Other.Mod")).unwrap_err(),
+            JunimoError::NotFound("Other.Mod in Pack.zip".to_string())
+        );
+
+        let single = InstallPlan {
+            id: 2,
+            archive: "Core.zip".to_string(),
+            mods: vec![planned("Author.Core")],
+        };
+        assert_eq!(
+            automatic_selection(&single, None).unwrap(),
+            Some(vec!["Author.Core".to_string()])
+        );
     }
 }
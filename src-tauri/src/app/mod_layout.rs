@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::app::error::JunimoError;
+use crate::app::mod_installation::get_manifest;
+use crate::app::models::mod_info::folder_name;
+use crate::app::mods::Manifest;
+
+/// A mod that was found in an unpacked archive
+#[derive(Debug, Clone)]
+pub struct ModLayoutEntry {
+    /// The folder that contains the manifest
+    pub path: PathBuf,
+    /// The path of the folder inside the archive, empty if the manifest is at the root
+    pub relative_path: PathBuf,
+    pub manifest: Manifest,
+    /// The folder the mod was bundled in together with other mods. None for single mods.
+    pub group: Option<String>,
+}
+
+impl ModLayoutEntry {
+    /// Gets the name of the folder the versions of the mod are installed into, named after its unique id
+    pub fn folder(&self) -> String {
+        match self.manifest.unique_id.is_empty() {
+            true => self.manifest.name.clone(),
+            false => folder_name(&self.manifest.unique_id),
+        }
+    }
+
+    /// Gets the folder in the mods folder the mod is installed into, named after its unique id and version
+    pub fn target_folder(&self) -> String {
+        format!("{}/{}", self.folder(), folder_name(&self.manifest.version.to_detailed()))
+    }
+}
+
+/// Finds every mod in an unpacked archive. Like SMAPI, every folder with a manifest.json is a mod
+/// and its subfolders aren't searched any further, so content packs that ship inside a mod stay
+/// part of it. Folders without a manifest are searched for more mods.
+/// If more than one mod is found below the same top folder, that folder becomes the group of the mods.
+///
+/// * `root` - The folder the archive was unpacked into
+///
+/// # Returns the mods sorted by their path or an error if there's no mod or a manifest is broken
+pub fn analyze_layout(root: &Path) -> Result<Vec<ModLayoutEntry>, JunimoError> {
+    let mut mod_roots: Vec<PathBuf> = Vec::new();
+    find_mod_roots(root, &mut mod_roots)?;
+
+    if mod_roots.is_empty() {
+        return Err(JunimoError::NotFound("A manifest.json in the archive".to_string()));
+    }
+
+    let relative_paths: Vec<PathBuf> = mod_roots
+        .iter()
+        .map(|path| path.strip_prefix(root).unwrap_or(path).to_path_buf())
+        .collect();
+
+    // Count how many mods share every top folder to tell groups and wrapper folders apart
+    let mut mods_per_folder: HashMap<String, usize> = HashMap::new();
+    for relative_path in &relative_paths {
+        if let Some(folder) = group_folder(relative_path) {
+            *mods_per_folder.entry(folder).or_insert(0) += 1;
+        }
+    }
+
+    let mut entries: Vec<ModLayoutEntry> = Vec::new();
+    for (path, relative_path) in mod_roots.into_iter().zip(relative_paths) {
+        let manifest = get_manifest(&path.join(manifest_name(&path)?))?;
+        let group = group_folder(&relative_path).filter(|folder| mods_per_folder[folder] > 1);
+
+        entries.push(ModLayoutEntry {
+            path,
+            relative_path,
+            manifest,
+            group,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Collects every folder with a manifest.json below a folder
+///
+/// * `dir` - The folder to search
+/// * `mod_roots` - The list the found folders get added to
+fn find_mod_roots(dir: &Path, mod_roots: &mut Vec<PathBuf>) -> Result<(), JunimoError> {
+    if manifest_name(dir).is_ok() {
+        mod_roots.push(dir.to_path_buf());
+        return Ok(());
+    }
+
+    let mut subfolders: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter(|path| {
+            // SMAPI ignores hidden folders and macOS puts its metadata into __MACOSX
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            !name.starts_with('.') && name != "__MACOSX"
+        })
+        .collect();
+    subfolders.sort();
+
+    for subfolder in subfolders {
+        find_mod_roots(&subfolder, mod_roots)?;
+    }
+    Ok(())
+}
+
+/// Gets the file name of the manifest in a folder, ignoring its case
+fn manifest_name(dir: &Path) -> Result<String, JunimoError> {
+    fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .find(|name| name.eq_ignore_ascii_case("manifest.json"))
+        .ok_or_else(|| JunimoError::NotFound(format!("The manifest.json in {}", dir.display())))
+}
+
+/// Gets the top folder of a mod that is nested at least one folder deep
+fn group_folder(relative_path: &Path) -> Option<String> {
+    let components: Vec<Component> = relative_path.components().collect();
+    if components.len() < 2 {
+        return None;
+    }
+    Some(components[0].as_os_str().to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    /// Creates the unpacked archive of a fixture, every path gets a manifest with the name of its folder
+    fn fixture(root: &Path, manifests: &[&str]) {
+        for manifest in manifests {
+            let path = root.join(manifest);
+            let dir = path.parent().unwrap();
+            let name = Path::new(manifest)
+                .parent()
+                .and_then(|parent| parent.file_name())
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or("Root".to_string());
+            fs::create_dir_all(dir).unwrap();
+            fs::write(
+                &path,
+                format!(
+                    "{{\"Name\": \"{0}\", \"UniqueID\": \"Test.{0}\", \"Version\": \"1.0.0\"}}",
+                    name
+                ),
+            )
+            .unwrap();
+        }
+    }
+
+    fn summary(entries: &Vec<ModLayoutEntry>) -> Vec<(String, String, Option<String>)> {
+        entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.relative_path.to_string_lossy().replace('\\', "/"),
+                    entry.manifest.name.clone(),
+                    entry.group.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_single_mod_layouts() {
+        // Mod folder at the top of the archive
+        let tmp_dir = tempdir().unwrap();
+        fixture(tmp_dir.path(), &["Mod/manifest.json"]);
+        let entries = analyze_layout(tmp_dir.path()).unwrap();
+        assert_eq!(summary(&entries), vec![("Mod".to_string(), "Mod".to_string(), None)]);
+        assert_eq!(entries[0].target_folder(), "Test.Mod/1.0.0");
+
+        // Manifest at the root of the archive
+        let tmp_dir = tempdir().unwrap();
+        fixture(tmp_dir.path(), &["manifest.json"]);
+        let entries = analyze_layout(tmp_dir.path()).unwrap();
+        assert_eq!(entries[0].path, tmp_dir.path());
+        assert_eq!(entries[0].manifest.name, "Root");
+
+        // Wrapper folder named after the release around a single mod
+        let tmp_dir = tempdir().unwrap();
+        fixture(tmp_dir.path(), &["Mod 1.2.0/Mod/MANIFEST.json"]);
+        let entries = analyze_layout(tmp_dir.path()).unwrap();
+        assert_eq!(
+            summary(&entries),
+            vec![("Mod 1.2.0/Mod".to_string(), "Mod".to_string(), None)]
+        );
+    }
+
+    #[test]
+    fn test_multi_mod_layouts() {
+        // Content packs for different frameworks bundled in one folder
+        let tmp_dir = tempdir().unwrap();
+        fixture(
+            tmp_dir.path(),
+            &[
+                "Pack/[CP] Pack/manifest.json",
+                "Pack/[JA] Pack/manifest.json",
+                "Pack/Optional/[CP] Pack Extra/manifest.json",
+            ],
+        );
+        let group = Some("Pack".to_string());
+        assert_eq!(
+            summary(&analyze_layout(tmp_dir.path()).unwrap()),
+            vec![
+                ("Pack/Optional/[CP] Pack Extra".to_string(), "[CP] Pack Extra".to_string(), group.clone()),
+                ("Pack/[CP] Pack".to_string(), "[CP] Pack".to_string(), group.clone()),
+                ("Pack/[JA] Pack".to_string(), "[JA] Pack".to_string(), group),
+            ]
+        );
+
+        // Several mods at the top of the archive
+        let tmp_dir = tempdir().unwrap();
+        fixture(tmp_dir.path(), &["First/manifest.json", "Second/manifest.json"]);
+        assert_eq!(
+            summary(&analyze_layout(tmp_dir.path()).unwrap()),
+            vec![
+                ("First".to_string(), "First".to_string(), None),
+                ("Second".to_string(), "Second".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_and_ignored_folders() {
+        let tmp_dir = tempdir().unwrap();
+        fixture(
+            tmp_dir.path(),
+            &[
+                "Mod/manifest.json",
+                "Mod/assets/[CP] Bundled/manifest.json",
+                "__MACOSX/Mod/manifest.json",
+                ".hidden/manifest.json",
+            ],
+        );
+        assert_eq!(
+            summary(&analyze_layout(tmp_dir.path()).unwrap()),
+            vec![("Mod".to_string(), "Mod".to_string(), None)]
+        );
+    }
+
+    #[test]
+    fn test_invalid_layouts() {
+        let tmp_dir = tempdir().unwrap();
+        fs::create_dir_all(tmp_dir.path().join("Mod").join("assets")).unwrap();
+        assert_eq!(
+            analyze_layout(tmp_dir.path()).unwrap_err(),
+            JunimoError::NotFound("A manifest.json in the archive".to_string())
+        );
+
+        fs::write(tmp_dir.path().join("Mod").join("manifest.json"), "{ broken").unwrap();
+        assert_eq!(analyze_layout(tmp_dir.path()).unwrap_err().kind(), "Manifest");
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::app::app_state::AppState;
+use crate::app::error::JunimoError;
+use crate::app::{console, mod_installation};
+use crate::app::models::mod_info::{folder_name, ModInfo};
+use crate::app::profiles::Profile;
+use crate::app::state_store::StateStore;
+use crate::app::utility::paths;
+use crate::app::utility::semantic_version::SemanticVersion;
+
+// Every installed version of a mod has a folder of its own: mods/<UniqueID>/<version>/.
+// Mods and versions that were deactivated by the rename strategy start with a dot, so SMAPI skips them.
+
+/// Moves mods that were installed before versions were kept into a folder of their version.
+/// Mods that are in the versioned layout already are left untouched.
+///
+/// * `app_handle` - The app handle, failures are reported to its console
+pub fn migrate_store<R: Runtime>(app_handle: &AppHandle<R>) {
+    let registry = app_handle.state::<AppState>().store.mods();
+    for error in migrate_flat_layout(&paths::mod_path(), &registry) {
+        console::error(
+            app_handle,
+            &JunimoError::Io(format!("Couldn't move the mod folder {} into a folder of its version", error)),
+        );
+    }
+}
+
+/// Moves every mod folder with a manifest directly inside into a folder of its version
+///
+/// * `store` - The folder the mods are installed in
+/// * `registry` - The installed mods, to get the version of mods with a broken manifest
+///
+/// # Returns the errors of all folders that couldn't be moved
+fn migrate_flat_layout(store: &Path, registry: &Vec<ModInfo>) -> Vec<String> {
+    let mut errors: Vec<String> = Vec::new();
+
+    for dir in sub_folders(store) {
+        let manifest = match fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .find(|path| {
+                    path.is_file()
+                        && path
+                            .file_name()
+                            .map_or(false, |name| name.to_string_lossy().eq_ignore_ascii_case("manifest.json"))
+                }),
+            Err(_) => None,
+        };
+        let manifest = match manifest {
+            Some(manifest) => manifest,
+            None => continue,
+        };
+
+        let name = dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let folder = name.strip_prefix('.').unwrap_or(&name).to_string();
+        let version = match mod_installation::get_manifest(&manifest) {
+            Ok(manifest) => manifest.version.to_detailed(),
+            Err(_) => registry
+                .iter()
+                .find(|mod_info| mod_info.folder_name() == folder)
+                .map(|mod_info| mod_info.version.clone())
+                .unwrap_or("0.0.0".to_string()),
+        };
+
+        // Move the folder aside first, so it can become the version folder inside the new mod folder
+        let moving = store.join(format!(".{}.migrating", folder));
+        let result = fs::rename(&dir, &moving)
+            .and_then(|_| fs::create_dir_all(&dir))
+            .and_then(|_| fs::rename(&moving, dir.join(version_folder(&version))));
+        if let Err(e) = result {
+            errors.push(format!("{} ({})", name, e));
+        }
+    }
+
+    errors
+}
+
+/// Gets the folder name of a mod version
+///
+/// * `version` - The version of the mod
+pub fn version_folder(version: &str) -> String {
+    folder_name(version)
+}
+
+/// Gets the folder of a mod in the store. New mods start deactivated.
+///
+/// * `store` - The folder the mods are installed in
+/// * `folder` - The folder name of the mod
+pub fn mod_dir(store: &Path, folder: &str) -> PathBuf {
+    let active = store.join(folder);
+    match active.exists() {
+        true => active,
+        false => store.join(format!(".{}", folder)),
+    }
+}
+
+/// Finds the folder of an installed mod version, no matter if the rename strategy deactivated it
+///
+/// * `store` - The folder the mods are installed in
+/// * `folder` - The folder name of the mod
+/// * `version` - The version of the mod
+pub fn find_version(store: &Path, folder: &str, version: &str) -> Option<PathBuf> {
+    let version = version_folder(version);
+    [store.join(folder), store.join(format!(".{}", folder))]
+        .into_iter()
+        .flat_map(|dir| [dir.join(&version), dir.join(format!(".{}", version))])
+        .find(|path| path.is_dir())
+}
+
+/// Lists the installed versions of a mod from the oldest to the newest
+///
+/// * `store` - The folder the mods are installed in
+/// * `folder` - The folder name of the mod
+pub fn versions(store: &Path, folder: &str) -> Vec<String> {
+    let mut versions: Vec<String> = [store.join(folder), store.join(format!(".{}", folder))]
+        .iter()
+        .flat_map(|dir| sub_folders(dir))
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().to_string();
+            Some(name.strip_prefix('.').unwrap_or(&name).to_string())
+        })
+        .collect();
+    versions.sort_by(|a, b| match (a.parse::<SemanticVersion>(), b.parse::<SemanticVersion>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    });
+    versions.dedup();
+    versions
+}
+
+/// Deactivates every version of a mod except the given one, so SMAPI only finds a single version.
+/// Used by the rename strategy.
+///
+/// * `store` - The folder the mods are installed in
+/// * `folder` - The folder name of the mod
+/// * `version` - The version to activate
+pub fn select_version(store: &Path, folder: &str, version: &str) -> Result<(), String> {
+    let selected = version_folder(version);
+    let dir = mod_dir(store, folder);
+
+    for path in sub_folders(&dir) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let new_name = match name.strip_prefix('.') {
+            Some(version) if version == selected => version.to_string(),
+            None if name != selected => format!(".{}", name),
+            _ => continue,
+        };
+        fs::rename(&path, dir.join(new_name)).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Moves an unpacked mod into the store as a version of its own. An installed folder of the same version
+/// is moved aside first and only removed once the new folder is in place, so it is restored if the move fails.
+///
+/// * `store` - The folder the mods are installed in
+/// * `folder` - The folder name of the mod
+/// * `version` - The version of the mod
+/// * `source` - The unpacked mod folder
+///
+/// # Returns the folder of the installed version
+pub fn install_version(store: &Path, folder: &str, version: &str, source: &Path) -> Result<PathBuf, JunimoError> {
+    let mod_dir = mod_dir(store, folder);
+    fs::create_dir_all(&mod_dir)?;
+    let target = mod_dir.join(version_folder(version));
+
+    let replaced = match find_version(store, folder, version) {
+        Some(installed) => {
+            let aside = installed.with_file_name(format!(".{}.replacing", version_folder(version)));
+            if aside.exists() {
+                remove_version(&aside)?;
+            }
+            fs::rename(&installed, &aside)?;
+            Some((installed, aside))
+        }
+        None => None,
+    };
+
+    if let Err(e) = fs::rename(source, &target) {
+        if let Some((installed, aside)) = &replaced {
+            let _ = fs::rename(aside, installed);
+        }
+        return Err(JunimoError::Io(format!("Failed to move {} into the mods folder: {}", folder, e)));
+    }
+    if let Some((_, aside)) = replaced {
+        let _ = remove_version(&aside);
+    }
+    Ok(target)
+}
+
+/// Removes every mod version that no profile uses. The installed version of a mod is always kept.
+///
+/// * `store` - The folder the mods are installed in
+/// * `registry` - The installed mods
+/// * `profiles` - All profiles
+///
+/// # Returns the removed versions as "name version"
+pub fn collect_garbage(store: &Path, registry: &Vec<ModInfo>, profiles: &Vec<Profile>) -> Vec<String> {
+    let mut removed: Vec<String> = Vec::new();
+
+    for mod_info in registry {
+        let folder = mod_info.folder_name();
+        let mut used: HashSet<String> = HashSet::new();
+        used.insert(version_folder(&mod_info.version));
+        for profile in profiles {
+            for reference in profile.mods.iter().filter(|reference| reference.is_mod(mod_info)) {
+                if let Some(version) = &reference.version {
+                    used.insert(version_folder(version));
+                }
+            }
+        }
+
+        for version in versions(store, &folder) {
+            if used.contains(&version_folder(&version)) {
+                continue;
+            }
+            if let Some(path) = find_version(store, &folder, &version) {
+                if remove_version(&path).is_ok() {
+                    removed.push(format!("{} {}", &mod_info.name, version));
+                }
+            }
+        }
+    }
+
+    removed
+}
+
+/// Removes every mod version that no profile uses from Junimo's mods folder
+///
+/// * `state` - The state store with the installed mods and the profiles
+///
+/// # Returns the removed versions as "name version"
+pub fn remove_unused_versions(state: &StateStore) -> Vec<String> {
+    collect_garbage(&paths::mod_path(), &state.mods(), &state.profiles())
+}
+
+/// Removes the folder of a mod version. Adopted mods might be links, those are removed without touching their target.
+///
+/// * `path` - The folder of the version
+fn remove_version(path: &Path) -> std::io::Result<()> {
+    match fs::symlink_metadata(path).map(|m| m.file_type().is_symlink()) {
+        Ok(true) => remove_link(path),
+        _ => fs::remove_dir_all(path),
+    }
+}
+
+/// Gets all folders inside a folder
+fn sub_folders(dir: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+#[cfg(unix)]
+fn remove_link(link: &Path) -> std::io::Result<()> {
+    fs::remove_file(link)
+}
+
+#[cfg(windows)]
+fn remove_link(link: &Path) -> std::io::Result<()> {
+    fs::remove_dir(link)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use crate::app::profiles::ModReference;
+
+    use super::*;
+
+    fn install(store: &Path, folder: &str, version: &str) {
+        let dir = store.join(folder).join(version);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("manifest.json"), format!("{{\"Version\": \"{}\"}}", version)).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_flat_layout() {
+        let tmp_dir = tempdir().unwrap();
+        let store = tmp_dir.path();
+        fs::create_dir_all(store.join(".Author.Flat")).unwrap();
+        fs::write(
+            store.join(".Author.Flat").join("Manifest.json"),
+            "{\"Name\": \"Flat\", \"UniqueID\": \"Author.Flat\", \"Version\": \"1.2.0\"}",
+        )
+        .unwrap();
+        install(store, "Author.Versioned", "2.0.0");
+
+        assert!(migrate_flat_layout(store, &vec![]).is_empty());
+        assert!(store.join(".Author.Flat").join("1.2.0").join("Manifest.json").exists());
+        assert!(store.join("Author.Versioned").join("2.0.0").join("manifest.json").exists());
+        assert!(!store.join(".Author.Flat.migrating").exists());
+    }
+
+    #[test]
+    fn test_versions_and_selection() {
+        let tmp_dir = tempdir().unwrap();
+        let store = tmp_dir.path();
+        install(store, "Author.Mod", "1.10.0");
+        install(store, "Author.Mod", "1.9.0");
+        install(store, "Author.Mod", "2.0.0-beta");
+
+        assert_eq!(versions(store, "Author.Mod"), vec!["1.9.0", "1.10.0", "2.0.0-beta"]);
+
+        select_version(store, "Author.Mod", "1.9.0").unwrap();
+        assert!(store.join("Author.Mod").join("1.9.0").exists());
+        assert!(store.join("Author.Mod").join(".1.10.0").exists());
+        assert_eq!(find_version(store, "Author.Mod", "1.10.0"), Some(store.join("Author.Mod").join(".1.10.0")));
+
+        select_version(store, "Author.Mod", "1.10.0").unwrap();
+        assert!(store.join("Author.Mod").join("1.10.0").exists());
+        assert!(store.join("Author.Mod").join(".1.9.0").exists());
+        assert_eq!(versions(store, "Author.Mod").len(), 3);
+    }
+
+    #[test]
+    fn test_install_version() {
+        let tmp_dir = tempdir().unwrap();
+        let store = tmp_dir.path().join("mods");
+        install(&store, ".Author.Mod", "1.0.0");
+        let unpacked = tmp_dir.path().join("unpacked");
+        fs::create_dir_all(&unpacked).unwrap();
+        fs::write(unpacked.join("new.txt"), "new").unwrap();
+
+        // A failed move keeps the installed files
+        let missing = tmp_dir.path().join("missing");
+        assert!(install_version(&store, "Author.Mod", "1.0.0", &missing).is_err());
+        let installed = store.join(".Author.Mod").join("1.0.0");
+        assert!(installed.join("manifest.json").exists());
+        assert_eq!(versions(&store, "Author.Mod"), vec!["1.0.0"]);
+
+        let target = install_version(&store, "Author.Mod", "1.0.0", &unpacked).unwrap();
+        assert_eq!(target, installed);
+        assert!(target.join("new.txt").exists());
+        assert!(!target.join("manifest.json").exists());
+        assert_eq!(versions(&store, "Author.Mod"), vec!["1.0.0"]);
+    }
+
+    #[test]
+    fn test_collect_garbage() {
+        let tmp_dir = tempdir().unwrap();
+        let store = tmp_dir.path();
+        install(store, "Author.Mod", "1.0.0");
+        install(store, "Author.Mod", "1.5.0");
+        install(store, "Author.Mod", "2.0.0");
+        install(store, "Author.Unknown", "1.0.0");
+
+        let registry = vec![ModInfo::for_test("Author.Mod", Some("Author.Mod"), "2.0.0")];
+        let profiles = vec![Profile {
+            name: "Old Save".to_string(),
+            mods: vec![ModReference {
+                unique_id: "author.mod".to_string(),
+                version: Some("1.0.0".to_string()),
+                enabled: true,
+            }],
+            currently: false,
+            configs: None,
+            smapi_version: None,
+        }];
+
+        let removed = collect_garbage(store, &registry, &profiles);
+        assert_eq!(removed, vec!["Author.Mod 1.5.0"]);
+        assert_eq!(versions(store, "Author.Mod"), vec!["1.0.0", "2.0.0"]);
+        assert!(store.join("Author.Unknown").join("1.0.0").exists());
+    }
+}
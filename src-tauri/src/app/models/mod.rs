@@ -1 +1,2 @@
 pub mod mod_info;
+pub mod nexus_file;
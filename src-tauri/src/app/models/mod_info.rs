@@ -33,16 +33,115 @@ pub struct ModInfo {
     pub dependencies: Option<Vec<Dependency>>,
     pub group: Option<String>,
     pub is_broken: Option<bool>,
+    pub update_keys: Option<Vec<String>>,
+    pub update_version: Option<String>,
+    pub update_url: Option<String>,
+}
+
+impl ModInfo {
+    /// Gets the key that identifies the mod. Mods are identified by their unique id,
+    /// only mods without a unique id fall back to their name.
+    pub fn key(&self) -> String {
+        match &self.unique_id {
+            Some(unique_id) if !unique_id.is_empty() => unique_id.to_lowercase(),
+            _ => self.name.clone(),
+        }
+    }
+
+    /// Checks if the mod is identified by the key, ignoring the case of unique ids
+    ///
+    /// * `key` - The unique id of the mod or the name of a mod without unique id
+    pub fn has_key(&self, key: &str) -> bool {
+        match &self.unique_id {
+            Some(unique_id) if !unique_id.is_empty() => unique_id.eq_ignore_ascii_case(key),
+            _ => self.name == key,
+        }
+    }
+
+    /// Gets the name of the folder the mod is installed in, without the dot of inactive mods
+    pub fn folder_name(&self) -> String {
+        match &self.unique_id {
+            Some(unique_id) if !unique_id.is_empty() => folder_name(unique_id),
+            _ => self.name.clone(),
+        }
+    }
+}
+
+/// Gets the folder name for a unique id. Characters that aren't allowed in paths are replaced.
+///
+/// * `unique_id` - The unique id of the mod
+pub fn folder_name(unique_id: &str) -> String {
+    unique_id
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
 }
 
 impl PartialEq for ModInfo {
     fn eq(&self, other: &Self) -> bool {
-        self.name == other.name
+        self.key() == other.key()
     }
 }
 
 impl Hash for ModInfo {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.name.hash(state); // Only hash the name
+        self.key().hash(state); // Only hash the identity
+    }
+}
+
+#[cfg(test)]
+impl ModInfo {
+    /// Creates the infos of a local mod for tests
+    ///
+    /// * `name` - The name of the mod
+    /// * `unique_id` - The unique id of the mod, None for mods without one
+    /// * `version` - The version of the mod
+    pub fn for_test(name: &str, unique_id: Option<&str>, version: &str) -> ModInfo {
+        ModInfo {
+            name: name.to_string(),
+            summary: None,
+            description: None,
+            picture_url: None,
+            mod_downloads: 0,
+            mod_unique_downloads: 0,
+            uid: 0,
+            mod_id: 0,
+            game_id: 1303,
+            allow_rating: false,
+            domain_name: "stardewvalley".to_string(),
+            category_id: 0,
+            version: version.to_string(),
+            endorsement_count: 0,
+            created_timestamp: 0,
+            created_time: "".to_string(),
+            updated_timestamp: 0,
+            updated_time: "".to_string(),
+            author: "".to_string(),
+            uploaded_by: "".to_string(),
+            uploaded_users_profile_url: "".to_string(),
+            contains_adult_content: false,
+            status: "".to_string(),
+            available: true,
+            unique_id: unique_id.map(|id| id.to_string()),
+            more_info: None,
+            dependencies: None,
+            group: None,
+            is_broken: None,
+            update_keys: None,
+            update_version: None,
+            update_url: None,
+        }
+    }
+
+    /// Sets the dependencies of a test mod
+    ///
+    /// * `dependencies` - The dependencies of the mod
+    pub fn with_dependencies(mut self, dependencies: Vec<Dependency>) -> ModInfo {
+        self.dependencies = Some(dependencies);
+        self
     }
 }
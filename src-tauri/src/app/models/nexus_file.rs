@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+/// Response of the Nexus files endpoint of a mod
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NexusFiles {
+    pub files: Vec<NexusFile>,
+}
+
+/// A single downloadable file of a mod on Nexus
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NexusFile {
+    pub file_id: u64,
+    pub name: String,
+    pub version: Option<String>,
+    pub category_name: Option<String>,
+    pub is_primary: bool,
+    pub file_name: String,
+    pub uploaded_timestamp: u64,
+    pub size_in_bytes: Option<u64>,
+    #[serde(default)]
+    pub md5: Option<String>,
+}
+
+/// A match of the Nexus md5 search
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Md5SearchResult {
+    #[serde(rename = "mod")]
+    pub mod_info: Md5SearchMod,
+    pub file_details: Md5FileDetails,
+}
+
+/// The mod a file of the md5 search belongs to
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Md5SearchMod {
+    pub mod_id: u32,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub domain_name: String,
+}
+
+/// The file that was found by the md5 search
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Md5FileDetails {
+    pub file_id: u64,
+    pub name: String,
+    pub version: Option<String>,
+    pub md5: String,
+    pub size_in_bytes: Option<u64>,
+}
@@ -1,19 +1,18 @@
-use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::fs::File;
 use std::hash::{Hash, Hasher};
-use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
 use rfd::FileDialog;
 use serde::{Deserialize, Serialize};
-use tauri::{command, AppHandle, Manager};
+use tauri::{command, AppHandle, Manager, State};
 
-use crate::app::api::compatibility;
+use crate::app::api::{compatibility, downloader};
+use crate::app::app_state::AppState;
+use crate::app::dependencies::{DependencyNode, DependencyState};
+use crate::app::error::JunimoError;
 use crate::app::models::mod_info::ModInfo;
-use crate::app::profiles::Profile;
-use crate::app::utility::{browser, paths};
-use crate::app::{config, console, mod_installation, profiles};
+use crate::app::utility::{browser, paths, persistence};
+use crate::app::{config, console, dependencies, mod_installation, mod_store};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Manifest {
@@ -81,123 +80,212 @@ pub struct Dependency {
     pub is_required: Option<bool>,
 }
 
-/// Saves the mods to the mods.json file in the appdata folder
-pub fn save_mods(mod_infos: Vec<ModInfo>) {
-    let json = serde_json::to_string(&mod_infos).unwrap();
-    let mut file = File::create(paths::mod_json_path()).expect("Fail");
-    file.write_all(json.as_bytes()).unwrap();
+/// Saves the mods to a mods.json file. Only the state store saves the installed mods,
+/// everything else changes them through the store.
+///
+/// * `path` - The path to the mods.json file
+/// * `mod_infos` - The mods to save
+pub fn save_mods(path: &Path, mod_infos: &Vec<ModInfo>) -> Result<(), JunimoError> {
+    persistence::write_json(path, mod_infos)
 }
 
 /// Tauri command to open the Stardew Valley Nexus page in the default browser
 #[command]
-pub async fn open_search_browser(handle: AppHandle) {
+pub async fn open_search_browser(handle: AppHandle) -> Result<(), JunimoError> {
     let nexus_link = "https://www.nexusmods.com/stardewvalley";
     browser::open_url(nexus_link);
+    Ok(())
 }
 
 /// Tauri command to get all installed mods that are not used in the current profile
 #[command]
-pub async fn get_installed_mods(app: tauri::AppHandle) -> Vec<ModInfo> {
-    let path = paths::mod_json_path();
-    let current_profile = profiles::get_current_profile(paths::profile_path()).await;
+pub async fn get_installed_mods(app: tauri::AppHandle) -> Result<Vec<ModInfo>, JunimoError> {
+    let store = app.state::<AppState>().store.clone();
+    let current_profile = store.current_profile();
 
-    if !path.exists() {
-        let mods: Vec<ModInfo> = Vec::new();
-        save_mods(mods);
-    }
-
-    let mut all_mods = get_all_mods();
+    let mut all_mods = store.mods();
     all_mods = all_mods
         .iter()
-        .filter(|mod_info| !current_profile.mods.contains(mod_info))
+        .filter(|mod_info| !current_profile.contains(mod_info))
         .cloned()
         .collect();
     all_mods.sort_by(|a, b| a.name.cmp(&b.name));
-    all_mods
+    Ok(all_mods)
 }
 
-/// Gets all mods from the mods.json file in the appdata folder
-pub fn get_all_mods() -> Vec<ModInfo> {
-    let path = paths::mod_json_path();
-
-    if !path.exists() {
-        let mods: Vec<ModInfo> = Vec::new();
-        save_mods(mods);
+/// Loads all mods from a mods.json file. The installed mods are loaded by the state store,
+/// everything else gets them from the store.
+///
+/// * `path` - The path to the mods.json file
+pub fn load_mods(path: &Path) -> Vec<ModInfo> {
+    if !persistence::exists(path) {
+        if let Err(e) = save_mods(path, &Vec::new()) {
+            println!("Failed to create mods.json: {}", e);
+        }
     }
 
-    let data_raw = fs::read_to_string(path).unwrap();
-    let data = data_raw.as_str();
-    serde_json::from_str(data).unwrap()
+    match persistence::read_json(path, |data| Ok(serde_json::from_str(data)?)) {
+        Ok(mods) => mods,
+        Err(e @ JunimoError::Unsupported(_)) => {
+            // The file belongs to a newer version of Junimo, so it is kept
+            println!("{}", e);
+            Vec::new()
+        }
+        Err(e) => {
+            // The broken file was moved aside, so start over instead of failing on every start
+            println!("{}", e);
+            if let Err(e) = save_mods(path, &Vec::new()) {
+                println!("Failed to reset mods.json: {}", e);
+            }
+            Vec::new()
+        }
+    }
 }
 
 /// Adds mod
 #[command]
-pub async fn add_mod(app_handle: AppHandle) {
+pub async fn add_mod(app_handle: AppHandle) -> Result<(), JunimoError> {
     // Opens File Dialog to select a mod's zip file
     let file = FileDialog::new()
-        .add_filter("zip", &["zip"])
+        .add_filter("Mod archive", &["zip", "7z", "rar", "gz", "tgz"])
         .set_directory("/")
         .pick_file();
 
-    // Starts the installation of the zip file.
+    // Starts the installation of the zip file. Failures were already reported to the console.
     if let Some(path) = file {
-        mod_installation::start_installation(app_handle.clone(), &path).await;
+        mod_installation::start_installation(app_handle.clone(), &path, None)
+            .await
+            .map(|_| ())
     } else {
         println!("No file was selected.");
+        Ok(())
     }
 }
 
+/// Tauri command to install the selected mods of an archive that contains more than one mod
+///
+/// * `app_handle` - The handle to the Tauri app
+/// * `id` - The id of the install plan
+/// * `selected` - The paths of the mods inside the archive that should be installed
+///
+/// # Returns a Result with the success state
+#[command]
+pub async fn apply_installation(
+    app_handle: AppHandle,
+    app_state: State<'_, AppState>,
+    id: u64,
+    selected: Vec<String>,
+) -> Result<(), JunimoError> {
+    let result = mod_installation::apply_installation(&app_handle, id, &selected).await;
+    let outcome = result.clone().map_err(|e| e.to_string());
+    downloader::selection_finished(&app_handle, &app_state, id, outcome);
+    result
+}
+
+/// Tauri command to discard an install plan without installing any of its mods
+///
+/// * `app_handle` - The handle to the Tauri app
+/// * `id` - The id of the install plan
+#[command]
+pub fn cancel_installation(
+    app_handle: AppHandle,
+    app_state: State<'_, AppState>,
+    id: u64,
+) -> Result<(), JunimoError> {
+    mod_installation::cancel_installation(&app_handle, id);
+    downloader::selection_cancelled(&app_handle, &app_state, id);
+    Ok(())
+}
+
 /// Uninstalls multiple mods
+///
+/// * `mods` - The unique ids of the mods, or the names of mods without unique id
+#[command]
+pub fn uninstall_mods(app_handle: AppHandle, mods: Vec<&str>) -> Result<(), JunimoError> {
+    for key in mods {
+        uninstall_mod(app_handle.clone(), key)?;
+    }
+    Ok(())
+}
+
+/// Tauri command to list the installed versions of a mod
+///
+/// * `id` - The unique id of the mod, or the name of a mod without unique id
+///
+/// # Returns the versions from the oldest to the newest
+#[command]
+pub fn get_mod_versions(app_state: State<'_, AppState>, id: &str) -> Result<Vec<String>, JunimoError> {
+    match app_state.store.mods().into_iter().find(|mod_info| mod_info.has_key(id)) {
+        Some(mod_info) => Ok(mod_store::versions(&paths::mod_path(), &mod_info.folder_name())),
+        None => Err(JunimoError::NotFound(format!("The mod {}", id))),
+    }
+}
+
+/// Tauri command to remove every installed mod version that isn't used by any profile
+///
+/// * `app_handle` - The app handle
+///
+/// # Returns the removed versions
 #[command]
-pub fn uninstall_mods(app_handle: AppHandle, mods: Vec<&str>) {
-    for mod_name in mods {
-        uninstall_mod(app_handle.clone(), mod_name);
+pub fn remove_unused_versions(app_handle: AppHandle) -> Result<Vec<String>, JunimoError> {
+    let removed = mod_store::remove_unused_versions(&app_handle.state::<AppState>().store);
+
+    for version in removed.iter() {
+        console::add_line(
+            &app_handle,
+            format!(
+                "<span class=\"console-green\">[Junimo] Removed unused version {}</span>",
+                version
+            ),
+        );
+    }
+    if removed.is_empty() {
+        console::add_line(
+            &app_handle,
+            "<span class=\"console-green\">[Junimo] No unused mod versions found</span>".to_string(),
+        );
     }
+    Ok(removed)
 }
 
 /// Uninstalls a mod by removing it from the mods.json file, all profiles and deleting the mod folder
+///
+/// * `id` - The unique id of the mod, or the name of a mod without unique id
 #[command]
-pub fn uninstall_mod(app_handle: AppHandle, name: &str) {
-    // Remove mod from mods.json
-    let mut mods = get_all_mods();
-    mods.retain(|mod_info| mod_info.name != name);
-    save_mods(mods);
-
-    // Remove mod from all profiles
-    let mut profile = profiles::get_profiles(paths::profile_path());
-    let mut new_profiles = Vec::new();
-    for p in profile {
-        if p.mods.iter().any(|mod_info| mod_info.name == name) {
-            let mut new_mods = p.mods.clone();
-            new_mods.retain(|mod_info| mod_info.name != name);
-            let new_profile = profiles::Profile {
-                name: p.name,
-                mods: new_mods,
-                currently: p.currently,
-            };
-            new_profiles.push(new_profile);
-        } else {
-            new_profiles.push(p);
+pub fn uninstall_mod(app_handle: AppHandle, id: &str) -> Result<(), JunimoError> {
+    let store = app_handle.state::<AppState>().store.clone();
+    let removed = store.update(&app_handle, |mods, profiles| {
+        // Remove mod from mods.json
+        let removed: Vec<ModInfo> = mods.iter().filter(|mod_info| mod_info.has_key(id)).cloned().collect();
+        mods.retain(|mod_info| !mod_info.has_key(id));
+
+        // Remove mod from all profiles
+        for profile in profiles.iter_mut() {
+            profile.mods.retain(|reference| {
+                !reference.unique_id.eq_ignore_ascii_case(id)
+                    && !removed.iter().any(|mod_info| reference.is_mod(mod_info))
+            });
         }
-    }
-    profiles::save_profiles(&new_profiles, &paths::profile_path());
+        removed
+    })?;
 
     // Remove mod from mods folder. If the mod is currently not used, the folder name is prefixed with a dot
-    let mut mod_path = paths::mod_path();
-    mod_path = mod_path.join(&name);
-    remove_mod_folder(&app_handle, &mod_path, name);
-    mod_path.pop();
-    mod_path = mod_path.join(format!(".{}", name));
-    remove_mod_folder(&app_handle, &mod_path, name);
-
-    // Add a success line to the console
-    console::add_line(
-        &app_handle,
-        format!(
-            "<span class=\"console-green\">[Junimo] Uninstalled: {}</span>",
-            &name
-        ),
-    );
+    let mod_path = paths::mod_path();
+    for mod_info in removed.iter() {
+        let folder_name = mod_info.folder_name();
+        remove_mod_folder(&app_handle, &mod_path.join(&folder_name), &mod_info.name);
+        remove_mod_folder(&app_handle, &mod_path.join(format!(".{}", &folder_name)), &mod_info.name);
+
+        // Add a success line to the console
+        console::add_line(
+            &app_handle,
+            format!(
+                "<span class=\"console-green\">[Junimo] Uninstalled: {}</span>",
+                &mod_info.name
+            ),
+        );
+    }
+    Ok(())
 }
 
 /// Removes a mod folder
@@ -219,118 +307,194 @@ fn remove_mod_folder(app_handle: &AppHandle, mod_path: &PathBuf, name: &str) {
 }
 
 /// Check for compatibility errors in mods
-pub async fn compatibility_check(app_handle: AppHandle) -> bool {
-    let config = config::get_config(paths::config_path());
-    let mut mods_result: Option<Vec<ModInfo>> = Some(get_all_mods());
+///
+/// # Returns false if smapi.io couldn't be reached
+pub async fn compatibility_check(app_handle: AppHandle) -> Result<bool, JunimoError> {
+    let config = config::load_config(paths::config_path());
+    let store = app_handle.state::<AppState>().store.clone();
+    let mut mods_result: Option<Vec<ModInfo>> = Some(store.mods());
 
     if config.activate_broken.is_none() || config.activate_broken.unwrap() {
-        mods_result = compatibility::get_compability(get_all_mods()).await;
+        mods_result = compatibility::get_compability(store.mods()).await;
     } else {
-        let mut mods = mods_result.clone().unwrap();
-        for mod_info in mods.iter_mut() {
-            mod_info.is_broken = None;
-            mod_info.more_info = None;
-        }
-        save_mods(mods);
+        store.update_mods(&app_handle, |mods| {
+            for mod_info in mods.iter_mut() {
+                mod_info.is_broken = None;
+                mod_info.more_info = None;
+            }
+        })?;
     }
 
     if config.activate_requirements.is_none() || config.activate_requirements.unwrap() {
         match mods_result {
-            Some(mods) => {
-                let profiles = profiles::get_profiles(paths::profile_path());
-
-                let hash_map: HashMap<_, _> = mods
-                    .clone()
-                    .into_iter()
-                    .enumerate()
-                    .map(|(index, modinfo)| (modinfo.unique_id, index))
-                    .collect();
-
-                let mut new_profiles: Vec<Profile> = vec![];
-                for profile in profiles {
-                    let mut new_profile: Profile = profile.clone();
-                    let mut new_profile_mods: Vec<ModInfo> = vec![];
-                    for mod_info in profile.mods {
-                        if hash_map.contains_key(&mod_info.unique_id) {
-                            let index = hash_map.get(&mod_info.unique_id).unwrap();
-                            let new_mod: ModInfo = mods[*index].clone();
-                            new_profile_mods.push(new_mod);
-                        } else {
-                            new_profile_mods.push(mod_info);
-                        }
-                    }
-                    new_profile.mods = new_profile_mods;
-                    new_profiles.push(new_profile);
-                }
-                profiles::save_profiles(&new_profiles, &paths::profile_path());
-
-                save_mods(mods);
-                true
+            Some(checked) => {
+                store.update_mods(&app_handle, |mods| merge_checked(mods, checked))?;
+                Ok(true)
             }
-            None => false,
+            None => Ok(false),
         }
     } else {
-        true
+        Ok(true)
     }
 }
 
-pub fn check_dependencies(mods: Vec<ModInfo>) -> Vec<ModInfo> {
-    let cloned_mods = mods.clone();
-    let mut new_modinfo = Vec::new();
+/// Applies the result of a compatibility check to the installed mods. The check runs without holding
+/// the state store, so mods that were removed or updated in the meantime keep their current state.
+///
+/// * `mods` - The installed mods
+/// * `checked` - The mods as they were sent to smapi.io, with the compatibility infos
+fn merge_checked(mods: &mut Vec<ModInfo>, checked: Vec<ModInfo>) {
+    for checked_mod in checked {
+        if let Some(mod_info) = mods
+            .iter_mut()
+            .find(|mod_info| mod_info.key() == checked_mod.key() && mod_info.version == checked_mod.version)
+        {
+            *mod_info = checked_mod;
+        }
+    }
+}
 
-    // Create hashset of all unique_ids
-    let unique_ids: HashSet<Option<String>> = mods
-        .iter()
-        .map(|mod_info| mod_info.unique_id.clone())
+/// Tauri command to check all installed mods for updates through smapi.io
+///
+/// * `app_handle` - The app handle
+///
+/// # Returns all mods that have an update available
+#[command]
+pub async fn check_updates(app_handle: AppHandle) -> Result<Vec<ModInfo>, JunimoError> {
+    let store = app_handle.state::<AppState>().store.clone();
+    match compatibility::get_compability(store.mods()).await {
+        Some(checked) => {
+            let mods = store.update_mods(&app_handle, |mods| {
+                merge_checked(mods, checked);
+                mods.clone()
+            })?;
+
+            let updates: Vec<ModInfo> = mods
+                .into_iter()
+                .filter(|mod_info| mod_info.update_version.is_some())
+                .collect();
+            console::add_line(
+                &app_handle,
+                format!(
+                    "<span class=\"console-green\">[Junimo] Found {} mod update(s)</span>",
+                    updates.len()
+                ),
+            );
+            Ok(updates)
+        }
+        None => Err(JunimoError::Network("smapi.io didn't answer the update check".to_string())),
+    }
+}
+
+/// Tauri command to download and install every available mod update
+///
+/// * `app_handle` - The app handle
+/// * `app_state` - The app state
+#[command]
+pub async fn update_all_mods(
+    app_handle: AppHandle,
+    app_state: State<'_, AppState>,
+) -> Result<(), JunimoError> {
+    let outdated_mods: Vec<ModInfo> = app_state
+        .store
+        .mods()
+        .into_iter()
+        .filter(|mod_info| mod_info.update_version.is_some())
         .collect();
 
-    for mut mod_info in mods.clone() {
-        if mod_info.dependencies.is_none() || mod_info.more_info.is_some() {
-            if !(mod_info.more_info.is_some()
-                && (mod_info.more_info.clone().unwrap().contains("Missing")
-                    || mod_info.more_info.clone().unwrap().contains("Recommended")))
-            {
-                new_modinfo.push(mod_info.clone());
-                continue;
+    if outdated_mods.is_empty() {
+        console::add_line(
+            &app_handle,
+            "<span class=\"console-green\">[Junimo] All mods are up to date</span>".to_string(),
+        );
+        return Ok(());
+    }
+
+    for mod_info in outdated_mods {
+        match downloader::download_update(&app_handle, &mod_info, app_state.inner().clone()).await {
+            Ok(_) => {
+                console::add_line(
+                    &app_handle,
+                    format!(
+                        "<span class=\"console-green\">[Junimo] Updated {} to {}</span>",
+                        &mod_info.name,
+                        mod_info.update_version.clone().unwrap_or_default()
+                    ),
+                );
+            }
+            Err(e) => {
+                console::add_line(
+                    &app_handle,
+                    format!(
+                        "<span class=\"console-red\">[Junimo] Failed to update {}: {}</span>",
+                        &mod_info.name, e
+                    ),
+                );
             }
         }
+    }
+
+    app_handle.emit("reload", true)?;
+    Ok(())
+}
 
-        match &mod_info.dependencies {
-            Some(dependencies) => {
-                let mut mode = 0;
-                for dependency in dependencies {
-                    if unique_ids.contains(&Some(dependency.unique_id.clone())) {
-                        mod_info.more_info = None;
-                        continue;
-                    }
-                    if dependency.is_required == None || dependency.is_required == Some(true) {
-                        let split = dependency.unique_id.split('.').collect::<Vec<&str>>();
-
-                        mod_info.more_info = Some(format!(
-                            "<span style=\"color: #cf3838\">Missing mod: {}</span>",
-                            dependency
-                                .unique_id
-                                .replace(format!("{}.", split[0]).as_str(), "")
-                        ));
-                        break;
-                    } else {
-                        if mode == 2 {
-                            continue;
-                        }
-
-                        let split = dependency.unique_id.split('.').collect::<Vec<&str>>();
-                        mod_info.more_info = Some(format!(
-                            "<span class=\"console-blue\">Recommended mod: {}</span>",
-                            split[split.len() - 1]
-                        ));
-                        continue;
-                    }
+/// Checks the dependencies of mods and adds a hint about missing, outdated or recommended mods
+///
+/// * `mods` - The mods that are used together
+///
+/// # Returns the mods with updated dependency hints
+pub fn check_dependencies(mods: Vec<ModInfo>) -> Vec<ModInfo> {
+    let nodes = dependencies::resolve(&mods);
+
+    mods.into_iter()
+        .zip(nodes)
+        .map(|(mut mod_info, node)| {
+            // Keep the compatibility infos of smapi.io and only replace our own hints
+            if let Some(more_info) = &mod_info.more_info {
+                if !more_info.contains("Missing")
+                    && !more_info.contains("Outdated")
+                    && !more_info.contains("Recommended")
+                {
+                    return mod_info;
                 }
-                new_modinfo.push(mod_info.clone());
             }
-            None => {}
-        }
+
+            mod_info.more_info = dependency_hint(&node);
+            mod_info
+        })
+        .collect()
+}
+
+/// Creates the hint for the first unmet dependency of a mod
+fn dependency_hint(node: &DependencyNode) -> Option<String> {
+    if let Some(dependency) = node.unmet_requirements().first() {
+        let split = dependency.unique_id.split('.').collect::<Vec<&str>>();
+        let name = dependency
+            .unique_id
+            .replace(format!("{}.", split[0]).as_str(), "");
+
+        return match dependency.state {
+            DependencyState::TooOld => Some(format!(
+                "<span style=\"color: #cf3838\">Outdated mod: {} (requires {}, installed {})</span>",
+                name,
+                dependency.minimum_version.clone().unwrap_or_default(),
+                dependency.installed_version.clone().unwrap_or_default()
+            )),
+            _ => Some(format!(
+                "<span style=\"color: #cf3838\">Missing mod: {}</span>",
+                name
+            )),
+        };
     }
 
-    new_modinfo
+    node.dependencies
+        .iter()
+        .find(|dependency| dependency.state == DependencyState::Missing)
+        .map(|dependency| {
+            let split = dependency.unique_id.split('.').collect::<Vec<&str>>();
+            format!(
+                "<span class=\"console-blue\">Recommended mod: {}</span>",
+                split[split.len() - 1]
+            )
+        })
 }
@@ -1,97 +1,358 @@
+use crate::app::app_state::AppState;
+use crate::app::error::JunimoError;
 use crate::app::models::mod_info::ModInfo;
-use crate::app::utility::paths;
-use crate::app::{config, mods};
+use crate::app::utility::{paths, persistence};
+use crate::app::{config, mod_installation, mod_store, mods};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
-use tauri::{command, Manager, Runtime, WebviewUrl};
+use std::path::{Path, PathBuf};
+use tauri::{command, Manager, Runtime, State, WebviewUrl};
+
+/// The version of the profile.json format. Version 1 was a plain list of profiles that contained
+/// copies of their mods.
+pub const PROFILE_SCHEMA_VERSION: u32 = 2;
+
+/// A mod that is part of a profile. The infos of the mod are looked up in the mod registry.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ModReference {
+    /// The unique id of the mod, or its name if the mod doesn't have a unique id
+    pub unique_id: String,
+    /// The version the profile is pinned to. None uses the installed version.
+    pub version: Option<String>,
+    pub enabled: bool,
+}
+
+impl ModReference {
+    pub fn new(mod_info: &ModInfo) -> Self {
+        ModReference {
+            unique_id: match &mod_info.unique_id {
+                Some(unique_id) if !unique_id.is_empty() => unique_id.clone(),
+                _ => mod_info.name.clone(),
+            },
+            version: None,
+            enabled: true,
+        }
+    }
+
+    /// Checks if the reference points to a mod
+    pub fn is_mod(&self, mod_info: &ModInfo) -> bool {
+        mod_info.has_key(&self.unique_id)
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Profile {
+    pub name: String,
+    pub mods: Vec<ModReference>,
+    pub currently: bool,
+    pub configs: Option<HashMap<String, String>>,
+    /// The SMAPI version the profile needs, None if any version works
+    #[serde(default)]
+    pub smapi_version: Option<String>,
+}
+
+impl Profile {
+    /// Checks if a mod is referenced by the profile, no matter if it is enabled
+    pub fn contains(&self, mod_info: &ModInfo) -> bool {
+        self.mods.iter().any(|reference| reference.is_mod(mod_info))
+    }
+
+    /// Looks up the enabled mods of the profile in the mod registry.
+    /// References to mods that aren't installed anymore are skipped.
+    ///
+    /// * `registry` - The installed mods
+    ///
+    /// # Returns the infos of the enabled mods with the version the profile is pinned to
+    pub fn resolve_mods(&self, registry: &Vec<ModInfo>) -> Vec<ModInfo> {
+        self.resolve_mods_in(registry, &paths::mod_path())
+    }
+
+    /// Looks up the enabled mods of the profile in the mod registry. Pinned mods get the infos
+    /// of the manifest of their version, as the name and dependencies can change between versions.
+    ///
+    /// * `registry` - The installed mods
+    /// * `store` - The folder the mods are installed in
+    ///
+    /// # Returns the infos of the enabled mods with the version the profile is pinned to
+    pub fn resolve_mods_in(&self, registry: &Vec<ModInfo>, store: &Path) -> Vec<ModInfo> {
+        self.mods
+            .iter()
+            .filter(|reference| reference.enabled)
+            .filter_map(|reference| {
+                let installed = registry.iter().find(|mod_info| reference.is_mod(mod_info))?;
+                Some(match &reference.version {
+                    Some(version) => pinned_mod_info(installed, store, version),
+                    None => installed.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Creates the resolved version of the profile
+    ///
+    /// * `registry` - The installed mods
+    pub fn resolve(&self, registry: &Vec<ModInfo>) -> ResolvedProfile {
+        ResolvedProfile {
+            name: self.name.clone(),
+            mods: self.resolve_mods(registry),
+            currently: self.currently,
+            configs: self.configs.clone(),
+            smapi_version: self.smapi_version.clone(),
+        }
+    }
+}
+
+/// A profile together with the infos of its enabled mods
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResolvedProfile {
     pub name: String,
     pub mods: Vec<ModInfo>,
     pub currently: bool,
+    pub configs: Option<HashMap<String, String>>,
+    /// The SMAPI version the profile needs, None if any version works
+    #[serde(default)]
+    pub smapi_version: Option<String>,
+}
+
+/// The content of the profile.json file
+#[derive(Serialize, Deserialize)]
+struct ProfileFile {
+    version: u32,
+    profiles: Vec<Profile>,
+}
+
+/// A profile of the version 1 format, that contained copies of its mods
+#[derive(Deserialize)]
+struct LegacyProfile {
+    name: String,
+    mods: Vec<ModInfo>,
+    currently: bool,
+    configs: Option<HashMap<String, String>>,
 }
 
 #[command]
-pub async fn open_profile<R: Runtime>(handle: tauri::AppHandle<R>) {
+pub async fn open_profile<R: Runtime>(handle: tauri::AppHandle<R>) -> Result<(), JunimoError> {
     #[cfg(target_os = "windows")]
     tauri::WebviewWindowBuilder::new(&handle, "Profiles", WebviewUrl::App("/profiles".into()))
         .title("Profiles")
         .transparent(true)
-        .build()
-        .unwrap();
+        .build()?;
 
     #[cfg(target_os = "unix")]
     tauri::WebviewWindowBuilder::new(&handle, "Profiles", WebviewUrl::App("/profiles".into()))
         .title("Profiles")
-        .build()
-        .unwrap();
+        .build()?;
+    Ok(())
 }
 
+/// Tauri command to get all profiles
+///
+/// * `path` - The path to the profile.json file
 #[command]
-pub fn get_profiles(path: PathBuf) -> Vec<Profile> {
-    check_path(&path);
+pub fn get_profiles(app_state: State<'_, AppState>, path: PathBuf) -> Result<Vec<Profile>, JunimoError> {
+    Ok(app_state.store.profiles_at(&path))
+}
 
-    let data_raw = fs::read_to_string(path).unwrap();
-    let data = data_raw.as_str();
-    serde_json::from_str(data).unwrap()
+/// Loads all profiles from a profile.json file. The profiles of the app are loaded by the state store,
+/// everything else gets them from the store.
+///
+/// * `path` - The path to the profile.json file
+pub fn load_profiles(path: &PathBuf) -> Vec<Profile> {
+    check_path(path);
+
+    let (profiles, migrated) = match persistence::read_json(path, parse_profiles) {
+        Ok(loaded) => loaded,
+        Err(e @ JunimoError::Unsupported(_)) => {
+            // The file belongs to a newer version of Junimo, so it is kept and never saved over
+            println!("{}", e);
+            (vec![default_profile()], false)
+        }
+        Err(e) => {
+            // The broken file was moved aside, so start over with the default profile
+            println!("{}", e);
+            let profiles = vec![default_profile()];
+            if let Err(e) = save_profiles(&profiles, path) {
+                println!("Failed to reset profile.json: {}", e);
+            }
+            (profiles, false)
+        }
+    };
+    if migrated {
+        if let Err(e) = save_profiles(&profiles, path) {
+            println!("Failed to save the migrated profile.json: {}", e);
+        }
+    }
+    profiles
 }
 
-pub fn save_profiles(profiles: &Vec<Profile>, path: &PathBuf) {
-    let json = serde_json::to_string(&profiles).unwrap();
-    let mut file = File::create(path).expect("Fail");
-    file.write_all(json.as_bytes()).unwrap();
+/// Gets the infos of a pinned mod version out of the manifest of its folder.
+/// If the manifest can't be read, the installed infos are used with the pinned version.
+///
+/// * `installed` - The infos of the installed version
+/// * `store` - The folder the mods are installed in
+/// * `version` - The pinned version
+fn pinned_mod_info(installed: &ModInfo, store: &Path, version: &str) -> ModInfo {
+    let manifest = mod_store::find_version(store, &installed.folder_name(), version)
+        .map(|dir| dir.join("manifest.json"))
+        .and_then(|path| mod_installation::get_manifest(&path).ok());
+
+    let mut mod_info = match manifest {
+        Some(manifest) => {
+            let pinned = mod_installation::mod_info_from_manifest(manifest, installed.group.clone());
+            // Nexus infos and the compatibility state belong to the mod, not to a version
+            ModInfo {
+                name: pinned.name,
+                dependencies: pinned.dependencies,
+                update_keys: pinned.update_keys,
+                update_version: None,
+                update_url: None,
+                ..installed.clone()
+            }
+        }
+        None => installed.clone(),
+    };
+    mod_info.version = version.to_string();
+    mod_info
 }
 
-#[command]
-pub async fn get_current_profile(path: PathBuf) -> Profile {
-    check_path(&path);
+/// Gets the profile a new profile.json starts with
+fn default_profile() -> Profile {
+    Profile {
+        name: "Default".to_string(),
+        mods: Vec::new(),
+        currently: true,
+        configs: None,
+        smapi_version: None,
+    }
+}
 
-    let data_raw = fs::read_to_string(path).unwrap();
-    let data = data_raw.as_str();
-    let profiles: Vec<Profile> = serde_json::from_str(data).unwrap();
+/// Parses the content of a profile.json file. Files of older versions are migrated to the current version.
+///
+/// * `data` - The content of the file
+///
+/// # Returns the profiles and whether they were migrated
+pub fn parse_profiles(data: &str) -> Result<(Vec<Profile>, bool), JunimoError> {
+    let value: serde_json::Value = serde_json::from_str(data)?;
+
+    // Version 1 didn't have a version yet and was a plain list of profiles
+    if value.is_array() {
+        let legacy: Vec<LegacyProfile> = serde_json::from_value(value)?;
+        return Ok((legacy.into_iter().map(migrate_legacy_profile).collect(), true));
+    }
 
-    let config = config::get_config(paths::config_path());
+    check_version(value.get("version").and_then(|version| version.as_u64()))?;
+    let file: ProfileFile = serde_json::from_value(value)?;
+    Ok((file.profiles, file.version < PROFILE_SCHEMA_VERSION))
+}
 
-    let mut return_profile: Profile = profiles[0].clone();
-    for profile in profiles {
-        if profile.currently {
-            let mut new_profile = profile;
-            if config.activate_requirements.is_none() || config.activate_requirements.unwrap() {
-                new_profile.mods = mods::check_dependencies(new_profile.clone().mods);
-            }
+/// Checks if a profile.json of the given version can be read and written
+///
+/// * `version` - The version of the file, None for files without a version
+///
+/// # Returns an error if the file belongs to a newer version of Junimo
+fn check_version(version: Option<u64>) -> Result<(), JunimoError> {
+    match version {
+        Some(version) if version > PROFILE_SCHEMA_VERSION as u64 => Err(JunimoError::Unsupported(format!(
+            "profile.json has version {}, but only version {} is supported",
+            version, PROFILE_SCHEMA_VERSION
+        ))),
+        _ => Ok(()),
+    }
+}
 
-            /*match compatibility::get_compability(app.clone(), new_profile.clone().mods).await {
-                Some(compability) => {
-                    new_profile.mods = compability;
-                }
-                None => {}
-            }*/
+/// Replaces the mod copies of a version 1 profile with references, without duplicates
+fn migrate_legacy_profile(profile: LegacyProfile) -> Profile {
+    let mut seen = HashSet::new();
+    Profile {
+        name: profile.name,
+        mods: profile
+            .mods
+            .iter()
+            .filter(|mod_info| seen.insert(mod_info.key()))
+            .map(ModReference::new)
+            .collect(),
+        currently: profile.currently,
+        configs: profile.configs,
+        smapi_version: None,
+    }
+}
 
-            return_profile = new_profile.clone();
-        }
+/// Saves the profiles to a profile.json file. Only the state store saves the profiles of the app.
+///
+/// * `profiles` - The profiles to save
+/// * `path` - The path to the profile.json file
+pub fn save_profiles(profiles: &Vec<Profile>, path: &PathBuf) -> Result<(), JunimoError> {
+    // A profile.json of a newer version of Junimo would lose everything this version doesn't know
+    let saved_version = fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str::<serde_json::Value>(&data).ok())
+        .and_then(|value| value.get("version").and_then(|version| version.as_u64()));
+    check_version(saved_version)?;
+
+    let file = ProfileFile {
+        version: PROFILE_SCHEMA_VERSION,
+        profiles: profiles.clone(),
+    };
+    persistence::write_json(path, &file)
+}
+
+/// Gets the profile that is currently used
+///
+/// * `profiles` - All profiles
+///
+/// # Returns the first profile if none is marked as current and an empty profile if there are no profiles
+pub fn current_profile(profiles: &Vec<Profile>) -> Profile {
+    profiles
+        .iter()
+        .find(|profile| profile.currently)
+        .or(profiles.first())
+        .cloned()
+        .unwrap_or_else(|| Profile {
+            name: "Default".to_string(),
+            mods: Vec::new(),
+            currently: true,
+            configs: None,
+            smapi_version: None,
+        })
+}
+
+#[command]
+pub async fn get_current_profile<R: Runtime>(
+    handle: tauri::AppHandle<R>,
+    path: PathBuf,
+) -> Result<ResolvedProfile, JunimoError> {
+    let store = handle.state::<AppState>().store.clone();
+    let profile = current_profile(&store.profiles_at(&path));
+    let registry = store.mods();
+
+    let config = config::load_config(paths::config_path());
+
+    let mut return_profile = profile.resolve(&registry);
+    if profile.currently && config.activate_requirements.unwrap_or(true) {
+        return_profile.mods = mods::check_dependencies(return_profile.clone().mods);
     }
+
+    /*match compatibility::get_compability(app.clone(), return_profile.clone().mods).await {
+        Some(compability) => {
+            return_profile.mods = compability;
+        }
+        None => {}
+    }*/
+
     return_profile
         .mods
         .sort_by(|info1, info2| info1.name.cmp(&info2.name));
 
-    return_profile
+    Ok(return_profile)
 }
 
 pub fn check_path(path: &PathBuf) -> bool {
-    if !path.exists() {
-        let mut profiles: Vec<Profile> = Vec::new();
-        let profile = Profile {
-            name: "Default".to_string(),
-            mods: Vec::new(),
-            currently: true,
-        };
-        profiles.push(profile);
-        save_profiles(&profiles, &paths::profile_path());
+    if !persistence::exists(path) {
+        let profiles = vec![default_profile()];
+        if let Err(e) = save_profiles(&profiles, path) {
+            println!("Failed to create profile.json: {}", e);
+        }
         return false;
     }
     true
@@ -102,32 +363,36 @@ pub fn change_current_profile<R: Runtime>(
     handle: tauri::AppHandle<R>,
     name: &str,
     path: PathBuf,
-) -> Vec<Profile> {
-    let profiles = get_profiles(path.clone());
-
-    let mut new_profiles: Vec<Profile> = Vec::new();
-    for profile in profiles {
-        if profile.name == name {
-            let new_profile = Profile {
-                name: profile.name,
-                mods: profile.mods,
-                currently: true,
-            };
-            new_profiles.push(new_profile);
-        } else {
-            let new_profile = Profile {
-                name: profile.name,
-                mods: profile.mods,
-                currently: false,
-            };
-            new_profiles.push(new_profile);
+) -> Result<Vec<Profile>, JunimoError> {
+    let store = handle.state::<AppState>().store.clone();
+    store.update_profiles_at(&handle, &path, |profiles| {
+        for profile in profiles.iter_mut() {
+            profile.currently = profile.name == name;
         }
-    }
-    handle
-        .emit("profile-update", &new_profiles)
-        .expect("Failed to emit event");
-    save_profiles(&new_profiles, &path);
-    new_profiles
+        profiles.clone()
+    })
+}
+
+/// Tauri command to pin a profile to a SMAPI version
+///
+/// * `handle` - The app handle
+/// * `name` - The name of the profile
+/// * `version` - The SMAPI version the profile needs or None to allow any version
+///
+/// # Returns the changed profiles
+#[command]
+pub fn set_smapi_version<R: Runtime>(
+    handle: tauri::AppHandle<R>,
+    name: &str,
+    version: Option<String>,
+) -> Result<Vec<Profile>, JunimoError> {
+    let store = handle.state::<AppState>().store.clone();
+    store.update_profiles(&handle, |profiles| {
+        if let Some(profile) = profiles.iter_mut().find(|profile| profile.name == name) {
+            profile.smapi_version = version;
+        }
+        profiles.clone()
+    })
 }
 
 #[command]
@@ -135,29 +400,22 @@ pub fn add_profile<R: Runtime>(
     handle: tauri::AppHandle<R>,
     name: &str,
     path: PathBuf,
-) -> Vec<Profile> {
-    let profiles = get_profiles(path.clone());
-
-    let mut new_profiles: Vec<Profile> = Vec::new();
-    for profile in profiles {
+) -> Result<Vec<Profile>, JunimoError> {
+    let store = handle.state::<AppState>().store.clone();
+    store.update_profiles_at(&handle, &path, |profiles| {
+        for profile in profiles.iter_mut() {
+            profile.currently = false;
+        }
         let new_profile = Profile {
-            name: profile.name,
-            mods: profile.mods,
-            currently: false,
+            name: name.to_string(),
+            mods: Vec::new(),
+            currently: true,
+            configs: None,
+            smapi_version: None,
         };
-        new_profiles.push(new_profile);
-    }
-    let new_profile = Profile {
-        name: name.to_string(),
-        mods: Vec::new(),
-        currently: true,
-    };
-    new_profiles.push(new_profile);
-    handle
-        .emit("profile-update", &new_profiles)
-        .expect("Failed to emit event");
-    save_profiles(&new_profiles, &path);
-    new_profiles
+        profiles.push(new_profile);
+        profiles.clone()
+    })
 }
 
 #[command]
@@ -165,44 +423,37 @@ pub fn remove_profile<R: Runtime>(
     handle: tauri::AppHandle<R>,
     name: &str,
     path: PathBuf,
-) -> Vec<Profile> {
-    let profiles = get_profiles(path.clone());
-
-    let mut new_profiles: Vec<Profile> = Vec::new();
-    for profile in profiles {
-        if profile.name != name {
-            new_profiles.push(profile);
-        }
-    }
-    handle
-        .emit("profile-update", &new_profiles)
-        .expect("Failed to emit event");
-    save_profiles(&new_profiles, &path);
-    new_profiles
+) -> Result<Vec<Profile>, JunimoError> {
+    let store = handle.state::<AppState>().store.clone();
+    store.update_profiles_at(&handle, &path, |profiles| {
+        profiles.retain(|profile| profile.name != name);
+        profiles.clone()
+    })
 }
 
 #[command]
-pub fn duplicate_profile(handle: tauri::AppHandle, from: String, name: String) -> Vec<Profile> {
-    let profiles = get_profiles(paths::profile_path());
-
-    let mut new_profiles: Vec<Profile> = Vec::new();
-    let mut duplicate_profile: Profile = Profile {
-        name,
-        mods: vec![],
-        currently: false,
-    };
-    for profile in profiles {
-        if (profile.name == from) {
-            duplicate_profile.mods = profile.clone().mods;
+pub fn duplicate_profile(
+    handle: tauri::AppHandle,
+    from: String,
+    name: String,
+) -> Result<Vec<Profile>, JunimoError> {
+    let store = handle.state::<AppState>().store.clone();
+    store.update_profiles(&handle, |profiles| {
+        let mut duplicate_profile: Profile = Profile {
+            name,
+            mods: vec![],
+            currently: false,
+            configs: None,
+            smapi_version: None,
+        };
+        if let Some(profile) = profiles.iter().find(|profile| profile.name == from) {
+            duplicate_profile.mods = profile.mods.clone();
+            duplicate_profile.configs = profile.configs.clone();
+            duplicate_profile.smapi_version = profile.smapi_version.clone();
         }
-        new_profiles.push(profile);
-    }
-    new_profiles.push(duplicate_profile);
-    save_profiles(&new_profiles, &paths::profile_path());
-    handle
-        .emit("profile-update", &new_profiles)
-        .expect("Failed to emit event");
-    new_profiles
+        profiles.push(duplicate_profile);
+        profiles.clone()
+    })
 }
 
 #[command]
@@ -211,55 +462,137 @@ pub fn modify_profile<R: Runtime>(
     name: &str,
     new_name: &str,
     path: PathBuf,
-) -> Vec<Profile> {
-    let profiles = get_profiles(path.clone());
-
-    let mut new_profiles: Vec<Profile> = Vec::new();
-    for profile in profiles {
-        if profile.name == name {
-            let new_profile = Profile {
-                name: new_name.parse().unwrap(),
-                mods: profile.mods,
-                currently: profile.currently,
-            };
-            new_profiles.push(new_profile);
-        } else {
-            new_profiles.push(profile);
+) -> Result<Vec<Profile>, JunimoError> {
+    let store = handle.state::<AppState>().store.clone();
+    store.update_profiles_at(&handle, &path, |profiles| {
+        for profile in profiles.iter_mut().filter(|profile| profile.name == name) {
+            profile.name = new_name.to_string();
         }
-    }
-    handle
-        .emit("profile-update", &new_profiles)
-        .expect("Failed to emit event");
-    save_profiles(&new_profiles, &path);
-    new_profiles
+        profiles.clone()
+    })
 }
 
+/// Tauri command to set the enabled mods of a profile. Pinned versions of mods that stay in the
+/// profile are kept, as well as the disabled mods.
+///
+/// * `handle` - The app handle
+/// * `name` - The name of the profile
+/// * `mods` - The enabled mods
+/// * `path` - The path to the profile.json file
 #[command]
-pub fn change_profile_mods(name: &str, mut mods: Vec<ModInfo>, path: PathBuf) {
-    let profiles = get_profiles(path.clone());
-
+pub fn change_profile_mods<R: Runtime>(
+    handle: tauri::AppHandle<R>,
+    name: &str,
+    mut mods: Vec<ModInfo>,
+    path: PathBuf,
+) -> Result<(), JunimoError> {
     let mut seen = HashSet::new();
-    mods.retain(|mod_info| seen.insert(mod_info.name.clone())); // Retains only if name is new to the set
-
-    let mut new_profiles: Vec<Profile> = Vec::new();
-    for profile in profiles {
-        if profile.name == name {
-            let new_profile = Profile {
-                name: profile.name,
-                mods: mods.clone(),
-                currently: profile.currently,
-            };
-            new_profiles.push(new_profile);
-        } else {
-            new_profiles.push(profile);
+    mods.retain(|mod_info| seen.insert(mod_info.key())); // Retains only if the mod is new to the set
+
+    let store = handle.state::<AppState>().store.clone();
+    store.update_profiles_at(&handle, &path, |profiles| {
+        for profile in profiles.iter_mut().filter(|profile| profile.name == name) {
+            let mut references: Vec<ModReference> = mods
+                .iter()
+                .map(|mod_info| {
+                    match profile.mods.iter().find(|reference| reference.is_mod(mod_info)) {
+                        Some(reference) => ModReference {
+                            enabled: true,
+                            ..reference.clone()
+                        },
+                        None => ModReference::new(mod_info),
+                    }
+                })
+                .collect();
+            references.extend(profile.mods.iter().filter(|reference| {
+                !reference.enabled && !mods.iter().any(|mod_info| reference.is_mod(mod_info))
+            }).cloned());
+
+            profile.mods = references;
         }
+    })
+}
+
+/// Tauri command to pin a mod of a profile to an installed version
+///
+/// * `name` - The name of the profile
+/// * `id` - The unique id of the mod, or the name of a mod without unique id
+/// * `version` - The version to use. None always uses the newest installed version
+///
+/// # Returns the changed profiles
+#[command]
+pub fn pin_mod_version<R: Runtime>(
+    handle: tauri::AppHandle<R>,
+    name: &str,
+    id: &str,
+    version: Option<String>,
+) -> Result<Vec<Profile>, JunimoError> {
+    let store = handle.state::<AppState>().store.clone();
+    if let Some(version) = &version {
+        let mod_info = store
+            .mods()
+            .into_iter()
+            .find(|mod_info| mod_info.has_key(id))
+            .ok_or_else(|| JunimoError::NotFound(format!("Mod {}", id)))?;
+        check_installed_version(&paths::mod_path(), &mod_info, version)?;
     }
-    save_profiles(&new_profiles, &path);
+
+    store.update_profiles(&handle, |profiles| {
+        for profile in profiles.iter_mut().filter(|profile| profile.name == name) {
+            for reference in profile
+                .mods
+                .iter_mut()
+                .filter(|reference| reference.unique_id.eq_ignore_ascii_case(id))
+            {
+                reference.version = version.clone();
+            }
+        }
+        profiles.clone()
+    })
+}
+
+/// Checks if a version of a mod is installed, so a profile can be pinned to it
+///
+/// * `store` - The folder the mods are installed in
+/// * `mod_info` - The installed mod
+/// * `version` - The version to pin
+///
+/// # Returns an error if the version isn't installed
+fn check_installed_version(store: &Path, mod_info: &ModInfo, version: &str) -> Result<(), JunimoError> {
+    let folder = mod_store::version_folder(version);
+    match mod_store::versions(store, &mod_info.folder_name()).contains(&folder) {
+        true => Ok(()),
+        false => Err(JunimoError::NotFound(format!("Version {} of {}", version, mod_info.name))),
+    }
+}
+
+/// Adds an installed mod to the current profile, if it isn't part of it yet
+///
+/// * `app_handle` - The app handle
+/// * `mod_info` - The mod to add
+///
+/// # Returns true if the mod was added
+pub fn add_to_current_profile<R: Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    mod_info: &ModInfo,
+) -> Result<bool, JunimoError> {
+    let store = app_handle.state::<AppState>().store.clone();
+    store.update_profiles(app_handle, |profiles| {
+        let mut added = false;
+        for profile in profiles.iter_mut().filter(|profile| profile.currently) {
+            if !profile.contains(mod_info) {
+                profile.mods.push(ModReference::new(mod_info));
+                added = true;
+            }
+        }
+        added
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use crate::app::app_state::AppState;
+    use std::fs;
     use tauri::test::mock_builder;
     use tempfile::tempdir;
 
@@ -342,10 +675,12 @@ mod tests {
             name: "Default".to_string(),
             mods: Vec::new(),
             currently: true,
+            configs: None,
+            smapi_version: None,
         };
         profiles.push(profile);
 
-        save_profiles(&profiles, &profile_path);
+        save_profiles(&profiles, &profile_path).unwrap();
         assert!(profile_path.exists());
     }
 
@@ -367,10 +702,12 @@ mod tests {
             name: "Default".to_string(),
             mods: Vec::new(),
             currently: true,
+            configs: None,
+            smapi_version: None,
         };
         profiles.push(profile);
 
-        save_profiles(&profiles, &profile_path);
+        save_profiles(&profiles, &profile_path).unwrap();
 
         let app = create_app(mock_builder());
         let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
@@ -416,16 +753,20 @@ mod tests {
             name: "Default".to_string(),
             mods: Vec::new(),
             currently: true,
+            configs: None,
+            smapi_version: None,
         };
         let test_profile = Profile {
             name: "Test".to_string(),
             mods: Vec::new(),
             currently: false,
+            configs: None,
+            smapi_version: None,
         };
         profiles.push(profile.clone());
         profiles.push(test_profile.clone());
 
-        save_profiles(&profiles, &profile_path);
+        save_profiles(&profiles, &profile_path).unwrap();
 
         let app = create_app(mock_builder());
         let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
@@ -472,15 +813,19 @@ mod tests {
             name: "Default".to_string(),
             mods: Vec::new(),
             currently: true,
+            configs: None,
+            smapi_version: None,
         };
         let test_profile = Profile {
             name: "Test".to_string(),
             mods: Vec::new(),
             currently: false,
+            configs: None,
+            smapi_version: None,
         };
         profiles.push(profile.clone());
 
-        save_profiles(&profiles, &profile_path);
+        save_profiles(&profiles, &profile_path).unwrap();
 
         let app = create_app(mock_builder());
         let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
@@ -527,16 +872,20 @@ mod tests {
             name: "Default".to_string(),
             mods: Vec::new(),
             currently: true,
+            configs: None,
+            smapi_version: None,
         };
         let test_profile = Profile {
             name: "Test".to_string(),
             mods: Vec::new(),
             currently: false,
+            configs: None,
+            smapi_version: None,
         };
         profiles.push(profile.clone());
         profiles.push(test_profile.clone());
 
-        save_profiles(&profiles, &profile_path);
+        save_profiles(&profiles, &profile_path).unwrap();
 
         let app = create_app(mock_builder());
         let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
@@ -584,16 +933,20 @@ mod tests {
             name: "Default".to_string(),
             mods: Vec::new(),
             currently: true,
+            configs: None,
+            smapi_version: None,
         };
         let test_profile = Profile {
             name: "Test".to_string(),
             mods: Vec::new(),
             currently: false,
+            configs: None,
+            smapi_version: None,
         };
         profiles.push(profile.clone());
         profiles.push(test_profile.clone());
 
-        save_profiles(&profiles, &profile_path);
+        save_profiles(&profiles, &profile_path).unwrap();
 
         let app = create_app(mock_builder());
         let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
@@ -637,16 +990,20 @@ mod tests {
             name: "Default".to_string(),
             mods: Vec::new(),
             currently: true,
+            configs: None,
+            smapi_version: None,
         };
         let test_profile = Profile {
             name: "Test".to_string(),
             mods: Vec::new(),
             currently: false,
+            configs: None,
+            smapi_version: None,
         };
         profiles.push(profile.clone());
         profiles.push(test_profile.clone());
 
-        save_profiles(&profiles, &profile_path);
+        save_profiles(&profiles, &profile_path).unwrap();
 
         let app = create_app(mock_builder());
         let webview = tauri::WebviewWindowBuilder::new(&app, "main", Default::default())
@@ -682,6 +1039,10 @@ mod tests {
             more_info: None,
             dependencies: None,
             group: None,
+            is_broken: None,
+            update_keys: None,
+            update_version: None,
+            update_url: None,
         };
 
         let mod_wrap = ModWrap {
@@ -704,9 +1065,125 @@ mod tests {
             },
         );
 
-        let loaded_profiles = get_profiles(profile_path.clone());
+        let loaded_profiles = load_profiles(&profile_path);
 
         assert!(res.is_ok());
         assert_eq!(loaded_profiles[1].mods.len(), 1);
     }
+
+    #[test]
+    fn test_migrate_legacy_profiles() {
+        let tmp_dir = tempdir().unwrap();
+        let profile_path = tmp_dir.path().join("profile.json");
+        let mod_json = |name: &str, unique_id: Option<&str>| {
+            serde_json::to_string(&ModInfo::for_test(name, unique_id, "1.0.0")).unwrap()
+        };
+        fs::write(
+            &profile_path,
+            format!(
+                "[{{\"name\":\"Default\",\"mods\":[{},{},{}],\"currently\":true,\"configs\":null}}]",
+                mod_json("Content Patcher", Some("Pathoschild.ContentPatcher")),
+                mod_json("Legacy", None),
+                mod_json("Content Patcher", Some("Pathoschild.ContentPatcher"))
+            ),
+        )
+        .unwrap();
+
+        let profiles = load_profiles(&profile_path);
+        let ids: Vec<String> = profiles[0].mods.iter().map(|r| r.unique_id.clone()).collect();
+        assert_eq!(ids, vec!["Pathoschild.ContentPatcher", "Legacy"]);
+        assert!(profiles[0].mods.iter().all(|r| r.enabled && r.version.is_none()));
+
+        // The migrated file is saved in the current format
+        let (_, migrated) = parse_profiles(&fs::read_to_string(&profile_path).unwrap()).unwrap();
+        assert!(!migrated);
+        assert!(parse_profiles("{\"version\":99,\"profiles\":[]}").is_err());
+    }
+
+    #[test]
+    fn test_newer_profiles_are_kept() {
+        let tmp_dir = tempdir().unwrap();
+        let profile_path = tmp_dir.path().join("profile.json");
+        let newer = "{\"version\":99,\"profiles\":[],\"groups\":[]}";
+        fs::write(&profile_path, newer).unwrap();
+
+        let profiles = load_profiles(&profile_path);
+        assert_eq!(profiles[0].name, "Default");
+        assert_eq!(fs::read_to_string(&profile_path).unwrap(), newer);
+        assert_eq!(save_profiles(&profiles, &profile_path).unwrap_err().kind(), "Unsupported");
+        assert_eq!(fs::read_to_string(&profile_path).unwrap(), newer);
+    }
+
+    #[test]
+    fn test_change_profile_mods_keeps_references() {
+        let tmp_dir = tempdir().unwrap();
+        let profile_path = tmp_dir.path().join("profile.json");
+        let pinned = ModReference {
+            unique_id: "Author.Pinned".to_string(),
+            version: Some("1.0.0".to_string()),
+            enabled: true,
+        };
+        let disabled = ModReference {
+            unique_id: "Author.Disabled".to_string(),
+            version: None,
+            enabled: false,
+        };
+        save_profiles(
+            &vec![Profile {
+                name: "Default".to_string(),
+                mods: vec![pinned.clone(), disabled.clone()],
+                currently: true,
+                configs: None,
+                smapi_version: None,
+            }],
+            &profile_path,
+        )
+        .unwrap();
+
+        let mod_info = |name: &str, unique_id: &str| ModInfo::for_test(name, Some(unique_id), "2.0.0");
+        let registry = vec![mod_info("Pinned", "Author.Pinned"), mod_info("New", "Author.New")];
+        let app = create_app(mock_builder());
+        change_profile_mods(app.handle().clone(), "Default", registry.clone(), profile_path.clone()).unwrap();
+
+        let profile = &load_profiles(&profile_path)[0];
+        assert_eq!(profile.mods.len(), 3);
+        assert_eq!(profile.mods[0], pinned);
+        assert_eq!(profile.mods[1].unique_id, "Author.New");
+        assert_eq!(profile.mods[2], disabled);
+        assert_eq!(profile.resolve_mods_in(&registry, tmp_dir.path()).len(), 2);
+    }
+
+    #[test]
+    fn test_resolve_pinned_version() {
+        let tmp_dir = tempdir().unwrap();
+        let store = tmp_dir.path();
+        let manifest = |name: &str, version: &str, dependency: &str| {
+            format!(
+                "{{\"Name\":\"{}\",\"Version\":\"{}\",\"UniqueID\":\"Author.Pinned\",\"Dependencies\":[{{\"UniqueID\":\"{}\"}}]}}",
+                name, version, dependency
+            )
+        };
+        let installed = mod_installation::mod_info_from_manifest(
+            serde_json::from_str(&manifest("Pinned", "2.0.0", "Author.New")).unwrap(),
+            None,
+        );
+        let version_dir = store.join("Author.Pinned").join("1.0.0");
+        fs::create_dir_all(&version_dir).unwrap();
+        fs::write(version_dir.join("manifest.json"), manifest("Pinned Old", "1.0.0", "Author.Old")).unwrap();
+
+        let mut profile = default_profile();
+        profile.mods.push(ModReference {
+            unique_id: "Author.Pinned".to_string(),
+            version: Some("1.0.0".to_string()),
+            enabled: true,
+        });
+        let resolved = profile.resolve_mods_in(&vec![installed.clone()], store);
+        assert_eq!(resolved[0].name, "Pinned Old");
+        assert_eq!(resolved[0].version, "1.0.0");
+        assert_eq!(resolved[0].dependencies.as_ref().unwrap()[0].unique_id, "Author.Old");
+
+        // Only installed versions can be pinned
+        assert!(check_installed_version(store, &installed, "1.0.0").is_ok());
+        assert_eq!(check_installed_version(store, &installed, "1.5.0").unwrap_err().kind(), "NotFound");
+    }
 }
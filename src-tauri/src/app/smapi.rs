@@ -1,25 +1,26 @@
+use std::fs;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use tauri::{command, Manager, WebviewUrl};
 use walkdir::WalkDir;
-use zip::ZipArchive;
-use crate::app::utility::{paths, zips};
+use crate::app::{config, smapi_manager};
+use crate::app::error::JunimoError;
+use crate::app::utility::{http_download, paths, zips};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SmapiProcess {
     pub started: bool,
     pub download_progress: u64,
-    pub size: u64,
+    pub size: Option<u64>,
     pub download_finished: bool,
     pub installation_started: bool,
     pub installation_finished: bool,
 }
 
 impl SmapiProcess {
-    pub fn new(started: bool, size: u64, installation_started: bool) -> Self {
+    pub fn new(started: bool, size: Option<u64>, installation_started: bool) -> Self {
         SmapiProcess {
             started,
             download_progress: 0,
@@ -32,7 +33,7 @@ impl SmapiProcess {
 }
 
 #[command]
-pub async fn open_smapi(handle: tauri::AppHandle) {
+pub async fn open_smapi(handle: tauri::AppHandle) -> Result<(), JunimoError> {
     #[cfg(target_os = "windows")]
     tauri::WebviewWindowBuilder::new(&handle, "Smapi", WebviewUrl::App("/smapi".into()))
         .title("Smapi")
@@ -40,8 +41,7 @@ pub async fn open_smapi(handle: tauri::AppHandle) {
         .maximizable(false)
         .inner_size(700.0, 350.0)
         .transparent(true)
-        .build()
-        .unwrap();
+        .build()?;
 
     #[cfg(target_os = "unix")]
     tauri::WebviewWindowBuilder::new(&handle, "Smapi", WebviewUrl::App("/smapi".into()))
@@ -49,89 +49,106 @@ pub async fn open_smapi(handle: tauri::AppHandle) {
         .resizable(false)
         .maximizable(false)
         .inner_size(600.0, 350.0)
-        .build()
-        .unwrap();
+        .build()?;
+    Ok(())
 }
 
 #[command]
-pub async fn download_smapi(handle: tauri::AppHandle, link: String) -> Result<(), String> {
-    let client = reqwest::Client::new();
-    let mut response = client.get(link).send().await.unwrap();
-    if !response.status().is_success() {
-        panic!("Received non-successful status code: {}", response.status());
-    }
-
-    let total_size = response
-        .content_length()
-        .expect("Failed to get content length");
+pub async fn download_smapi(handle: tauri::AppHandle, link: String) -> Result<(), JunimoError> {
+    let mut progress: SmapiProcess = SmapiProcess::new(true, None, false);
+    handle.emit("smapi_progress", &progress)?;
+
+    // Every installer gets its own partial file, so an interrupted download is only continued with the same release
+    let temp_path = installer_path(&link);
+    discard_other_installers(&temp_path);
+
+    http_download::download_resumable(
+        &link,
+        &temp_path,
+        |current, size| {
+            progress.download_progress = current;
+            progress.size = size;
+
+            // Emit the updated download object to the frontend
+            let _ = handle.emit("smapi_progress", &progress);
+        },
+        || false,
+    )
+    .await
+    .map_err(|e| JunimoError::Network(e.to_string()))?;
 
-    let mut progress: SmapiProcess = SmapiProcess::new(true, total_size, false);
-    handle.emit("smapi_progress", &progress).unwrap();
-
-    let zip_path = "smapi.zip".to_string();
-    let mut temp_path = paths::temp_path();
-    temp_path.push(&zip_path);
-    let mut file = File::create(&temp_path).unwrap();
+    progress.download_finished = true;
+    handle.emit("smapi_progress", &progress)?;
 
-    let mut downloaded: u64 = 0;
-    let mut stream = response.bytes_stream();
+    let result = install_smapi(&temp_path).await;
+    http_download::discard_partial(&temp_path);
 
+    result
+}
 
-    while let Some(item) = stream.next().await {
-        let chunk = item.expect("Failed to read chunk");
-        file.write_all(&chunk).unwrap();
-        let new = downloaded + (chunk.len() as u64);
-        progress.download_progress = new;
-        downloaded = new;
+/// Gets the path the installer of a download url is saved to. The name contains a hash of the url
+/// and the file name of the installer with its version.
+///
+/// * `link` - The download url of the installer
+fn installer_path(link: &str) -> PathBuf {
+    let file_name: String = link
+        .split(['?', '#'])
+        .next()
+        .and_then(|url| url.rsplit('/').next())
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+        .collect();
+    let file_name = if file_name.ends_with(".zip") { file_name } else { "installer.zip".to_string() };
+    paths::temp_path().join(format!("smapi-{:x}-{}", md5::compute(link), file_name))
+}
 
-        // Emit the updated download object to the frontend
-        handle.emit("smapi_progress", &progress).unwrap();
+/// Removes the partial installers of other releases
+///
+/// * `keep` - The installer that is downloaded now
+fn discard_other_installers(keep: &Path) {
+    let entries = match fs::read_dir(paths::temp_path()) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with("smapi") && name.ends_with(".zip") && entry.path() != keep {
+            http_download::discard_partial(&entry.path());
+        }
     }
-
-    progress.download_progress = progress.size;
-    progress.download_finished = true;
-    handle.emit("smapi_progress", &progress).unwrap();
-
-    // Flushes and deletes the download object
-    file.flush().unwrap();
-    drop(file);
-
-    install_smapi().await;
-
-    Ok(())
 }
 
-pub async fn install_smapi() {
-    let mut temp_path = paths::temp_path();
-    temp_path = temp_path.join("smapi.zip");
+/// Installs or updates SMAPI from the downloaded installer. The installer is unpacked into the appdata folder
+/// and the install.dat for the current platform is installed into the game folder.
+///
+/// * `archive` - The downloaded installer
+pub async fn install_smapi(archive: &Path) -> Result<(), JunimoError> {
+    let game_path = paths::get_game_path();
+    if game_path == paths::appdata_path() {
+        return Err(JunimoError::GameNotFound);
+    }
 
-    let mut desination = paths::appdata_path();
-    desination = desination.join("smapi");
+    let smapi_path = smapi_manager::smapi_path();
+    let destination = smapi_path.join("installer");
+    if destination.exists() {
+        fs::remove_dir_all(&destination)?;
+    }
 
-    let file = File::open(&temp_path).unwrap();
-    let zip_archive = zip::ZipArchive::new(file).unwrap();
+    let file = File::open(archive)?;
+    let zip_archive = zip::ZipArchive::new(file)?;
 
-    let unpack_result = zips::unpack_smapi(zip_archive, &desination);
+    let limits = config::extraction_limits();
+    let installer = zips::unpack_smapi(zip_archive, &destination, limits).map_err(JunimoError::Archive)?;
 
     #[cfg(target_os = "windows")]
-    let dat_path = desination.join(unpack_result.unwrap()).join("internal").join("windows").join("install.dat");
+    let dat_path = destination.join(installer).join("internal").join("windows").join("install.dat");
     #[cfg(target_os = "linux")]
-    let dat_path = desination.join(unpack_result.unwrap()).join("internal").join("linux").join("install.dat");
+    let dat_path = destination.join(installer).join("internal").join("linux").join("install.dat");
     #[cfg(target_os = "macos")]
-    let dat_path = desination.join(unpack_result.unwrap()).join("internal").join("macOS").join("install.dat");
-
-    let file = File::open(&dat_path).unwrap();
-    let buf_reader = BufReader::new(file);
-
-    let archive = ZipArchive::new(buf_reader).unwrap();
-    let unpack_result = zips::unpack_smapi(archive, &paths::get_game_path());
+    let dat_path = destination.join(installer).join("internal").join("macOS").join("install.dat");
 
-    match unpack_result {
-        Ok(_) => {
-
-        }
-        Err(e) => {
-            println!("Failed to unpack smapi: {}", e);
-        }
-    }
-}
\ No newline at end of file
+    smapi_manager::install_bundle(&dat_path, &game_path, &smapi_path, limits)?;
+    fs::remove_dir_all(&destination)?;
+    Ok(())
+}
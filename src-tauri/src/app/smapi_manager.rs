@@ -0,0 +1,543 @@
+use std::fs;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{command, AppHandle};
+use walkdir::WalkDir;
+use zip::ZipArchive;
+
+use crate::app::error::JunimoError;
+use crate::app::utility::extraction::ExtractionLimits;
+use crate::app::utility::semantic_version::SemanticVersion;
+use crate::app::utility::{paths, persistence, version_extractor, zips};
+use crate::app::{config, console};
+
+// SMAPI is installed from the install.dat inside of the installer, which contains the files for the game folder.
+// Every game file that gets replaced is copied into a backup first and every installed file is recorded,
+// so SMAPI can be removed again and the game folder looks like before.
+
+/// The files SMAPI can't run without, relative to the game folder
+#[cfg(target_os = "windows")]
+const REQUIRED_FILES: [&str; 4] = [
+    "StardewModdingAPI.exe",
+    "StardewModdingAPI.dll",
+    "smapi-internal/SMAPI.Toolkit.dll",
+    "smapi-internal/config.json",
+];
+#[cfg(not(target_os = "windows"))]
+const REQUIRED_FILES: [&str; 3] = [
+    "StardewModdingAPI.dll",
+    "smapi-internal/SMAPI.Toolkit.dll",
+    "smapi-internal/config.json",
+];
+
+/// The launcher script SMAPI ships for Linux and macOS, which replaces the launcher of the game
+const UNIX_LAUNCHER: &str = "unix-launcher.sh";
+/// The launcher of the game on Linux and macOS
+const GAME_LAUNCHER: &str = "StardewValley";
+/// The name the official installer gives the original launcher
+const GAME_LAUNCHER_BACKUP: &str = "StardewValley-original";
+
+/// The state of the SMAPI installation in the game folder
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SmapiStatus {
+    /// The installed version or None if SMAPI isn't installed
+    pub version: Option<String>,
+    /// The required files that are missing
+    pub missing_files: Vec<String>,
+    /// Whether the replaced game files were backed up by Junimo
+    pub backup: bool,
+    /// Whether the install.dat of the installed version is kept to repair the installation
+    pub repairable: bool,
+}
+
+/// The files Junimo installed into the game folder, saved as smapi.json
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+struct SmapiRecord {
+    /// Every file SMAPI added to or replaced in the game folder
+    installed: Vec<String>,
+    /// The game files that were replaced and are kept in the backup folder
+    replaced: Vec<String>,
+}
+
+impl SmapiRecord {
+    /// Backs up a game file before it gets replaced. Files that were installed by SMAPI or
+    /// are already backed up are skipped, so the backup always holds the original game files.
+    /// Files of an installation by the official installer are SMAPI files and aren't backed up either,
+    /// instead of its launcher the original launcher it kept is backed up.
+    ///
+    /// * `game_path` - The game folder
+    /// * `backup_path` - The backup folder
+    /// * `file` - The path of the file relative to the game folder
+    fn back_up(&mut self, game_path: &Path, backup_path: &Path, file: &str) -> Result<(), JunimoError> {
+        if self.installed.iter().any(|installed| installed == file) {
+            return Ok(());
+        }
+
+        let original_launcher = game_path.join(GAME_LAUNCHER_BACKUP);
+        let target = if file == GAME_LAUNCHER && original_launcher.is_file() {
+            original_launcher
+        } else {
+            game_path.join(file)
+        };
+        if target.is_file() && !is_smapi_file(file) {
+            let backup = backup_path.join(file);
+            if let Some(parent) = backup.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(&target, &backup)?;
+            self.replaced.push(file.to_string());
+        }
+        self.installed.push(file.to_string());
+        Ok(())
+    }
+}
+
+/// Checks if a file belongs to SMAPI itself and not to the game
+///
+/// * `file` - The path of the file relative to the game folder
+fn is_smapi_file(file: &str) -> bool {
+    file.starts_with("smapi-internal/") || file.starts_with("StardewModdingAPI")
+}
+
+/// Gets the folder Junimo keeps the SMAPI installer, the backup and the record in
+pub fn smapi_path() -> PathBuf {
+    paths::appdata_path().join("smapi")
+}
+
+/// Gets the game folder
+///
+/// # Returns the game folder or an error if it isn't set
+fn game_path() -> Result<PathBuf, JunimoError> {
+    let game_path = paths::get_game_path();
+    if game_path == paths::appdata_path() || !game_path.exists() {
+        return Err(JunimoError::GameNotFound);
+    }
+    Ok(game_path)
+}
+
+/// Loads the record of the installed files
+///
+/// * `smapi_path` - The folder of the record
+fn load_record(smapi_path: &Path) -> SmapiRecord {
+    let path = smapi_path.join("smapi.json");
+    if !persistence::exists(&path) {
+        return SmapiRecord::default();
+    }
+    persistence::read_json(&path, |data| Ok(serde_json::from_str(data)?))
+        .unwrap_or_default()
+}
+
+/// Gets the state of the SMAPI installation
+///
+/// * `game_path` - The game folder
+/// * `smapi_path` - The folder of the installer, the backup and the record
+/// * `version` - The version of the installed StardewModdingAPI.dll
+pub fn status(game_path: &Path, smapi_path: &Path, version: Option<String>) -> SmapiStatus {
+    let missing_files = REQUIRED_FILES
+        .iter()
+        .filter(|file| !game_path.join(file).exists())
+        .map(|file| file.to_string())
+        .collect();
+
+    SmapiStatus {
+        version,
+        missing_files,
+        backup: !load_record(smapi_path).replaced.is_empty(),
+        repairable: smapi_path.join("install.dat").exists(),
+    }
+}
+
+/// Installs or updates SMAPI from the install.dat of an installer. The replaced game files are backed up,
+/// afterwards the install.dat is kept to repair the installation.
+///
+/// * `bundle` - The install.dat for the current platform
+/// * `game_path` - The game folder
+/// * `smapi_path` - The folder of the installer, the backup and the record
+/// * `limits` - The limits the install.dat has to stay within
+///
+/// # Returns a Result with the success state
+pub fn install_bundle(
+    bundle: &Path,
+    game_path: &Path,
+    smapi_path: &Path,
+    limits: ExtractionLimits,
+) -> Result<(), JunimoError> {
+    fs::create_dir_all(smapi_path)?;
+    let staging = smapi_path.join("bundle");
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    let archive = ZipArchive::new(BufReader::new(File::open(bundle)?))?;
+    zips::unpack_smapi(archive, &staging, limits).map_err(JunimoError::Archive)?;
+
+    let backup_path = smapi_path.join("backup");
+    let mut record = load_record(smapi_path);
+    let mut files = Vec::new();
+    for entry in WalkDir::new(&staging).into_iter().filter_map(|e| e.ok()) {
+        if entry.file_type().is_file() {
+            let relative = entry
+                .path()
+                .strip_prefix(&staging)
+                .map_err(|e| JunimoError::Other(e.to_string()))?
+                .to_path_buf();
+            record.back_up(game_path, &backup_path, &relative.to_string_lossy().replace('\\', "/"))?;
+            files.push(relative);
+        }
+    }
+
+    // Everything is backed up and recorded before the first file is replaced, so a failed installation
+    // can still be uninstalled
+    let record_path = smapi_path.join("smapi.json");
+    persistence::write_json(&record_path, &record)?;
+    for relative in files {
+        let target = game_path.join(&relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(staging.join(&relative), &target)?;
+    }
+
+    post_install(game_path, &backup_path, &mut record)?;
+    persistence::write_json(&record_path, &record)?;
+
+    let kept_bundle = smapi_path.join("install.dat");
+    if bundle != kept_bundle {
+        fs::copy(bundle, &kept_bundle)?;
+    }
+    fs::remove_dir_all(&staging)?;
+    Ok(())
+}
+
+/// Runs the steps of the official installer after the files were copied
+///
+/// * `game_path` - The game folder
+/// * `backup_path` - The backup folder
+/// * `record` - The record of the installed files
+///
+/// # Returns a Result with the success state
+fn post_install(game_path: &Path, backup_path: &Path, record: &mut SmapiRecord) -> Result<(), JunimoError> {
+    // SMAPI needs the dependencies of the game to load native libraries
+    let game_deps = game_path.join("Stardew Valley.deps.json");
+    if game_deps.exists() {
+        record.back_up(game_path, backup_path, "StardewModdingAPI.deps.json")?;
+        fs::copy(&game_deps, game_path.join("StardewModdingAPI.deps.json"))?;
+    }
+
+    // On Linux and macOS the launcher of the game starts SMAPI, so Steam starts the game with mods
+    let launcher = game_path.join(UNIX_LAUNCHER);
+    if cfg!(not(target_os = "windows")) && launcher.exists() {
+        record.back_up(game_path, backup_path, GAME_LAUNCHER)?;
+        fs::copy(&launcher, game_path.join(GAME_LAUNCHER))?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        for executable in [GAME_LAUNCHER, "StardewModdingAPI"] {
+            let path = game_path.join(executable);
+            if record.installed.iter().any(|file| file == executable) && path.exists() {
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Removes SMAPI from the game folder and restores the backed up game files. An installation
+/// of the official installer is removed by the files it is known to add.
+///
+/// * `game_path` - The game folder
+/// * `smapi_path` - The folder of the installer, the backup and the record
+///
+/// # Returns a Result with the success state
+pub fn uninstall(game_path: &Path, smapi_path: &Path) -> Result<(), JunimoError> {
+    let record = load_record(smapi_path);
+    let backup_path = smapi_path.join("backup");
+
+    let mut installed = record.installed.clone();
+    if installed.is_empty() {
+        installed = REQUIRED_FILES.iter().map(|file| file.to_string()).collect();
+        installed.push("StardewModdingAPI".to_string());
+        installed.push("StardewModdingAPI.deps.json".to_string());
+        installed.push("StardewModdingAPI.runtimeconfig.json".to_string());
+    }
+
+    for file in installed.iter() {
+        let path = game_path.join(file);
+        if path.is_file() {
+            fs::remove_file(&path)?;
+        }
+        remove_empty_parents(game_path, &path);
+    }
+    // SMAPI writes logs and caches into its folder, so it has to be removed as a whole
+    let internal = game_path.join("smapi-internal");
+    if internal.exists() {
+        fs::remove_dir_all(&internal)?;
+    }
+
+    for file in record.replaced.iter() {
+        let target = game_path.join(file);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(backup_path.join(file), &target)?;
+    }
+
+    // The official installer keeps the original launcher next to the game, if Junimo updated
+    // that installation the original launcher was already restored from the backup
+    let original_launcher = game_path.join(GAME_LAUNCHER_BACKUP);
+    if record.installed.is_empty() && original_launcher.exists() {
+        fs::rename(&original_launcher, game_path.join(GAME_LAUNCHER))?;
+    } else if original_launcher.exists() {
+        fs::remove_file(&original_launcher)?;
+    }
+
+    if backup_path.exists() {
+        fs::remove_dir_all(&backup_path)?;
+    }
+    let _ = fs::remove_file(smapi_path.join("smapi.json"));
+    let _ = fs::remove_file(smapi_path.join("smapi.json.bak"));
+    let _ = fs::remove_file(smapi_path.join("install.dat"));
+    Ok(())
+}
+
+/// Removes the folders of a removed file, as long as they are empty and inside of the game folder
+///
+/// * `game_path` - The game folder
+/// * `path` - The removed file
+fn remove_empty_parents(game_path: &Path, path: &Path) {
+    let mut parent = path.parent();
+    while let Some(folder) = parent {
+        if folder == game_path || !folder.starts_with(game_path) || fs::remove_dir(folder).is_err() {
+            return;
+        }
+        parent = folder.parent();
+    }
+}
+
+/// Installs the kept install.dat again, which brings back missing or broken SMAPI files
+///
+/// * `game_path` - The game folder
+/// * `smapi_path` - The folder of the installer, the backup and the record
+/// * `limits` - The limits the install.dat has to stay within
+///
+/// # Returns a Result with the success state
+pub fn repair(game_path: &Path, smapi_path: &Path, limits: ExtractionLimits) -> Result<(), JunimoError> {
+    let bundle = smapi_path.join("install.dat");
+    if !bundle.exists() {
+        return Err(JunimoError::NotFound(
+            "The installer of the installed SMAPI version".to_string(),
+        ));
+    }
+    install_bundle(&bundle, game_path, smapi_path, limits)
+}
+
+/// Gets the version of the installed SMAPI
+pub fn installed_version() -> Option<String> {
+    version_extractor::get_version("StardewModdingAPI.dll")
+}
+
+/// Checks whether the installed SMAPI is the wanted version. The version of the dll doesn't contain
+/// beta tags, so only the version numbers are compared.
+///
+/// * `wanted` - The wanted version
+/// * `installed` - The installed version
+pub fn version_matches(wanted: &str, installed: Option<&str>) -> bool {
+    let installed = match installed {
+        Some(installed) => installed,
+        None => return false,
+    };
+    match (wanted.parse::<SemanticVersion>(), installed.parse::<SemanticVersion>()) {
+        (Ok(wanted), Ok(installed)) => {
+            (wanted.major, wanted.minor, wanted.patch) == (installed.major, installed.minor, installed.patch)
+        }
+        _ => wanted == installed,
+    }
+}
+
+/// Tauri command to get the state of the SMAPI installation
+#[command]
+pub fn get_smapi_status() -> Result<SmapiStatus, JunimoError> {
+    Ok(status(&game_path()?, &smapi_path(), installed_version()))
+}
+
+/// Tauri command to repair the SMAPI installation with the kept installer
+///
+/// * `app_handle` - The app handle
+///
+/// # Returns the state of the repaired installation
+#[command]
+pub fn repair_smapi(app_handle: AppHandle) -> Result<SmapiStatus, JunimoError> {
+    let game_path = game_path()?;
+    repair(&game_path, &smapi_path(), config::extraction_limits())?;
+    console::add_line(
+        &app_handle,
+        "<span style=\"color: #2fb565\">[Junimo] SMAPI was repaired</span>".to_string(),
+    );
+    Ok(status(&game_path, &smapi_path(), installed_version()))
+}
+
+/// Tauri command to remove SMAPI from the game folder
+///
+/// * `app_handle` - The app handle
+#[command]
+pub fn uninstall_smapi(app_handle: AppHandle) -> Result<(), JunimoError> {
+    uninstall(&game_path()?, &smapi_path())?;
+    console::add_line(
+        &app_handle,
+        "<span style=\"color: #2fb565\">[Junimo] SMAPI was removed and the game files were restored</span>".to_string(),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tempfile::tempdir;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    use super::*;
+
+    /// Writes an install.dat with the given files
+    fn bundle(path: &Path, files: &[(&str, &str)]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_install_and_uninstall() {
+        let tmp_dir = tempdir().unwrap();
+        let game_path = tmp_dir.path().join("game");
+        let smapi_path = tmp_dir.path().join("smapi");
+        fs::create_dir_all(&game_path).unwrap();
+        fs::create_dir_all(&smapi_path).unwrap();
+        fs::write(game_path.join("Stardew Valley.deps.json"), "game deps").unwrap();
+        fs::write(game_path.join("steam_appid.txt"), "original").unwrap();
+
+        let mut files = vec![
+            ("smapi-internal/SMAPI.Toolkit.dll", "toolkit"),
+            ("smapi-internal/config.json", "{}"),
+            ("steam_appid.txt", "smapi"),
+            ("StardewModdingAPI.dll", "smapi"),
+        ];
+        if cfg!(target_os = "windows") {
+            files.push(("StardewModdingAPI.exe", "smapi"));
+        }
+        let dat = tmp_dir.path().join("install.dat");
+        bundle(&dat, &files);
+
+        install_bundle(&dat, &game_path, &smapi_path, ExtractionLimits::default()).unwrap();
+        let installed = status(&game_path, &smapi_path, None);
+        assert!(installed.missing_files.is_empty());
+        assert!(installed.backup && installed.repairable);
+        assert_eq!(fs::read_to_string(game_path.join("steam_appid.txt")).unwrap(), "smapi");
+        assert_eq!(
+            fs::read_to_string(game_path.join("StardewModdingAPI.deps.json")).unwrap(),
+            "game deps"
+        );
+
+        // An update keeps the original game files in the backup
+        install_bundle(&dat, &game_path, &smapi_path, ExtractionLimits::default()).unwrap();
+
+        // A repair brings back deleted files
+        fs::remove_file(game_path.join("StardewModdingAPI.dll")).unwrap();
+        assert_eq!(
+            status(&game_path, &smapi_path, None).missing_files,
+            vec!["StardewModdingAPI.dll"]
+        );
+        repair(&game_path, &smapi_path, ExtractionLimits::default()).unwrap();
+        assert!(game_path.join("StardewModdingAPI.dll").exists());
+
+        uninstall(&game_path, &smapi_path).unwrap();
+        assert_eq!(fs::read_to_string(game_path.join("steam_appid.txt")).unwrap(), "original");
+        assert!(!game_path.join("StardewModdingAPI.dll").exists());
+        assert!(!game_path.join("StardewModdingAPI.deps.json").exists());
+        assert!(!game_path.join("smapi-internal").exists());
+        assert!(game_path.join("Stardew Valley.deps.json").exists());
+        assert!(!status(&game_path, &smapi_path, None).repairable);
+    }
+
+    #[test]
+    fn test_version_matches() {
+        assert!(version_matches("4.0.8", Some("4.0.8")));
+        assert!(version_matches("4.1.0-beta.2", Some("4.1.0")));
+        assert!(!version_matches("3.18.6", Some("4.0.8")));
+        assert!(!version_matches("4.0.8", None));
+    }
+
+    #[test]
+    fn test_repair_without_installer() {
+        let tmp_dir = tempdir().unwrap();
+        let result = repair(tmp_dir.path(), tmp_dir.path(), ExtractionLimits::default());
+        assert_eq!(result.err().map(|error| error.kind()), Some("NotFound"));
+    }
+
+    #[test]
+    fn test_uninstall_official_installation() {
+        let tmp_dir = tempdir().unwrap();
+        let game_path = tmp_dir.path().join("game");
+        fs::create_dir_all(game_path.join("smapi-internal")).unwrap();
+        fs::write(game_path.join("StardewModdingAPI.dll"), "smapi").unwrap();
+        fs::write(game_path.join(GAME_LAUNCHER), "smapi launcher").unwrap();
+        fs::write(game_path.join(GAME_LAUNCHER_BACKUP), "game launcher").unwrap();
+
+        uninstall(&game_path, &tmp_dir.path().join("smapi")).unwrap();
+        assert!(!game_path.join("StardewModdingAPI.dll").exists());
+        assert!(!game_path.join("smapi-internal").exists());
+        assert_eq!(fs::read_to_string(game_path.join(GAME_LAUNCHER)).unwrap(), "game launcher");
+    }
+
+    #[test]
+    fn test_update_official_installation() {
+        let tmp_dir = tempdir().unwrap();
+        let game_path = tmp_dir.path().join("game");
+        let smapi_path = tmp_dir.path().join("smapi");
+        fs::create_dir_all(game_path.join("smapi-internal")).unwrap();
+        fs::write(game_path.join("smapi-internal/config.json"), "{}").unwrap();
+        fs::write(game_path.join("StardewModdingAPI.dll"), "old smapi").unwrap();
+        fs::write(game_path.join(GAME_LAUNCHER), "old smapi launcher").unwrap();
+        fs::write(game_path.join(GAME_LAUNCHER_BACKUP), "game launcher").unwrap();
+
+        let dat = tmp_dir.path().join("install.dat");
+        bundle(
+            &dat,
+            &[
+                ("smapi-internal/config.json", "{}"),
+                ("StardewModdingAPI.dll", "smapi"),
+                (UNIX_LAUNCHER, "smapi launcher"),
+            ],
+        );
+        install_bundle(&dat, &game_path, &smapi_path, ExtractionLimits::default()).unwrap();
+
+        // Only the original launcher is a game file, the old SMAPI files aren't kept
+        let record = load_record(&smapi_path);
+        let expected: Vec<String> = if cfg!(target_os = "windows") {
+            vec![]
+        } else {
+            vec![GAME_LAUNCHER.to_string()]
+        };
+        assert_eq!(record.replaced, expected);
+        if cfg!(not(target_os = "windows")) {
+            assert_eq!(
+                fs::read_to_string(smapi_path.join("backup").join(GAME_LAUNCHER)).unwrap(),
+                "game launcher"
+            );
+        }
+
+        uninstall(&game_path, &smapi_path).unwrap();
+        assert!(!game_path.join("StardewModdingAPI.dll").exists());
+        assert!(!game_path.join(GAME_LAUNCHER_BACKUP).exists());
+        if cfg!(not(target_os = "windows")) {
+            assert_eq!(fs::read_to_string(game_path.join(GAME_LAUNCHER)).unwrap(), "game launcher");
+        }
+    }
+}
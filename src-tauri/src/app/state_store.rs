@@ -0,0 +1,246 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::app::error::JunimoError;
+use crate::app::models::mod_info::ModInfo;
+use crate::app::mods;
+use crate::app::profiles::{self, Profile};
+use crate::app::utility::paths;
+
+// The mods and profiles are only read from the disk once. Afterwards the store is the only one that
+// writes mods.json and profile.json, so a download that finishes while the user edits a profile
+// can't overwrite the changes of the other.
+
+/// The loaded files together with the path they were loaded from
+#[derive(Default)]
+struct StoredState {
+    mods: Option<(PathBuf, Vec<ModInfo>)>,
+    profiles: Option<(PathBuf, Vec<Profile>)>,
+}
+
+impl StoredState {
+    /// Loads the mods, if they weren't loaded from the path yet
+    fn load_mods(&mut self, path: &Path) {
+        if self.mods.as_ref().map_or(true, |(loaded, _)| loaded != path) {
+            self.mods = Some((path.to_path_buf(), mods::load_mods(path)));
+        }
+    }
+
+    /// Loads the profiles, if they weren't loaded from the path yet
+    fn load_profiles(&mut self, path: &Path) {
+        if self.profiles.as_ref().map_or(true, |(loaded, _)| loaded != path) {
+            self.profiles = Some((path.to_path_buf(), profiles::load_profiles(&path.to_path_buf())));
+        }
+    }
+}
+
+/// Owns the installed mods and the profiles. A change holds the lock from reading to saving,
+/// so changes are applied one after another. It is shared through the app state, so every clone
+/// works on the same data.
+#[derive(Clone, Default)]
+pub struct StateStore {
+    state: Arc<Mutex<StoredState>>,
+}
+
+impl StateStore {
+    fn lock(&self) -> MutexGuard<'_, StoredState> {
+        // A panic while changing the state leaves the last saved state behind, which is still valid
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Gets all installed mods
+    pub fn mods(&self) -> Vec<ModInfo> {
+        let mut state = self.lock();
+        state.load_mods(&paths::mod_json_path());
+        state.mods.as_ref().unwrap().1.clone()
+    }
+
+    /// Gets all profiles of the profile.json in the appdata folder
+    pub fn profiles(&self) -> Vec<Profile> {
+        self.profiles_at(&paths::profile_path())
+    }
+
+    /// Gets all profiles of a profile.json file
+    ///
+    /// * `path` - The path to the profile.json file
+    pub fn profiles_at(&self, path: &Path) -> Vec<Profile> {
+        let mut state = self.lock();
+        state.load_profiles(path);
+        state.profiles.as_ref().unwrap().1.clone()
+    }
+
+    /// Gets the profile that is currently used
+    pub fn current_profile(&self) -> Profile {
+        profiles::current_profile(&self.profiles())
+    }
+
+    /// Changes the installed mods, saves them and sends them to the frontend as "mods-update".
+    /// If saving fails, the change is undone.
+    ///
+    /// * `app_handle` - The app handle
+    /// * `change` - Changes the mods
+    ///
+    /// # Returns the result of the change
+    pub fn update_mods<R: Runtime, T>(
+        &self,
+        app_handle: &AppHandle<R>,
+        change: impl FnOnce(&mut Vec<ModInfo>) -> T,
+    ) -> Result<T, JunimoError> {
+        let mut state = self.lock();
+        let path = paths::mod_json_path();
+        state.load_mods(&path);
+
+        let mods = &mut state.mods.as_mut().unwrap().1;
+        let previous = mods.clone();
+        let result = change(mods);
+        if let Err(error) = mods::save_mods(&path, mods) {
+            *mods = previous;
+            return Err(error);
+        }
+        app_handle.emit("mods-update", &*mods)?;
+        Ok(result)
+    }
+
+    /// Changes the profiles of the profile.json in the appdata folder
+    ///
+    /// * `app_handle` - The app handle
+    /// * `change` - Changes the profiles
+    ///
+    /// # Returns the result of the change
+    pub fn update_profiles<R: Runtime, T>(
+        &self,
+        app_handle: &AppHandle<R>,
+        change: impl FnOnce(&mut Vec<Profile>) -> T,
+    ) -> Result<T, JunimoError> {
+        self.update_profiles_at(app_handle, &paths::profile_path(), change)
+    }
+
+    /// Changes the profiles of a profile.json file, saves them and sends them to the frontend as "profile-update".
+    /// If saving fails, the change is undone.
+    ///
+    /// * `app_handle` - The app handle
+    /// * `path` - The path to the profile.json file
+    /// * `change` - Changes the profiles
+    ///
+    /// # Returns the result of the change
+    pub fn update_profiles_at<R: Runtime, T>(
+        &self,
+        app_handle: &AppHandle<R>,
+        path: &Path,
+        change: impl FnOnce(&mut Vec<Profile>) -> T,
+    ) -> Result<T, JunimoError> {
+        let mut state = self.lock();
+        state.load_profiles(path);
+
+        let profiles = &mut state.profiles.as_mut().unwrap().1;
+        let previous = profiles.clone();
+        let result = change(profiles);
+        if let Err(error) = profiles::save_profiles(profiles, &path.to_path_buf()) {
+            *profiles = previous;
+            return Err(error);
+        }
+        app_handle.emit("profile-update", &*profiles)?;
+        Ok(result)
+    }
+
+    /// Changes the installed mods and the profiles together, like uninstalling a mod that has to
+    /// disappear from every profile as well. If saving fails, both changes are undone.
+    ///
+    /// * `app_handle` - The app handle
+    /// * `change` - Changes the mods and the profiles
+    ///
+    /// # Returns the result of the change
+    pub fn update<R: Runtime, T>(
+        &self,
+        app_handle: &AppHandle<R>,
+        change: impl FnOnce(&mut Vec<ModInfo>, &mut Vec<Profile>) -> T,
+    ) -> Result<T, JunimoError> {
+        let mut state = self.lock();
+        let mods_path = paths::mod_json_path();
+        let profiles_path = paths::profile_path();
+        state.load_mods(&mods_path);
+        state.load_profiles(&profiles_path);
+
+        let StoredState { mods, profiles } = &mut *state;
+        let mods = &mut mods.as_mut().unwrap().1;
+        let profiles = &mut profiles.as_mut().unwrap().1;
+        let previous = (mods.clone(), profiles.clone());
+        let result = change(mods, profiles);
+
+        let saved = mods::save_mods(&mods_path, mods)
+            .and_then(|_| profiles::save_profiles(profiles, &profiles_path));
+        if let Err(error) = saved {
+            // The mods may already be saved, so they are written back as well
+            (*mods, *profiles) = previous;
+            let _ = mods::save_mods(&mods_path, mods);
+            return Err(error);
+        }
+        app_handle.emit("mods-update", &*mods)?;
+        app_handle.emit("profile-update", &*profiles)?;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use tauri::test::mock_app;
+    use tempfile::tempdir;
+
+    use crate::app::profiles::ModReference;
+
+    use super::*;
+
+    #[test]
+    fn test_concurrent_profile_changes() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("profile.json");
+        let app = mock_app();
+        let store = StateStore::default();
+        assert_eq!(store.profiles_at(&path).len(), 1);
+
+        // Every change reads the state the previous change left behind, so none of them gets lost
+        let threads: Vec<_> = (0..8)
+            .map(|index| {
+                let store = store.clone();
+                let app_handle = app.handle().clone();
+                let path = path.clone();
+                thread::spawn(move || {
+                    store.update_profiles_at(&app_handle, &path, |profiles| {
+                        profiles[0].mods.push(ModReference {
+                            unique_id: format!("Author.Mod{}", index),
+                            version: None,
+                            enabled: true,
+                        });
+                    })
+                    .unwrap();
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(store.profiles_at(&path)[0].mods.len(), 8);
+        assert_eq!(profiles::load_profiles(&path)[0].mods.len(), 8);
+    }
+
+    #[test]
+    fn test_reloads_other_file() {
+        let tmp_dir = tempdir().unwrap();
+        let first = tmp_dir.path().join("first.json");
+        let second = tmp_dir.path().join("second.json");
+        let app = mock_app();
+        let store = StateStore::default();
+
+        store.update_profiles_at(app.handle(), &first, |profiles| {
+            profiles[0].name = "First".to_string();
+        })
+        .unwrap();
+        assert_eq!(store.profiles_at(&second)[0].name, "Default");
+        assert_eq!(store.profiles_at(&first)[0].name, "First");
+    }
+}
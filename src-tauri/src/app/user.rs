@@ -9,6 +9,8 @@ use std::fs::File;
 use std::io::{Read, Write};
 use tauri::command;
 
+use crate::app::error::JunimoError;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct User {
     user_id: i64,
@@ -20,42 +22,47 @@ pub struct User {
 }
 
 #[command]
-pub fn load_user() -> Option<User> {
+pub fn load_user() -> Result<Option<User>, JunimoError> {
     if app_path("user.stp").exists() {
-        let mut file = File::open(app_path("user.stp")).unwrap();
+        let mut file = File::open(app_path("user.stp"))?;
         let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).unwrap();
-        let user: User = bincode::deserialize(&buffer[..]).unwrap();
+        file.read_to_end(&mut buffer)?;
+        let user: User = bincode::deserialize(&buffer[..])?;
 
-        return Some(user);
+        return Ok(Some(user));
     }
 
-    None
+    Ok(None)
 }
 
-fn save_user(user: &User) {
-    let encoded: Vec<u8> = bincode::serialize(&user).unwrap();
-    let mut file = File::create(app_path("user.stp")).unwrap();
-    file.write_all(&encoded).unwrap();
+fn save_user(user: &User) -> Result<(), JunimoError> {
+    let encoded: Vec<u8> = bincode::serialize(&user)?;
+    let mut file = File::create(app_path("user.stp"))?;
+    file.write_all(&encoded)?;
+    Ok(())
 }
 
 #[command]
-pub async fn connect_user(handle: tauri::AppHandle) -> Option<User> {
-    nexuswebsocket::connect_user(handle).await;
+pub async fn connect_user(handle: tauri::AppHandle) -> Result<Option<User>, JunimoError> {
+    nexuswebsocket::connect_user(handle).await?;
     user_info().await
 }
 
 #[command]
-pub async fn disconnect_user() {
-    fs::remove_file(app_path("key.stp")).unwrap();
-    fs::remove_file(app_path("connection.stp")).unwrap();
-    fs::remove_file(app_path("user.stp")).unwrap();
+pub async fn disconnect_user() -> Result<(), JunimoError> {
+    for file in ["key.stp", "connection.stp", "user.stp"] {
+        let path = app_path(file);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
 }
 
-async fn user_info() -> Option<User> {
+async fn user_info() -> Result<Option<User>, JunimoError> {
     let key = nexuswebsocket::load_key();
 
-    let loaded_user = load_user();
+    let loaded_user = load_user()?;
 
     match loaded_user {
         None => {
@@ -65,19 +72,17 @@ async fn user_info() -> Option<User> {
                 .header("accept", "application/json")
                 .header("apikey", key)
                 .send()
-                .await
-                .unwrap();
+                .await?;
 
             if res.status().is_success() {
-                let body = res.text().await.unwrap();
-                let body_str = body.as_str();
-                let user: User = serde_json::from_str(body_str).unwrap();
-                save_user(&user);
-                Some(user)
+                let body = res.text().await?;
+                let user: User = serde_json::from_str(body.as_str())?;
+                save_user(&user)?;
+                Ok(Some(user))
             } else {
-                None
+                Ok(None)
             }
         }
-        Some(value) => Some(value),
+        Some(value) => Ok(Some(value)),
     }
 }
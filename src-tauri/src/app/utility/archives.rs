@@ -0,0 +1,520 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+use flate2::read::GzDecoder;
+use zip::ZipArchive;
+
+use crate::app::utility::extraction::{self, EntryKind, Extraction, ExtractionLimits};
+
+/// The Windows attribute of links and junctions
+const REPARSE_POINT: u32 = 0x400;
+
+/// The archive formats mods are shipped in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    SevenZip,
+    Rar,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// Gets the file extension archives of the format usually have
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::SevenZip => "7z",
+            ArchiveFormat::Rar => "rar",
+            ArchiveFormat::TarGz => "tar.gz",
+        }
+    }
+}
+
+/// A mod archive on disk. The format is detected by the magic bytes of the file,
+/// so the file extension doesn't matter.
+#[derive(Debug, Clone)]
+pub struct ModArchive {
+    pub path: PathBuf,
+    pub format: ArchiveFormat,
+}
+
+impl ModArchive {
+    /// Opens an archive and detects its format
+    ///
+    /// * `path` - The path to the archive
+    ///
+    /// # Returns the archive or an error if the format isn't supported
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let mut header = [0u8; 8];
+        let mut file = File::open(path).map_err(|e| e.to_string())?;
+        let read = file.read(&mut header).map_err(|e| e.to_string())?;
+
+        match detect_format(&header[..read]) {
+            Some(format) => Ok(ModArchive {
+                path: path.to_path_buf(),
+                format,
+            }),
+            None => Err(format!(
+                "{} is not a zip, 7z, rar or tar.gz archive",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            )),
+        }
+    }
+
+    /// Lists the paths of all files and folders in the archive. Folders end with a slash.
+    pub fn entries(&self) -> Result<Vec<String>, String> {
+        match self.format {
+            ArchiveFormat::Zip => {
+                let archive = ZipArchive::new(self.file()?).map_err(|e| e.to_string())?;
+                Ok(archive.file_names().map(|name| name.replace('\\', "/")).collect())
+            }
+            ArchiveFormat::SevenZip => {
+                let reader = sevenz_rust::SevenZReader::open(&self.path, sevenz_rust::Password::empty())
+                    .map_err(|e| e.to_string())?;
+                Ok(reader
+                    .archive()
+                    .files
+                    .iter()
+                    .map(|entry| entry_name(entry.name(), entry.is_directory()))
+                    .collect())
+            }
+            ArchiveFormat::Rar => {
+                let archive = unrar::Archive::new(&self.path)
+                    .open_for_listing()
+                    .map_err(|e| e.to_string())?;
+                let mut entries = Vec::new();
+                for header in archive {
+                    let header = header.map_err(|e| e.to_string())?;
+                    entries.push(entry_name(
+                        &header.filename.to_string_lossy(),
+                        header.is_directory(),
+                    ));
+                }
+                Ok(entries)
+            }
+            ArchiveFormat::TarGz => {
+                let mut archive = tar::Archive::new(GzDecoder::new(self.file()?));
+                let mut entries = Vec::new();
+                for entry in archive.entries().map_err(|e| e.to_string())? {
+                    let entry = entry.map_err(|e| e.to_string())?;
+                    let path = entry.path().map_err(|e| e.to_string())?;
+                    entries.push(entry_name(
+                        &path.to_string_lossy(),
+                        entry.header().entry_type().is_dir(),
+                    ));
+                }
+                Ok(entries)
+            }
+        }
+    }
+
+    /// Extracts all files of the archive. macOS metadata folders are skipped. Entries that would end up
+    /// outside of the destination, links and archives that exceed the limits are rejected.
+    ///
+    /// * `destination` - The folder to extract into
+    /// * `limits` - The limits the archive has to stay within
+    /// * `on_entry` - Gets called with the index of every extracted entry
+    ///
+    /// # Returns a Result with the success state
+    pub fn extract<F: FnMut(usize)>(
+        &self,
+        destination: &Path,
+        limits: ExtractionLimits,
+        mut on_entry: F,
+    ) -> Result<(), String> {
+        let archive_size = fs::metadata(&self.path).map_err(|e| e.to_string())?.len();
+        let mut extraction = Extraction::new(limits, archive_size);
+
+        match self.format {
+            ArchiveFormat::Zip => {
+                let mut archive = ZipArchive::new(self.file()?).map_err(|e| e.to_string())?;
+                for i in 0..archive.len() {
+                    let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
+                    let kind = zip_entry_kind(&file);
+                    if let Some(outpath) = extraction.entry(destination, file.name(), kind)? {
+                        extraction.write(&outpath, kind, &mut file)?;
+
+                        #[cfg(unix)]
+                        {
+                            use std::os::unix::fs::PermissionsExt;
+                            if let Some(mode) = file.unix_mode() {
+                                let mode = extraction::file_permissions(mode);
+                                fs::set_permissions(&outpath, fs::Permissions::from_mode(mode))
+                                    .map_err(|e| e.to_string())?;
+                            }
+                        }
+                    }
+                    on_entry(i);
+                }
+                Ok(())
+            }
+            ArchiveFormat::SevenZip => {
+                let mut reader = sevenz_rust::SevenZReader::open(&self.path, sevenz_rust::Password::empty())
+                    .map_err(|e| e.to_string())?;
+                let mut index = 0;
+                reader
+                    .for_each_entries(|entry, data| {
+                        let kind = match entry.is_directory() {
+                            true => EntryKind::Folder,
+                            false if is_seven_zip_link(entry) => EntryKind::Link,
+                            false => EntryKind::File,
+                        };
+                        match extraction.entry(destination, entry.name(), kind).map_err(sevenz_rust::Error::other)? {
+                            Some(outpath) => extraction
+                                .write(&outpath, kind, data)
+                                .map_err(sevenz_rust::Error::other)?,
+                            // Solid archives need every entry to be read, even the skipped ones
+                            None => {
+                                io::copy(data, &mut io::sink()).map_err(sevenz_rust::Error::io)?;
+                            }
+                        }
+                        on_entry(index);
+                        index += 1;
+                        Ok(true)
+                    })
+                    .map_err(|e| e.to_string())
+            }
+            ArchiveFormat::Rar => {
+                let mut archive = unrar::Archive::new(&self.path)
+                    .open_for_processing()
+                    .map_err(|e| e.to_string())?;
+                let mut index = 0;
+                while let Some(header) = archive.read_header().map_err(|e| e.to_string())? {
+                    let entry = header.entry();
+                    let name = entry.filename.to_string_lossy().to_string();
+                    let unpacked_size = entry.unpacked_size;
+                    let kind = match entry.is_directory() {
+                        true => EntryKind::Folder,
+                        false if is_rar_link(entry.file_attr) => EntryKind::Link,
+                        false => EntryKind::File,
+                    };
+                    archive = match extraction.entry(destination, &name, kind)? {
+                        Some(outpath) if kind == EntryKind::File => {
+                            // unrar only hands out whole files, which are read into memory, so the
+                            // announced size has to stay below the buffer limit before anything is read.
+                            // The written bytes are counted as usual.
+                            extraction.check_buffered(&name, unpacked_size)?;
+                            let (data, archive) = header.read().map_err(|e| e.to_string())?;
+                            extraction.write(&outpath, kind, &mut data.as_slice())?;
+                            archive
+                        }
+                        Some(outpath) => {
+                            fs::create_dir_all(&outpath).map_err(|e| e.to_string())?;
+                            header.skip().map_err(|e| e.to_string())?
+                        }
+                        None => header.skip().map_err(|e| e.to_string())?,
+                    };
+                    on_entry(index);
+                    index += 1;
+                }
+                Ok(())
+            }
+            ArchiveFormat::TarGz => {
+                let mut archive = tar::Archive::new(GzDecoder::new(self.file()?));
+                for (index, entry) in archive.entries().map_err(|e| e.to_string())?.enumerate() {
+                    let mut entry = entry.map_err(|e| e.to_string())?;
+                    let name = entry.path().map_err(|e| e.to_string())?.to_string_lossy().to_string();
+                    let entry_type = entry.header().entry_type();
+                    let kind = if entry_type.is_dir() {
+                        EntryKind::Folder
+                    } else if entry_type.is_symlink() || entry_type.is_hard_link() {
+                        EntryKind::Link
+                    } else if entry_type.is_file() {
+                        EntryKind::File
+                    } else {
+                        // Devices, fifos and the like are never part of a mod
+                        on_entry(index);
+                        continue;
+                    };
+
+                    if let Some(outpath) = extraction.entry(destination, &name, kind)? {
+                        extraction.write(&outpath, kind, &mut entry)?;
+                    }
+                    on_entry(index);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn file(&self) -> Result<File, String> {
+        File::open(&self.path).map_err(|e| e.to_string())
+    }
+}
+
+/// Detects the format of an archive by its first bytes
+///
+/// * `header` - The first bytes of the file
+///
+/// # Returns the format or None if it isn't a supported archive
+pub fn detect_format(header: &[u8]) -> Option<ArchiveFormat> {
+    if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+        Some(ArchiveFormat::Zip)
+    } else if header.starts_with(&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C]) {
+        Some(ArchiveFormat::SevenZip)
+    } else if header.starts_with(b"Rar!\x1A\x07") {
+        Some(ArchiveFormat::Rar)
+    } else if header.starts_with(&[0x1F, 0x8B]) {
+        Some(ArchiveFormat::TarGz)
+    } else {
+        None
+    }
+}
+
+/// Normalizes the name of an entry to forward slashes with a trailing slash for folders
+fn entry_name(name: &str, is_dir: bool) -> String {
+    let name = name.replace('\\', "/");
+    if is_dir && !name.ends_with('/') {
+        format!("{}/", name)
+    } else {
+        name
+    }
+}
+
+/// Gets the kind of a zip entry. Links are stored as files with the unix mode of a link.
+///
+/// * `file` - The entry of the zip archive
+pub fn zip_entry_kind(file: &zip::read::ZipFile<'_>) -> EntryKind {
+    if file.is_dir() {
+        EntryKind::Folder
+    } else if file.unix_mode().map_or(false, extraction::is_link_mode) {
+        EntryKind::Link
+    } else {
+        EntryKind::File
+    }
+}
+
+/// Checks whether a 7z entry is a link. Windows marks links as reparse points, the unix mode
+/// is stored in the upper half of the attributes.
+fn is_seven_zip_link(entry: &sevenz_rust::SevenZArchiveEntry) -> bool {
+    let attributes = entry.windows_attributes();
+    entry.has_windows_attributes
+        && (attributes & REPARSE_POINT != 0
+            || attributes & 0x8000 != 0 && extraction::is_link_mode(attributes >> 16))
+}
+
+/// Checks whether a rar entry is a link. The attributes are either Windows attributes,
+/// where links are reparse points, or a unix mode. A unix file with the setgid bit looks like
+/// a reparse point as well, which is rejected too, as mods never need it.
+///
+/// * `attributes` - The attributes of the entry
+fn is_rar_link(attributes: u32) -> bool {
+    attributes & REPARSE_POINT != 0 || extraction::is_link_mode(attributes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use tempfile::tempdir;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    use super::*;
+
+    const MANIFEST: &str = "{\"Name\": \"Test\", \"UniqueID\": \"Test.Mod\", \"Version\": \"1.0.0\"}";
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(detect_format(b"PK\x03\x04rest"), Some(ArchiveFormat::Zip));
+        assert_eq!(
+            detect_format(&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C, 0, 4]),
+            Some(ArchiveFormat::SevenZip)
+        );
+        assert_eq!(detect_format(b"Rar!\x1A\x07\x01\x00"), Some(ArchiveFormat::Rar));
+        assert_eq!(detect_format(&[0x1F, 0x8B, 0x08]), Some(ArchiveFormat::TarGz));
+        assert_eq!(detect_format(b"{\"Name\""), None);
+        assert_eq!(ArchiveFormat::TarGz.extension(), "tar.gz");
+    }
+
+    #[test]
+    fn test_zip_archive() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("mod.download");
+
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        zip.add_directory("Test Mod/", SimpleFileOptions::default()).unwrap();
+        zip.start_file("Test Mod/manifest.json", SimpleFileOptions::default()).unwrap();
+        zip.write_all(MANIFEST.as_bytes()).unwrap();
+        zip.finish().unwrap();
+
+        let archive = ModArchive::open(&path).unwrap();
+        assert_eq!(archive.format, ArchiveFormat::Zip);
+        assert_eq!(archive.entries().unwrap(), vec!["Test Mod/", "Test Mod/manifest.json"]);
+
+        let destination = tmp_dir.path().join("out");
+        archive.extract(&destination, ExtractionLimits::default(), |_| {}).unwrap();
+        assert_eq!(
+            fs::read_to_string(destination.join("Test Mod").join("manifest.json")).unwrap(),
+            MANIFEST
+        );
+    }
+
+    #[test]
+    fn test_tar_gz_archive() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("mod.tar.gz");
+
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(&path).unwrap(),
+            Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(MANIFEST.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "Test Mod/manifest.json", MANIFEST.as_bytes())
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let archive = ModArchive::open(&path).unwrap();
+        assert_eq!(archive.format, ArchiveFormat::TarGz);
+        assert_eq!(archive.entries().unwrap(), vec!["Test Mod/manifest.json"]);
+
+        let destination = tmp_dir.path().join("out");
+        archive.extract(&destination, ExtractionLimits::default(), |_| {}).unwrap();
+        assert!(destination.join("Test Mod").join("manifest.json").exists());
+    }
+
+    #[test]
+    fn test_seven_zip_archive() {
+        let tmp_dir = tempdir().unwrap();
+        let source = tmp_dir.path().join("source").join("Test Mod");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("manifest.json"), MANIFEST).unwrap();
+
+        let path = tmp_dir.path().join("mod.7z");
+        sevenz_rust::compress_to_path(tmp_dir.path().join("source"), &path).unwrap();
+
+        let archive = ModArchive::open(&path).unwrap();
+        assert_eq!(archive.format, ArchiveFormat::SevenZip);
+        assert!(archive
+            .entries()
+            .unwrap()
+            .contains(&"Test Mod/manifest.json".to_string()));
+
+        let destination = tmp_dir.path().join("out");
+        archive.extract(&destination, ExtractionLimits::default(), |_| {}).unwrap();
+        assert_eq!(
+            fs::read_to_string(destination.join("Test Mod").join("manifest.json")).unwrap(),
+            MANIFEST
+        );
+    }
+
+    #[test]
+    fn test_unsupported_archive() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("mod.zip");
+        fs::write(&path, MANIFEST).unwrap();
+
+        assert!(ModArchive::open(&path).is_err());
+    }
+
+    /// Writes a zip archive with the given files
+    fn zip_fixture(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for (name, data) in files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_rejects_zip_slip() {
+        let tmp_dir = tempdir().unwrap();
+        let destination = tmp_dir.path().join("out").join("install");
+
+        for name in ["../../evil.dll", "Test Mod/../../../evil.dll", "/tmp/evil.dll", "C:\\evil.dll"] {
+            let path = tmp_dir.path().join("slip.zip");
+            zip_fixture(&path, &[("Test Mod/manifest.json", MANIFEST.as_bytes()), (name, b"evil")]);
+
+            let archive = ModArchive::open(&path).unwrap();
+            let error = archive.extract(&destination, ExtractionLimits::default(), |_| {}).unwrap_err();
+            assert!(error.contains("evil.dll"), "{}", error);
+        }
+        assert!(!tmp_dir.path().join("evil.dll").exists());
+        assert!(!tmp_dir.path().join("out").join("evil.dll").exists());
+    }
+
+    #[test]
+    fn test_rejects_links() {
+        let tmp_dir = tempdir().unwrap();
+        let destination = tmp_dir.path().join("out");
+
+        let path = tmp_dir.path().join("link.zip");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        zip.add_symlink("Test Mod/passwd", "/etc/passwd", SimpleFileOptions::default()).unwrap();
+        zip.finish().unwrap();
+        let error = ModArchive::open(&path)
+            .unwrap()
+            .extract(&destination, ExtractionLimits::default(), |_| {})
+            .unwrap_err();
+        assert!(error.contains("link"), "{}", error);
+
+        let path = tmp_dir.path().join("link.tar.gz");
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(&path).unwrap(),
+            Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_cksum();
+        builder.append_link(&mut header, "Test Mod/passwd", "/etc/passwd").unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+        let error = ModArchive::open(&path)
+            .unwrap()
+            .extract(&destination, ExtractionLimits::default(), |_| {})
+            .unwrap_err();
+        assert!(error.contains("link"), "{}", error);
+        assert!(!destination.join("Test Mod").join("passwd").exists());
+    }
+
+    #[test]
+    fn test_rar_links() {
+        assert!(is_rar_link(0x20 | REPARSE_POINT));
+        assert!(is_rar_link(0o120777));
+        assert!(!is_rar_link(0x20));
+        assert!(!is_rar_link(0o100644));
+    }
+
+    #[test]
+    fn test_rejects_archive_bomb() {
+        let tmp_dir = tempdir().unwrap();
+        let destination = tmp_dir.path().join("out");
+
+        // 17 MB of zeros are compressed to a few kilobytes
+        let path = tmp_dir.path().join("bomb.zip");
+        zip_fixture(&path, &[("Test Mod/bomb.bin", &vec![0u8; 17 * 1024 * 1024])]);
+        let error = ModArchive::open(&path)
+            .unwrap()
+            .extract(&destination, ExtractionLimits::default(), |_| {})
+            .unwrap_err();
+        assert!(error.contains("times its own size"), "{}", error);
+
+        let limits = ExtractionLimits {
+            max_total_size: 1024,
+            ..ExtractionLimits::default()
+        };
+        let path = tmp_dir.path().join("big.zip");
+        zip_fixture(&path, &[("Test Mod/big.bin", &[1u8; 2048])]);
+        let error = ModArchive::open(&path).unwrap().extract(&destination, limits, |_| {}).unwrap_err();
+        assert!(error.contains("MB"), "{}", error);
+
+        let limits = ExtractionLimits {
+            max_entries: 2,
+            ..ExtractionLimits::default()
+        };
+        let path = tmp_dir.path().join("many.zip");
+        zip_fixture(&path, &[("a.json", b"{}"), ("b.json", b"{}"), ("c.json", b"{}")]);
+        let error = ModArchive::open(&path).unwrap().extract(&destination, limits, |_| {}).unwrap_err();
+        assert!(error.contains("more than 2 files"), "{}", error);
+    }
+}
@@ -0,0 +1,316 @@
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+// Archives come from Nexus, GitHub or exports of other users, so nothing in them can be trusted.
+// Every extractor goes through an Extraction, which checks the path and the kind of each entry
+// and counts the bytes that are really written instead of trusting the sizes in the headers.
+
+/// Archives smaller than this are never rejected because of their compression ratio,
+/// a few kilobytes of json can easily be compressed a hundred times
+const RATIO_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// The limits an archive has to stay within while it is extracted
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ExtractionLimits {
+    /// The maximum size of all extracted files together in bytes
+    pub max_total_size: u64,
+    /// The maximum number of files and folders in the archive
+    pub max_entries: usize,
+    /// The maximum ratio between the extracted size and the size of the archive
+    pub max_ratio: u64,
+    /// The maximum size of a single file that has to be read into memory before it is written.
+    /// Configs of older versions don't have it, so it falls back to the default.
+    #[serde(default = "default_max_buffered_size")]
+    pub max_buffered_size: u64,
+}
+
+impl Default for ExtractionLimits {
+    fn default() -> Self {
+        ExtractionLimits {
+            max_total_size: 4 * 1024 * 1024 * 1024,
+            max_entries: 100_000,
+            max_ratio: 100,
+            max_buffered_size: default_max_buffered_size(),
+        }
+    }
+}
+
+/// Gets the default maximum size of a file that is read into memory
+fn default_max_buffered_size() -> u64 {
+    256 * 1024 * 1024
+}
+
+/// What an entry of an archive is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryKind {
+    File,
+    Folder,
+    /// A symbolic or hard link, which could point anywhere on the disk
+    Link,
+}
+
+/// Checks the entries of one archive while it is extracted
+pub struct Extraction {
+    limits: ExtractionLimits,
+    archive_size: u64,
+    entries: usize,
+    total_size: u64,
+}
+
+impl Extraction {
+    /// Starts the extraction of an archive
+    ///
+    /// * `limits` - The limits the archive has to stay within
+    /// * `archive_size` - The size of the archive, used for the compression ratio
+    pub fn new(limits: ExtractionLimits, archive_size: u64) -> Self {
+        Extraction {
+            limits,
+            archive_size,
+            entries: 0,
+            total_size: 0,
+        }
+    }
+
+    /// Checks an entry before it is extracted
+    ///
+    /// * `destination` - The folder the archive is extracted into
+    /// * `name` - The name of the entry in the archive
+    /// * `kind` - Whether the entry is a file, folder or link
+    ///
+    /// # Returns the path to extract the entry to, None if the entry is skipped or an error
+    /// if the archive has to be rejected
+    pub fn entry(&mut self, destination: &Path, name: &str, kind: EntryKind) -> Result<Option<PathBuf>, String> {
+        self.entries += 1;
+        if self.entries > self.limits.max_entries {
+            return Err(format!(
+                "The archive contains more than {} files and folders",
+                self.limits.max_entries
+            ));
+        }
+
+        let path = entry_path(destination, name)?;
+        if path.is_some() && kind == EntryKind::Link {
+            return Err(format!("{} is a link, links aren't allowed in archives", name));
+        }
+        Ok(path)
+    }
+
+    /// Counts bytes that are extracted
+    ///
+    /// * `size` - The number of extracted bytes
+    ///
+    /// # Returns an error if the archive got too big
+    pub fn count(&mut self, size: u64) -> Result<(), String> {
+        self.check(size)?;
+        self.total_size += size;
+        Ok(())
+    }
+
+    /// Checks if a file can be read into memory as a whole before it is written
+    ///
+    /// * `name` - The name of the entry in the archive
+    /// * `size` - The size of the file
+    ///
+    /// # Returns an error if the file is too big to be held in memory or the archive would get too big
+    pub fn check_buffered(&self, name: &str, size: u64) -> Result<(), String> {
+        if size > self.limits.max_buffered_size {
+            return Err(format!(
+                "{} unpacks to more than {} MB, which is too big to extract",
+                name,
+                self.limits.max_buffered_size / 1024 / 1024
+            ));
+        }
+        self.check(size)
+    }
+
+    /// Checks if more bytes can be extracted, without counting them
+    ///
+    /// * `size` - The number of bytes that would be extracted
+    ///
+    /// # Returns an error if the archive would get too big
+    pub fn check(&self, size: u64) -> Result<(), String> {
+        let total_size = self.total_size.saturating_add(size);
+        if total_size > self.limits.max_total_size {
+            return Err(format!(
+                "The archive unpacks to more than {} MB",
+                self.limits.max_total_size / 1024 / 1024
+            ));
+        }
+        if total_size > RATIO_THRESHOLD
+            && total_size / self.archive_size.max(1) > self.limits.max_ratio
+        {
+            return Err(format!(
+                "The archive unpacks to more than {} times its own size",
+                self.limits.max_ratio
+            ));
+        }
+        Ok(())
+    }
+
+    /// Writes a single file or folder of an archive and counts its size
+    ///
+    /// * `outpath` - The path to write to
+    /// * `kind` - Whether the entry is a file or folder
+    /// * `data` - The content of the file
+    ///
+    /// # Returns a Result with the success state
+    pub fn write<R: Read + ?Sized>(&mut self, outpath: &Path, kind: EntryKind, data: &mut R) -> Result<(), String> {
+        if kind == EntryKind::Folder {
+            return fs::create_dir_all(outpath).map_err(|e| e.to_string());
+        }
+
+        if let Some(parent) = outpath.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let mut outfile = File::create(outpath).map_err(|e| e.to_string())?;
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            let read = data.read(&mut buffer).map_err(|e| e.to_string())?;
+            if read == 0 {
+                return Ok(());
+            }
+            self.count(read as u64)?;
+            outfile.write_all(&buffer[..read]).map_err(|e| e.to_string())?;
+        }
+    }
+}
+
+/// Gets the path an entry is extracted to
+///
+/// * `destination` - The folder the archive is extracted into
+/// * `name` - The name of the entry in the archive
+///
+/// # Returns the path, None if the entry should be skipped or an error if the entry
+/// would end up outside of the destination
+pub fn entry_path(destination: &Path, name: &str) -> Result<Option<PathBuf>, String> {
+    let name = name.replace('\\', "/");
+    if name.contains("__MACOSX") {
+        return Ok(None);
+    }
+
+    // Drive letters aren't a path component on Linux, so they are checked by hand
+    let has_drive = name.split('/').next().map_or(false, |first| first.contains(':'));
+    if name.starts_with('/') || has_drive {
+        return Err(format!("{} is an absolute path", name));
+    }
+
+    let relative = PathBuf::from(name.trim_end_matches('/'));
+    for component in relative.components() {
+        match component {
+            Component::Normal(_) | Component::CurDir => {}
+            Component::ParentDir => {
+                return Err(format!("{} points outside of the destination folder", name))
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(format!("{} is an absolute path", name))
+            }
+        }
+    }
+    if relative.components().all(|component| component == Component::CurDir) {
+        return Ok(None);
+    }
+
+    Ok(Some(destination.join(relative)))
+}
+
+/// Checks whether a unix mode belongs to a symbolic link
+///
+/// * `mode` - The unix mode of an entry
+pub fn is_link_mode(mode: u32) -> bool {
+    mode & 0o170000 == 0o120000
+}
+
+/// Gets the permissions an extracted file gets. Setuid, setgid and sticky bits are dropped
+/// and nothing becomes writable for other users.
+///
+/// * `mode` - The unix mode of an entry
+pub fn file_permissions(mode: u32) -> u32 {
+    mode & 0o755
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_entry_path() {
+        let destination = Path::new("/tmp/junimo");
+        assert_eq!(
+            entry_path(destination, "Mod\\manifest.json"),
+            Ok(Some(destination.join("Mod").join("manifest.json")))
+        );
+        assert_eq!(entry_path(destination, "__MACOSX/Mod/._manifest.json"), Ok(None));
+        assert_eq!(entry_path(destination, "./"), Ok(None));
+        assert!(entry_path(destination, "../evil.dll").unwrap_err().contains("outside"));
+        assert!(entry_path(destination, "Mod/../../evil.dll").unwrap_err().contains("outside"));
+        assert!(entry_path(destination, "..\\evil.dll").unwrap_err().contains("outside"));
+        assert!(entry_path(destination, "/etc/passwd").unwrap_err().contains("absolute"));
+        assert!(entry_path(destination, "C:\\Windows\\evil.dll").unwrap_err().contains("absolute"));
+    }
+
+    #[test]
+    fn test_limits() {
+        let destination = Path::new("/tmp/junimo");
+        let limits = ExtractionLimits {
+            max_total_size: 10,
+            max_entries: 2,
+            max_ratio: 100,
+            max_buffered_size: 5,
+        };
+
+        let mut extraction = Extraction::new(limits, 1);
+        assert!(extraction.entry(destination, "a", EntryKind::File).is_ok());
+        assert!(extraction.entry(destination, "b", EntryKind::File).is_ok());
+        assert!(extraction.entry(destination, "c", EntryKind::File).is_err());
+
+        let mut extraction = Extraction::new(limits, 1);
+        assert!(extraction.entry(destination, "link", EntryKind::Link).unwrap_err().contains("link"));
+
+        let mut extraction = Extraction::new(limits, 1);
+        assert!(extraction.check(11).is_err());
+        assert!(extraction.count(10).is_ok());
+        assert!(extraction.count(1).unwrap_err().contains("MB"));
+
+        let extraction = Extraction::new(limits, 1);
+        assert!(extraction.check_buffered("small.bin", 5).is_ok());
+        assert!(extraction.check_buffered("big.bin", 6).unwrap_err().contains("too big"));
+
+        // Limits saved by older versions don't have a buffer limit yet
+        let saved: ExtractionLimits =
+            serde_json::from_str("{\"max_total_size\": 10, \"max_entries\": 2, \"max_ratio\": 100}").unwrap();
+        assert_eq!(saved.max_buffered_size, ExtractionLimits::default().max_buffered_size);
+
+        let limits = ExtractionLimits::default();
+        let mut extraction = Extraction::new(limits, 1024 * 1024);
+        assert!(extraction.count(RATIO_THRESHOLD).is_ok());
+        assert!(extraction.count(RATIO_THRESHOLD * 8).unwrap_err().contains("times"));
+    }
+
+    #[test]
+    fn test_write_counts_real_size() {
+        let tmp_dir = tempdir().unwrap();
+        let limits = ExtractionLimits {
+            max_total_size: 1024,
+            ..ExtractionLimits::default()
+        };
+        let mut extraction = Extraction::new(limits, 1);
+        let outpath = tmp_dir.path().join("Mod").join("big.bin");
+
+        let result = extraction.write(&outpath, EntryKind::File, &mut Cursor::new(vec![0u8; 2048]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_modes() {
+        assert!(is_link_mode(0o120777));
+        assert!(!is_link_mode(0o100644));
+        assert_eq!(file_permissions(0o104777), 0o755);
+    }
+}
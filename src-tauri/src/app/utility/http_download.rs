@@ -0,0 +1,361 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use reqwest::header::{HeaderMap, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+/// How often a download is retried after a transient error before it fails
+const MAX_RETRIES: u32 = 5;
+
+/// Why a download didn't finish
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransferError {
+    /// The download was stopped by the caller. The partial file is kept, so it can be resumed.
+    Stopped,
+    /// The download failed even after retrying
+    Failed(String),
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferError::Stopped => write!(f, "Download stopped"),
+            TransferError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Identifies the version of the file on the server a partial file belongs to. It is saved next to
+/// the partial file, so a partial of another file is never continued with the wrong bytes.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+struct Validator {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    total: Option<u64>,
+}
+
+impl Validator {
+    fn from_headers(headers: &HeaderMap, total: Option<u64>) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        Validator {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            total,
+        }
+    }
+
+    /// Gets the value of the If-Range header. Weak ETags can't be used for ranges.
+    fn if_range(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
+/// The result of a single attempt to download the rest of a file
+enum Attempt {
+    Finished,
+    Stopped,
+    Retry(String),
+    Failed(String),
+}
+
+/// Downloads a file and continues a partial file at the same path with a Range request.
+/// Transient errors are retried with an increasing delay, every retry continues where the
+/// last one stopped. If the server doesn't send a size, the progress has no total.
+/// A partial file is only continued if the server confirms with If-Range that the file didn't change.
+///
+/// * `url` - The url of the file
+/// * `path` - The path the file is written to
+/// * `on_progress` - Gets called with the downloaded bytes and the total size, if known
+/// * `should_stop` - Gets checked before every chunk, the download stops once it returns true
+///
+/// # Returns a Result with the success state
+pub async fn download_resumable<P, S>(
+    url: &str,
+    path: &Path,
+    mut on_progress: P,
+    should_stop: S,
+) -> Result<(), TransferError>
+where
+    P: FnMut(u64, Option<u64>),
+    S: Fn() -> bool,
+{
+    let client = reqwest::Client::new();
+    let mut retries = 0;
+
+    loop {
+        let before = file_size(path);
+        match attempt(&client, url, path, &mut on_progress, &should_stop).await {
+            Attempt::Finished => {
+                let _ = fs::remove_file(validator_path(path));
+                return Ok(());
+            }
+            Attempt::Stopped => return Err(TransferError::Stopped),
+            Attempt::Failed(e) => return Err(TransferError::Failed(e)),
+            Attempt::Retry(e) => {
+                // Only connections that keep failing without any progress give up
+                if file_size(path) > before {
+                    retries = 0;
+                }
+                if retries >= MAX_RETRIES {
+                    return Err(TransferError::Failed(format!(
+                        "{} (gave up after {} retries)",
+                        e, MAX_RETRIES
+                    )));
+                }
+                tokio::time::sleep(backoff_delay(retries)).await;
+                retries += 1;
+            }
+        }
+    }
+}
+
+/// Downloads the part of the file that is still missing
+async fn attempt<P, S>(
+    client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+    on_progress: &mut P,
+    should_stop: &S,
+) -> Attempt
+where
+    P: FnMut(u64, Option<u64>),
+    S: Fn() -> bool,
+{
+    let mut existing = file_size(path);
+    let validator = load_validator(path);
+
+    // A partial file that can't be matched to the file on the server might belong to another file
+    let if_range = validator.as_ref().and_then(Validator::if_range);
+    let mut request = client.get(url);
+    match if_range {
+        Some(if_range) if existing > 0 => {
+            request = request
+                .header(RANGE, format!("bytes={}-", existing))
+                .header(IF_RANGE, if_range);
+        }
+        _ if existing > 0 => {
+            discard_partial(path);
+            existing = 0;
+        }
+        _ => {}
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => return Attempt::Retry(e.to_string()),
+    };
+
+    let status = response.status();
+    let (mut downloaded, total) = if status == StatusCode::PARTIAL_CONTENT {
+        let range = response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(content_range);
+        let expected = validator.as_ref().and_then(|validator| validator.total);
+        if !continues_partial(range, existing, expected) {
+            // The range doesn't continue the partial file, so start over
+            discard_partial(path);
+            return Attempt::Retry("The file on the server changed".to_string());
+        }
+        let total = range
+            .and_then(|(_, total)| total)
+            .or(response.content_length().map(|length| existing + length));
+        (existing, total)
+    } else if status == StatusCode::RANGE_NOT_SATISFIABLE {
+        // The partial file doesn't fit the file on the server anymore, so start over
+        discard_partial(path);
+        return Attempt::Retry(status.to_string());
+    } else if status.is_success() {
+        // The server ignored the range or the file changed, so the whole file is sent
+        let total = response.content_length();
+        if let Err(e) = save_validator(path, &Validator::from_headers(response.headers(), total)) {
+            return Attempt::Failed(e);
+        }
+        (0, total)
+    } else if is_transient(status) {
+        return Attempt::Retry(status.to_string());
+    } else {
+        return Attempt::Failed(format!("Received non-successful status code: {}", status));
+    };
+
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(downloaded > 0)
+        .truncate(downloaded == 0)
+        .open(path);
+    let mut file = match file {
+        Ok(file) => file,
+        Err(e) => return Attempt::Failed(e.to_string()),
+    };
+
+    on_progress(downloaded, total);
+
+    let mut stream = response.bytes_stream();
+    while let Some(item) = stream.next().await {
+        if should_stop() {
+            let _ = file.flush();
+            return Attempt::Stopped;
+        }
+
+        let chunk = match item {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                let _ = file.flush();
+                return Attempt::Retry(e.to_string());
+            }
+        };
+        if let Err(e) = file.write_all(&chunk) {
+            return Attempt::Failed(e.to_string());
+        }
+        downloaded += chunk.len() as u64;
+        on_progress(downloaded, total);
+    }
+
+    if let Err(e) = file.flush() {
+        return Attempt::Failed(e.to_string());
+    }
+
+    match total {
+        Some(total) if downloaded < total => {
+            Attempt::Retry(format!("Connection closed after {} of {} bytes", downloaded, total))
+        }
+        _ => Attempt::Finished,
+    }
+}
+
+/// Removes a partial file together with the validator of the file it belongs to
+///
+/// * `path` - The path of the partial file
+pub fn discard_partial(path: &Path) {
+    let _ = fs::remove_file(path);
+    let _ = fs::remove_file(validator_path(path));
+}
+
+/// Gets the size of the partial file, 0 if there is none
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Gets the path the validator of a partial file is saved to
+fn validator_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".validator");
+    path.with_file_name(name)
+}
+
+fn load_validator(path: &Path) -> Option<Validator> {
+    let content = fs::read_to_string(validator_path(path)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn save_validator(path: &Path, validator: &Validator) -> Result<(), String> {
+    let json = serde_json::to_string(validator).map_err(|e| e.to_string())?;
+    fs::write(validator_path(path), json).map_err(|e| e.to_string())
+}
+
+/// Reads the start and the total size from a Content-Range header like "bytes 200-999/1000"
+fn content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let (range, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split_once('-')?.0.trim().parse().ok()?;
+    Some((start, total.trim().parse().ok()))
+}
+
+/// Checks if a range continues the partial file of the same file
+///
+/// * `range` - The start and the total size of the received range
+/// * `existing` - The size of the partial file
+/// * `expected` - The total size the file had when the partial file was started
+fn continues_partial(range: Option<(u64, Option<u64>)>, existing: u64, expected: Option<u64>) -> bool {
+    match range {
+        Some((start, total)) => {
+            start == existing
+                && match (total, expected) {
+                    (Some(total), Some(expected)) => total == expected,
+                    _ => true,
+                }
+        }
+        None => false,
+    }
+}
+
+/// Checks if a failed request might work when it's sent again
+fn is_transient(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+}
+
+/// Gets the delay before a retry, doubling with every retry up to half a minute
+fn backoff_delay(retry: u32) -> Duration {
+    Duration::from_secs((1u64 << retry.min(5)).min(30))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_content_range() {
+        assert_eq!(content_range("bytes 200-999/1000"), Some((200, Some(1000))));
+        assert_eq!(content_range("bytes 200-999/*"), Some((200, None)));
+        assert_eq!(content_range("invalid"), None);
+    }
+
+    #[test]
+    fn test_continues_partial() {
+        assert!(continues_partial(Some((200, Some(1000))), 200, Some(1000)));
+        assert!(continues_partial(Some((200, None)), 200, Some(1000)));
+        // A partial of another release has another size or gets another range
+        assert!(!continues_partial(Some((200, Some(2000))), 200, Some(1000)));
+        assert!(!continues_partial(Some((0, Some(1000))), 200, Some(1000)));
+        assert!(!continues_partial(None, 200, Some(1000)));
+    }
+
+    #[test]
+    fn test_validator() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("smapi.zip");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, "W/\"weak\"".parse().unwrap());
+        headers.insert(LAST_MODIFIED, "Wed, 21 Oct 2026 07:28:00 GMT".parse().unwrap());
+        let validator = Validator::from_headers(&headers, Some(1000));
+        assert_eq!(validator.if_range(), Some("Wed, 21 Oct 2026 07:28:00 GMT"));
+
+        save_validator(&path, &validator).unwrap();
+        fs::write(&path, "partial").unwrap();
+        assert_eq!(load_validator(&path), Some(validator));
+
+        discard_partial(&path);
+        assert!(!path.exists());
+        assert_eq!(load_validator(&path), None);
+    }
+
+    #[test]
+    fn test_retry_rules() {
+        assert!(is_transient(StatusCode::BAD_GATEWAY));
+        assert!(is_transient(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_transient(StatusCode::FORBIDDEN));
+
+        assert_eq!(backoff_delay(0), Duration::from_secs(1));
+        assert_eq!(backoff_delay(3), Duration::from_secs(8));
+        assert_eq!(backoff_delay(10), Duration::from_secs(30));
+    }
+}
@@ -1,4 +1,9 @@
+pub mod archives;
 pub mod browser;
+pub mod extraction;
+pub mod http_download;
 pub mod paths;
+pub mod persistence;
+pub mod semantic_version;
 pub mod version_extractor;
 pub mod zips;
@@ -1,8 +1,8 @@
-use crate::app::utility::paths;
+use crate::app::models::mod_info::folder_name;
+use crate::app::utility::{paths, persistence};
 use crate::app::{config, profiles};
 use std::path::PathBuf;
 use std::{env, fs};
-use tauri::command;
 
 pub fn appdata_path() -> PathBuf {
     let mut path = dirs::config_dir().unwrap();
@@ -34,6 +34,36 @@ pub fn mod_json_path() -> PathBuf {
     mods_path
 }
 
+pub fn download_queue_path() -> PathBuf {
+    let mut path = dirs::config_dir().unwrap();
+    path.push("Junimo");
+    path.push("downloads.json");
+    path
+}
+
+pub fn quarantine_path() -> PathBuf {
+    let mut path = dirs::config_dir().unwrap();
+    path.push("Junimo");
+    path.push("quarantine");
+
+    if !path.exists() {
+        fs::create_dir_all(&path).unwrap();
+    }
+
+    path
+}
+
+/// Gets the mods folder of a profile, that links to the mods of the profile
+///
+/// * `profile_name` - The name of the profile
+pub fn profile_mods_path(profile_name: &str) -> PathBuf {
+    let mut path = dirs::config_dir().unwrap();
+    path.push("Junimo");
+    path.push("profiles");
+    path.push(folder_name(profile_name));
+    path
+}
+
 pub fn temp_path() -> PathBuf {
     let mut temp_path = std::env::temp_dir();
     temp_path.push("Junimo");
@@ -42,7 +72,7 @@ pub fn temp_path() -> PathBuf {
 }
 
 pub fn get_game_path() -> PathBuf {
-    let config = config::get_config(config_path());
+    let config = config::load_config(config_path());
     let game_path = config.game_path;
     if game_path == "" {
         return appdata_path();
@@ -50,7 +80,6 @@ pub fn get_game_path() -> PathBuf {
     PathBuf::from(game_path)
 }
 
-#[command]
 pub fn config_path() -> PathBuf {
     let mut config_path = dirs::config_dir().unwrap();
     config_path.push("Junimo");
@@ -58,19 +87,39 @@ pub fn config_path() -> PathBuf {
     config_path
 }
 
-#[command]
 pub fn profile_path() -> PathBuf {
     let mut path = dirs::config_dir().unwrap();
     path.push("Junimo");
     path.push("profile.json");
 
-    if !path.exists() {
-        profiles::save_profiles(&vec![], &path);
+    if !persistence::exists(&path) {
+        profiles::check_path(&path);
     }
 
     path
 }
 
+/// The paths as Tauri commands for the frontend
+pub mod commands {
+    use std::path::PathBuf;
+
+    use tauri::command;
+
+    use crate::app::error::JunimoError;
+
+    /// Tauri command to get the path of the config.json file
+    #[command]
+    pub fn config_path() -> Result<PathBuf, JunimoError> {
+        Ok(super::config_path())
+    }
+
+    /// Tauri command to get the path of the profile.json file
+    #[command]
+    pub fn profile_path() -> Result<PathBuf, JunimoError> {
+        Ok(super::profile_path())
+    }
+}
+
 pub fn get_app_bundle_path() -> Option<String> {
     env::current_exe().ok().and_then(|path| {
         path.parent() // Points to the executable's directory, typically Contents/MacOS
@@ -0,0 +1,219 @@
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::app::error::JunimoError;
+
+/// Saves a value as json without ever leaving a half written file behind.
+/// The json is written into a temporary file next to the target, which then replaces the target.
+/// The previous file is kept as backup, as long as it was valid json.
+///
+/// * `path` - The path to the json file
+/// * `value` - The value to save
+///
+/// # Returns an error if the file couldn't be written
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), JunimoError> {
+    let json = serde_json::to_string(value)?;
+    write_atomic(path, json.as_bytes())
+}
+
+/// Reads a json file that was saved by `write_json`. If the file is missing or broken,
+/// the backup is used instead and restored. A broken file is moved aside, so it can be inspected.
+/// A file of a newer version of Junimo isn't broken, so it is left untouched.
+///
+/// * `path` - The path to the json file
+/// * `parse` - Parses the content of the file, returns `JunimoError::Unsupported` for files of newer versions
+///
+/// # Returns the parsed file or an error if neither the file nor its backup could be parsed
+pub fn read_json<T, F>(path: &Path, parse: F) -> Result<T, JunimoError>
+where
+    F: Fn(&str) -> Result<T, JunimoError>,
+{
+    let error = match fs::read_to_string(path) {
+        Ok(content) => match parse(&content) {
+            Ok(value) => return Ok(value),
+            Err(e @ JunimoError::Unsupported(_)) => return Err(e),
+            Err(e) => e.to_string(),
+        },
+        Err(e) => e.to_string(),
+    };
+
+    if path.exists() {
+        let _ = fs::rename(path, corrupt_path(path));
+    }
+
+    let backup = sibling(path, "bak");
+    let content = fs::read_to_string(&backup)
+        .map_err(|_| JunimoError::Json(format!("{} is broken and has no backup: {}", path.display(), error)))?;
+    let value = parse(&content).map_err(|e| match e {
+        JunimoError::Unsupported(_) => e,
+        e => JunimoError::Json(format!("{} and its backup are broken: {}", path.display(), e)),
+    })?;
+
+    println!("Recovered {} from its backup: {}", path.display(), error);
+    write_atomic(path, content.as_bytes())?;
+    Ok(value)
+}
+
+/// Checks if a json file or its backup exists. A crash while saving can leave only the backup behind,
+/// which must not be overwritten by a new default file.
+///
+/// * `path` - The path to the json file
+pub fn exists(path: &Path) -> bool {
+    path.exists() || sibling(path, "bak").exists()
+}
+
+/// Replaces a file with new content in a way that survives crashes at any point
+///
+/// * `path` - The path to the file
+/// * `content` - The new content
+fn write_atomic(path: &Path, content: &[u8]) -> Result<(), JunimoError> {
+    let temp = sibling(path, "tmp");
+    let mut file = File::create(&temp)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    drop(file);
+
+    // Only a valid file becomes the backup, so a broken file never replaces the last good copy.
+    // The file is copied, so the target exists at every point and the rename replaces it at once.
+    let is_valid = fs::read_to_string(path)
+        .map(|current| serde_json::from_str::<serde_json::Value>(&current).is_ok())
+        .unwrap_or(false);
+    if is_valid {
+        fs::copy(path, sibling(path, "bak"))?;
+    }
+
+    fs::rename(&temp, path)?;
+    sync_dir(path);
+    Ok(())
+}
+
+/// Gets a file next to a file with an additional extension, like profile.json.bak
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.{}", name, extension))
+}
+
+/// Gets the path a broken file is moved to. Every broken file gets its own name, so an earlier one isn't overwritten.
+fn corrupt_path(path: &Path) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or_default();
+    sibling(path, &format!("corrupt-{}", timestamp))
+}
+
+/// Makes sure the renames of a folder are written to the disk
+#[cfg(unix)]
+fn sync_dir(path: &Path) {
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+}
+
+/// Windows writes renames through without opening the folder
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn parse(content: &str) -> Result<Vec<u32>, JunimoError> {
+        Ok(serde_json::from_str(content)?)
+    }
+
+    /// Gets the broken files that were moved aside
+    fn corrupt_files(path: &Path) -> Vec<PathBuf> {
+        let prefix = format!("{}.corrupt-", path.file_name().unwrap().to_string_lossy());
+        fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|file| file.file_name().unwrap().to_string_lossy().starts_with(&prefix))
+            .collect()
+    }
+
+    #[test]
+    fn test_write_keeps_backup() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("mods.json");
+
+        write_json(&path, &vec![1]).unwrap();
+        write_json(&path, &vec![1, 2]).unwrap();
+        assert_eq!(read_json(&path, parse).unwrap(), vec![1, 2]);
+        assert_eq!(parse(&fs::read_to_string(sibling(&path, "bak")).unwrap()).unwrap(), vec![1]);
+        assert!(!sibling(&path, "tmp").exists());
+
+        // A broken file never becomes the backup
+        fs::write(&path, "[1, 2").unwrap();
+        write_json(&path, &vec![3]).unwrap();
+        assert_eq!(parse(&fs::read_to_string(sibling(&path, "bak")).unwrap()).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn test_recover_from_backup() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("profile.json");
+        write_json(&path, &vec![1]).unwrap();
+        write_json(&path, &vec![2]).unwrap();
+
+        // Truncated by a crash while writing
+        fs::write(&path, "").unwrap();
+        assert_eq!(read_json(&path, parse).unwrap(), vec![1]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[1]");
+        assert_eq!(corrupt_files(&path).len(), 1);
+
+        // The file went missing, only the backup is left
+        fs::rename(&path, sibling(&path, "bak")).unwrap();
+        assert!(exists(&path));
+        assert_eq!(read_json(&path, parse).unwrap(), vec![1]);
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn test_broken_without_backup() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("config.json");
+        fs::write(&path, "{ broken").unwrap();
+
+        assert!(read_json(&path, parse).is_err());
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(&corrupt_files(&path)[0]).unwrap(), "{ broken");
+
+        // A second broken file doesn't replace the first one
+        fs::write(&path, "[ broken").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert!(read_json(&path, parse).is_err());
+        assert_eq!(corrupt_files(&path).len(), 2);
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("profile.json");
+        write_json(&path, &vec![1]).unwrap();
+        write_json(&path, &vec![99]).unwrap();
+
+        let newer = |content: &str| -> Result<Vec<u32>, JunimoError> {
+            let value = parse(content)?;
+            match value[0] > 10 {
+                true => Err(JunimoError::Unsupported("profile.json has version 99".to_string())),
+                false => Ok(value),
+            }
+        };
+        assert_eq!(
+            read_json(&path, newer),
+            Err(JunimoError::Unsupported("profile.json has version 99".to_string()))
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[99]");
+        assert!(corrupt_files(&path).is_empty());
+    }
+}
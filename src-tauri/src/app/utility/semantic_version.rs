@@ -0,0 +1,174 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// A version following SMAPI's semantic version rules.
+/// Besides normal semantic versions this allows a missing minor/patch number, a fourth
+/// platform release number (e.g. 1.5.6.1), a leading "v" and prerelease tags like "beta.2".
+#[derive(Debug, Clone)]
+pub struct SemanticVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub platform_release: u64,
+    pub prerelease: Option<String>,
+    pub build: Option<String>,
+}
+
+impl SemanticVersion {
+    /// Compares the prerelease tags of two versions. A release is newer than any prerelease,
+    /// numeric parts are compared numerically and text parts case-insensitively.
+    fn compare_prerelease(&self, other: &Self) -> Ordering {
+        match (&self.prerelease, &other.prerelease) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(own), Some(other)) => {
+                let own_parts: Vec<&str> = own.split(|c| c == '.' || c == '-').collect();
+                let other_parts: Vec<&str> = other.split(|c| c == '.' || c == '-').collect();
+
+                for (own_part, other_part) in own_parts.iter().zip(other_parts.iter()) {
+                    let ordering = match (own_part.parse::<u64>(), other_part.parse::<u64>()) {
+                        (Ok(own_number), Ok(other_number)) => own_number.cmp(&other_number),
+                        (Ok(_), Err(_)) => Ordering::Less,
+                        (Err(_), Ok(_)) => Ordering::Greater,
+                        (Err(_), Err(_)) => own_part
+                            .to_lowercase()
+                            .cmp(&other_part.to_lowercase()),
+                    };
+                    if ordering != Ordering::Equal {
+                        return ordering;
+                    }
+                }
+
+                own_parts.len().cmp(&other_parts.len())
+            }
+        }
+    }
+}
+
+impl FromStr for SemanticVersion {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let trimmed = input.trim();
+        let trimmed = trimmed
+            .strip_prefix('v')
+            .or_else(|| trimmed.strip_prefix('V'))
+            .unwrap_or(trimmed);
+
+        let (rest, build) = match trimmed.split_once('+') {
+            Some((rest, build)) => (rest, Some(build.to_string())),
+            None => (trimmed, None),
+        };
+        let (numbers, prerelease) = match rest.split_once('-') {
+            Some((numbers, prerelease)) => (numbers, Some(prerelease.to_string())),
+            None => (rest, None),
+        };
+
+        if prerelease.as_deref() == Some("") || build.as_deref() == Some("") {
+            return Err(format!("Invalid version: {}", input));
+        }
+
+        let parts: Vec<&str> = numbers.split('.').collect();
+        if parts.is_empty() || parts.len() > 4 {
+            return Err(format!("Invalid version: {}", input));
+        }
+
+        let mut values = [0u64; 4];
+        for (index, part) in parts.iter().enumerate() {
+            values[index] = part
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid version: {}", input))?;
+        }
+
+        Ok(SemanticVersion {
+            major: values[0],
+            minor: values[1],
+            patch: values[2],
+            platform_release: values[3],
+            prerelease,
+            build,
+        })
+    }
+}
+
+impl fmt::Display for SemanticVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if self.platform_release != 0 {
+            write!(f, ".{}", self.platform_release)?;
+        }
+        if let Some(prerelease) = &self.prerelease {
+            write!(f, "-{}", prerelease)?;
+        }
+        if let Some(build) = &self.build {
+            write!(f, "+{}", build)?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for SemanticVersion {
+    /// Build metadata is ignored, just like SMAPI does
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.major
+            .cmp(&other.major)
+            .then(self.minor.cmp(&other.minor))
+            .then(self.patch.cmp(&other.patch))
+            .then(self.platform_release.cmp(&other.platform_release))
+            .then_with(|| self.compare_prerelease(other))
+    }
+}
+
+impl PartialOrd for SemanticVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SemanticVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SemanticVersion {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(input: &str) -> SemanticVersion {
+        input.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let parsed = version("v1.2.3.4-beta.2+build.5");
+        assert_eq!(parsed.major, 1);
+        assert_eq!(parsed.minor, 2);
+        assert_eq!(parsed.patch, 3);
+        assert_eq!(parsed.platform_release, 4);
+        assert_eq!(parsed.prerelease, Some("beta.2".to_string()));
+        assert_eq!(parsed.build, Some("build.5".to_string()));
+
+        assert_eq!(version("2").to_string(), "2.0.0");
+        assert_eq!(version("1.6").to_string(), "1.6.0");
+        assert!("1.a.0".parse::<SemanticVersion>().is_err());
+        assert!("1.0.0-".parse::<SemanticVersion>().is_err());
+        assert!("1.2.3.4.5".parse::<SemanticVersion>().is_err());
+    }
+
+    #[test]
+    fn test_compare() {
+        assert!(version("1.10.0") > version("1.9.0"));
+        assert!(version("1.5.6.1") > version("1.5.6"));
+        assert!(version("1.0.0") > version("1.0.0-beta"));
+        assert!(version("1.0.0-beta.10") > version("1.0.0-beta.2"));
+        assert!(version("1.0.0-beta") > version("1.0.0-alpha.5"));
+        assert!(version("1.0.0-beta.2") > version("1.0.0-beta"));
+        assert!(version("1.0.0-Beta") == version("1.0.0-beta"));
+        assert!(version("1.0.0+abc") == version("1.0"));
+    }
+}
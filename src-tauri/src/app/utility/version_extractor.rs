@@ -1,193 +1,517 @@
-use crate::app::utility::{paths, version_extractor};
-use std::ffi::OsString;
-use std::fs::File;
-use std::io::Read;
-#[cfg(target_os = "windows")]
-use std::os::windows::ffi::OsStrExt;
-use widestring::U16CString;
-#[cfg(target_os = "windows")]
-use winapi::shared::minwindef::{DWORD, LPVOID};
-#[cfg(target_os = "windows")]
-use winapi::um::winver::{GetFileVersionInfoSizeW, GetFileVersionInfoW, VerQueryValueW};
-
-#[cfg(target_family = "unix")]
-use pelite::pe64::{Pe, PeFile};
-#[cfg(target_family = "unix")]
-use pelite::resources::{FindError, Resources};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use pelite::PeFile;
+
+use crate::app::error::JunimoError;
+use crate::app::utility::paths;
+
+// The game and SMAPI are .NET assemblies, which are PE files on every platform. The version is read
+// from the version resource and, if the assembly has none, from the assembly table of its metadata.
+// Everything is parsed by hand out of the bytes, so nothing depends on Windows APIs.
+
+/// The files of which at least one is part of every Stardew Valley installation.
+/// Since 1.5.5 the game is a dll on every platform, before it was an exe that ran through Mono on Linux and macOS.
+pub const GAME_FILES: [&str; 3] = ["Stardew Valley.dll", "Stardew Valley.exe", "StardewValley.exe"];
+
+/// The index of the CLI header in the data directories of a PE file
+const COM_DESCRIPTOR: usize = 14;
+/// The signature at the start of the metadata of an assembly
+const METADATA_SIGNATURE: u32 = 0x424A5342;
+/// The index of the assembly table, which contains the version of the assembly
+const ASSEMBLY_TABLE: usize = 0x20;
 
 /// Struct that forms a dll version
-#[derive(Debug)]
-struct Version {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Version {
     pub major: u16,
     pub minor: u16,
     pub patch: u16,
-    build: u16,
-}
-
-#[cfg(target_os = "windows")]
-/// Struct to extract the version information from a DLL
-#[repr(C)]
-struct VsFixedfileinfo {
-    dw_signature: DWORD,
-    dw_struc_version: DWORD,
-    dw_file_version_ms: DWORD,
-    dw_file_version_ls: DWORD,
-    dw_product_version_ms: DWORD,
-    dw_product_version_ls: DWORD,
-    dw_file_flags_mask: DWORD,
-    dw_file_flags: DWORD,
-    dw_file_os: DWORD,
-    dw_file_type: DWORD,
-    dw_file_subtype: DWORD,
-    dw_file_date_ms: DWORD,
-    dw_file_date_ls: DWORD,
-}
-
-#[cfg(target_os = "windows")]
+    pub build: u16,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
 /// Extracts the version of a dll file in the game directory
 ///
 /// * `dll` - The name of the dll file
 ///
 /// # Returns Version of the dll or none if the version could not be extracted
 pub fn get_version(dll: &str) -> Option<String> {
-    let path = paths::get_game_path()
-        .join(dll)
-        .to_string_lossy()
-        .to_string();
-    return match get_version_info_from_dll(path.as_str()) {
-        Some(version) => Some(format!(
-            "{}.{}.{}",
-            version.major, version.minor, version.patch
-        )),
-        None => None,
-    };
+    get_file_version(&paths::get_game_path().join(dll))
 }
 
-#[cfg(target_os = "windows")]
-/// Extracts the version of our games and smapis dll
-fn get_version_info_from_dll(path: &str) -> Option<Version> {
-    // Convert path to a wide string
-    let wide_path: Vec<u16> = OsString::from(path)
-        .encode_wide()
-        .chain(std::iter::once(0))
-        .collect();
+/// Extracts the version of a dll or exe file
+///
+/// * `path` - The path to the file
+///
+/// # Returns Version of the file or none if the version could not be extracted
+pub fn get_file_version(path: &Path) -> Option<String> {
+    read_version(path).ok().map(|version| version.to_string())
+}
 
-    // Get the size of the version info
-    let mut handle: DWORD = 0;
-    let size = unsafe { GetFileVersionInfoSizeW(wide_path.as_ptr(), &mut handle) };
-    if size == 0 {
-        return None;
+/// Gets the version of the game in the game directory
+///
+/// # Returns the version or an error if the game wasn't found or has no version
+pub fn game_version() -> Result<String, JunimoError> {
+    let game_path = paths::get_game_path();
+    let file = GAME_FILES
+        .iter()
+        .map(|file| game_path.join(file))
+        .find(|file| file.is_file())
+        .ok_or(JunimoError::GameNotFound)?;
+    Ok(read_version(&file)?.to_string())
+}
+
+/// Reads the version of a dll or exe file
+///
+/// * `path` - The path to the file
+///
+/// # Returns the version or an error if the file isn't an assembly or has no version
+pub fn read_version(path: &Path) -> Result<Version, JunimoError> {
+    let bytes = fs::read(path)?;
+    version_from_bytes(&bytes).map_err(|e| {
+        JunimoError::Other(format!(
+            "The version of {} couldn't be read: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+/// Reads the version of a PE32 or PE32+ file
+///
+/// * `bytes` - The content of the file
+fn version_from_bytes(bytes: &[u8]) -> Result<Version, String> {
+    let pe = PeFile::from_bytes(bytes).map_err(|e| e.to_string())?;
+    match resource_version(&pe) {
+        Some(version) => Ok(version),
+        None => assembly_version(&pe),
     }
+}
 
-    // Read the version info into a buffer
-    let mut buffer: Vec<u8> = vec![0; size as usize];
-    let result = unsafe {
-        GetFileVersionInfoW(
-            wide_path.as_ptr(),
-            handle as usize as DWORD,
-            size,
-            buffer.as_mut_ptr() as *mut _,
-        )
+/// Reads the file version out of the version resource
+fn resource_version(pe: &PeFile) -> Option<Version> {
+    let fixed = pe.resources().ok()?.version_info().ok()?.fixed()?;
+    let version = Version {
+        major: fixed.dwFileVersion.Major,
+        minor: fixed.dwFileVersion.Minor,
+        patch: fixed.dwFileVersion.Patch,
+        build: fixed.dwFileVersion.Build,
     };
-    if result == 0 {
+    // Some builds leave the resource empty
+    if version == (Version { major: 0, minor: 0, patch: 0, build: 0 }) {
         return None;
     }
+    Some(version)
+}
 
-    // Extract the version information
-    let mut lp_buffer: LPVOID = std::ptr::null_mut();
-    let mut len: u32 = 0;
-    let sub_block = U16CString::from_str("\\").unwrap();
-    let result = unsafe {
-        VerQueryValueW(
-            buffer.as_ptr() as *const _,
-            sub_block.as_ptr(),
-            &mut lp_buffer,
-            &mut len,
-        )
-    };
-    if result == 0 {
-        return None;
+/// Reads the assembly version out of the metadata of a .NET assembly
+fn assembly_version(pe: &PeFile) -> Result<Version, String> {
+    let directory = pe
+        .data_directory()
+        .get(COM_DESCRIPTOR)
+        .filter(|directory| directory.VirtualAddress != 0)
+        .ok_or("The file has no version and isn't a .NET assembly")?;
+    let cli_header = pe
+        .derva_slice::<u8>(directory.VirtualAddress, 16)
+        .map_err(|e| e.to_string())?;
+
+    let mut reader = Reader::new(cli_header, 8);
+    let metadata_rva = reader.u32()?;
+    let metadata_size = reader.u32()? as usize;
+    let metadata = pe
+        .derva_slice::<u8>(metadata_rva, metadata_size)
+        .map_err(|e| e.to_string())?;
+    metadata_version(metadata)
+}
+
+/// Reads the version out of the assembly table of the metadata
+///
+/// * `metadata` - The metadata the CLI header points to
+fn metadata_version(metadata: &[u8]) -> Result<Version, String> {
+    let tables = metadata_stream(metadata, "#~").or_else(|_| metadata_stream(metadata, "#-"))?;
+
+    let mut reader = Reader::new(tables, 6);
+    let heap_sizes = reader.u8()?;
+    reader.take(1)?;
+    let valid = reader.u64()?;
+    reader.take(8)?;
+
+    let mut rows = [0u32; 64];
+    for (table, count) in rows.iter_mut().enumerate() {
+        if valid & (1 << table) != 0 {
+            *count = reader.u32()?;
+        }
+    }
+    if rows[ASSEMBLY_TABLE] == 0 {
+        return Err("The assembly has no version".to_string());
+    }
+
+    // The tables are stored one after another, so every table before the assembly table is skipped
+    let sizes = IndexSizes { heap_sizes, rows };
+    for (table, columns) in TABLE_COLUMNS.iter().enumerate() {
+        let row_size: usize = columns.iter().map(|column| sizes.size(column)).sum();
+        reader.take(row_size * rows[table] as usize)?;
+    }
+
+    reader.take(4)?;
+    Ok(Version {
+        major: reader.u16()?,
+        minor: reader.u16()?,
+        patch: reader.u16()?,
+        build: reader.u16()?,
+    })
+}
+
+/// Finds a stream in the metadata by its name
+fn metadata_stream<'a>(metadata: &'a [u8], name: &str) -> Result<&'a [u8], String> {
+    let mut reader = Reader::new(metadata, 0);
+    if reader.u32()? != METADATA_SIGNATURE {
+        return Err("The assembly metadata is invalid".to_string());
     }
+    reader.take(8)?;
+    let version_length = reader.u32()? as usize;
+    reader.take(version_length)?;
+    reader.take(2)?;
+
+    let streams = reader.u16()?;
+    for _ in 0..streams {
+        let offset = reader.u32()? as usize;
+        let size = reader.u32()? as usize;
 
-    // Safely dereference the pointer without moving the content
-    unsafe {
-        let version_info: &VsFixedfileinfo = &*(lp_buffer as *const VsFixedfileinfo);
-        if version_info.dw_signature != 0xfeef04bd {
-            return None;
+        // The name ends with a zero and is padded to 4 bytes
+        let start = reader.position;
+        let mut stream_name = Vec::new();
+        loop {
+            match reader.u8()? {
+                0 => break,
+                c => stream_name.push(c),
+            }
         }
+        reader.take((4 - (reader.position - start) % 4) % 4)?;
 
-        Some(Version {
-            major: (version_info.dw_file_version_ms >> 16) as u16,
-            minor: (version_info.dw_file_version_ms & 0xFFFF) as u16,
-            patch: (version_info.dw_file_version_ls >> 16) as u16,
-            build: (version_info.dw_file_version_ls & 0xFFFF) as u16,
-        })
+        if stream_name == name.as_bytes() {
+            return offset
+                .checked_add(size)
+                .and_then(|end| metadata.get(offset..end))
+                .ok_or_else(|| format!("The {} stream is outside of the metadata", name));
+        }
     }
+    Err(format!("The assembly metadata has no {} stream", name))
 }
 
-#[cfg(target_family = "unix")]
-/// Extracts the version of a dll file in the game directory
-///
-/// * `dll` - The name of the dll file
-///
-/// # Returns Version of the dll or none if the version could not be extracted
-pub fn get_version(dll: &str) -> Option<String> {
-    let path = paths::get_game_path()
-        .join(dll)
-        .to_string_lossy()
-        .to_string();
-    return match get_version_info_from_dll(path.as_str()) {
-        Ok(version) => match version {
-            Some(version) => Some(format!(
-                "{}.{}.{}",
-                version.major, version.minor, version.patch
-            )),
-            None => None,
-        },
-        _ => None,
-    };
+/// A column of a metadata table
+enum Column {
+    Fixed(usize),
+    String,
+    Guid,
+    Blob,
+    /// An index into another table
+    Table(usize),
+    /// An index into one of several tables, the lowest bits tell which one
+    Coded(&'static [usize], u32),
 }
 
-#[cfg(target_family = "unix")]
-/// Extracts the version of our games and smapis dll
-fn get_version_info_from_dll(path: &str) -> Result<Option<Version>, String> {
-    // Load the DLL file
-    let file = File::open(path);
-    if file.is_err() {
-        return Err(file.err().unwrap().to_string());
+const TYPE_DEF_OR_REF: Column = Column::Coded(&[0x02, 0x01, 0x1B], 2);
+const HAS_CONSTANT: Column = Column::Coded(&[0x04, 0x08, 0x17], 2);
+const HAS_CUSTOM_ATTRIBUTE: Column = Column::Coded(
+    &[
+        0x06, 0x04, 0x01, 0x02, 0x08, 0x09, 0x0A, 0x00, 0x0E, 0x17, 0x14, 0x11, 0x1A, 0x1B, 0x20, 0x23,
+        0x26, 0x27, 0x28, 0x2A, 0x2C, 0x2B,
+    ],
+    5,
+);
+const HAS_FIELD_MARSHAL: Column = Column::Coded(&[0x04, 0x08], 1);
+const HAS_DECL_SECURITY: Column = Column::Coded(&[0x02, 0x06, 0x20], 2);
+const MEMBER_REF_PARENT: Column = Column::Coded(&[0x02, 0x01, 0x1A, 0x06, 0x1B], 3);
+const HAS_SEMANTICS: Column = Column::Coded(&[0x14, 0x17], 1);
+const METHOD_DEF_OR_REF: Column = Column::Coded(&[0x06, 0x0A], 1);
+const MEMBER_FORWARDED: Column = Column::Coded(&[0x04, 0x06], 1);
+const CUSTOM_ATTRIBUTE_TYPE: Column = Column::Coded(&[0x06, 0x0A], 3);
+const RESOLUTION_SCOPE: Column = Column::Coded(&[0x00, 0x1A, 0x23, 0x01], 2);
+
+/// The columns of every table that is stored before the assembly table (ECMA-335 II.22)
+const TABLE_COLUMNS: [&[Column]; ASSEMBLY_TABLE] = [
+    // Module
+    &[Column::Fixed(2), Column::String, Column::Guid, Column::Guid, Column::Guid],
+    // TypeRef
+    &[RESOLUTION_SCOPE, Column::String, Column::String],
+    // TypeDef
+    &[Column::Fixed(4), Column::String, Column::String, TYPE_DEF_OR_REF, Column::Table(0x04), Column::Table(0x06)],
+    // FieldPtr
+    &[Column::Table(0x04)],
+    // Field
+    &[Column::Fixed(2), Column::String, Column::Blob],
+    // MethodPtr
+    &[Column::Table(0x06)],
+    // MethodDef
+    &[Column::Fixed(4), Column::Fixed(2), Column::Fixed(2), Column::String, Column::Blob, Column::Table(0x08)],
+    // ParamPtr
+    &[Column::Table(0x08)],
+    // Param
+    &[Column::Fixed(2), Column::Fixed(2), Column::String],
+    // InterfaceImpl
+    &[Column::Table(0x02), TYPE_DEF_OR_REF],
+    // MemberRef
+    &[MEMBER_REF_PARENT, Column::String, Column::Blob],
+    // Constant
+    &[Column::Fixed(2), HAS_CONSTANT, Column::Blob],
+    // CustomAttribute
+    &[HAS_CUSTOM_ATTRIBUTE, CUSTOM_ATTRIBUTE_TYPE, Column::Blob],
+    // FieldMarshal
+    &[HAS_FIELD_MARSHAL, Column::Blob],
+    // DeclSecurity
+    &[Column::Fixed(2), HAS_DECL_SECURITY, Column::Blob],
+    // ClassLayout
+    &[Column::Fixed(2), Column::Fixed(4), Column::Table(0x02)],
+    // FieldLayout
+    &[Column::Fixed(4), Column::Table(0x04)],
+    // StandAloneSig
+    &[Column::Blob],
+    // EventMap
+    &[Column::Table(0x02), Column::Table(0x14)],
+    // EventPtr
+    &[Column::Table(0x14)],
+    // Event
+    &[Column::Fixed(2), Column::String, TYPE_DEF_OR_REF],
+    // PropertyMap
+    &[Column::Table(0x02), Column::Table(0x17)],
+    // PropertyPtr
+    &[Column::Table(0x17)],
+    // Property
+    &[Column::Fixed(2), Column::String, Column::Blob],
+    // MethodSemantics
+    &[Column::Fixed(2), Column::Table(0x06), HAS_SEMANTICS],
+    // MethodImpl
+    &[Column::Table(0x02), METHOD_DEF_OR_REF, METHOD_DEF_OR_REF],
+    // ModuleRef
+    &[Column::String],
+    // TypeSpec
+    &[Column::Blob],
+    // ImplMap
+    &[Column::Fixed(2), MEMBER_FORWARDED, Column::String, Column::Table(0x1A)],
+    // FieldRVA
+    &[Column::Fixed(4), Column::Table(0x04)],
+    // EncLog
+    &[Column::Fixed(4), Column::Fixed(4)],
+    // EncMap
+    &[Column::Fixed(4)],
+];
+
+/// Decides whether indices into the heaps and tables take 2 or 4 bytes
+struct IndexSizes {
+    heap_sizes: u8,
+    rows: [u32; 64],
+}
+
+impl IndexSizes {
+    /// Gets the size of a column in bytes
+    fn size(&self, column: &Column) -> usize {
+        let wide = match column {
+            Column::Fixed(size) => return *size,
+            Column::String => self.heap_sizes & 0x01 != 0,
+            Column::Guid => self.heap_sizes & 0x02 != 0,
+            Column::Blob => self.heap_sizes & 0x04 != 0,
+            Column::Table(table) => self.rows[*table] > 0xFFFF,
+            Column::Coded(tables, bits) => tables.iter().any(|table| self.rows[*table] >= 1 << (16 - bits)),
+        };
+        if wide {
+            4
+        } else {
+            2
+        }
     }
-    let mut file = file.unwrap();
+}
+
+/// Reads little endian numbers out of bytes and fails instead of panicking when the bytes end
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
 
-    let mut buffer = Vec::new();
-    let _result = file.read_to_end(&mut buffer);
-    if _result.is_err() {
-        return Err(_result.err().unwrap().to_string());
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], position: usize) -> Self {
+        Reader { bytes, position }
     }
 
-    // Parse the PE file
-    let pe = PeFile::from_bytes(&buffer).expect("Failed to parse PE file");
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .position
+            .checked_add(length)
+            .and_then(|end| self.bytes.get(self.position..end))
+            .ok_or("The assembly metadata is truncated")?;
+        self.position += length;
+        Ok(bytes)
+    }
 
-    // Access the resources section
-    let resources = pe.resources().expect("Failed to get resources");
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
 
-    let version_info = resources.version_info();
-    if version_info.is_err() {
-        return Err(version_info.err().unwrap().to_string());
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
     }
-    let version_info = version_info.unwrap();
 
-    let fixed = version_info.fixed();
-    if fixed.is_none() {
-        return Ok(None);
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
     }
-    let fixed = fixed.unwrap();
 
-    let version = Version {
-        major: fixed.dwFileVersion.Major,
-        minor: fixed.dwFileVersion.Minor,
-        patch: fixed.dwFileVersion.Patch,
-        build: fixed.dwFileVersion.Build,
-    };
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(self.u32()? as u64 | (self.u32()? as u64) << 32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
 
-    Ok(Some(version))
+    /// Builds the metadata of an assembly with a module and the assembly table
+    fn metadata(version: [u16; 4]) -> Vec<u8> {
+        let mut tables = vec![0, 0, 0, 0, 2, 0, 0, 1];
+        tables.extend_from_slice(&((1u64 << 0x00) | (1u64 << ASSEMBLY_TABLE)).to_le_bytes());
+        tables.extend_from_slice(&0u64.to_le_bytes());
+        tables.extend_from_slice(&1u32.to_le_bytes());
+        tables.extend_from_slice(&1u32.to_le_bytes());
+        // Module: generation, name, mvid, enc id and enc base id
+        tables.extend_from_slice(&[0; 10]);
+        // Assembly: hash algorithm, version, flags, public key, name and culture
+        tables.extend_from_slice(&0x8004u32.to_le_bytes());
+        for part in version {
+            tables.extend_from_slice(&part.to_le_bytes());
+        }
+        tables.extend_from_slice(&[0; 10]);
+
+        let runtime = b"v4.0.30319\0\0";
+        let mut metadata = METADATA_SIGNATURE.to_le_bytes().to_vec();
+        metadata.extend_from_slice(&[1, 0, 1, 0, 0, 0, 0, 0]);
+        metadata.extend_from_slice(&(runtime.len() as u32).to_le_bytes());
+        metadata.extend_from_slice(runtime);
+        metadata.extend_from_slice(&[0, 0]);
+        metadata.extend_from_slice(&2u16.to_le_bytes());
+
+        // A #Strings stream that is skipped and the #~ stream behind the headers
+        let offset = metadata.len() as u32 + 8 + 12 + 8 + 4;
+        metadata.extend_from_slice(&offset.to_le_bytes());
+        metadata.extend_from_slice(&0u32.to_le_bytes());
+        metadata.extend_from_slice(b"#Strings\0\0\0\0");
+        metadata.extend_from_slice(&offset.to_le_bytes());
+        metadata.extend_from_slice(&(tables.len() as u32).to_le_bytes());
+        metadata.extend_from_slice(b"#~\0\0");
+        metadata.extend_from_slice(&tables);
+        metadata
+    }
+
+    /// Builds a PE32 or PE32+ assembly without a version resource, like a managed dll
+    fn assembly(pe32_plus: bool, metadata: &[u8]) -> Vec<u8> {
+        let mut pe = vec![0u8; 0x40];
+        pe[0..2].copy_from_slice(b"MZ");
+        pe[0x3C..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        pe.extend_from_slice(b"PE\0\0");
+
+        // File header
+        let optional_size: u16 = if pe32_plus { 240 } else { 224 };
+        pe.extend_from_slice(&(if pe32_plus { 0x8664u16 } else { 0x14Cu16 }).to_le_bytes());
+        pe.extend_from_slice(&1u16.to_le_bytes());
+        pe.extend_from_slice(&[0; 12]);
+        pe.extend_from_slice(&optional_size.to_le_bytes());
+        pe.extend_from_slice(&0x2102u16.to_le_bytes());
+
+        // Optional header with one section at 0x200 in the file and the image
+        let section_size = (72 + metadata.len() as u32 + 0x1FF) & !0x1FF;
+        pe.extend_from_slice(&(if pe32_plus { 0x20Bu16 } else { 0x10Bu16 }).to_le_bytes());
+        pe.extend_from_slice(&[0; 22]);
+        if pe32_plus {
+            pe.extend_from_slice(&0x180000000u64.to_le_bytes());
+        } else {
+            pe.extend_from_slice(&[0; 4]);
+            pe.extend_from_slice(&0x10000000u32.to_le_bytes());
+        }
+        pe.extend_from_slice(&0x200u32.to_le_bytes());
+        pe.extend_from_slice(&0x200u32.to_le_bytes());
+        pe.extend_from_slice(&[0; 16]);
+        pe.extend_from_slice(&(0x200 + section_size).to_le_bytes());
+        pe.extend_from_slice(&0x200u32.to_le_bytes());
+        pe.extend_from_slice(&[0; 8]);
+        pe.extend_from_slice(&vec![0; if pe32_plus { 32 } else { 16 }]);
+        pe.extend_from_slice(&0u32.to_le_bytes());
+        pe.extend_from_slice(&16u32.to_le_bytes());
+        for directory in 0..16 {
+            let (rva, size) = if directory == COM_DESCRIPTOR { (0x200u32, 72u32) } else { (0, 0) };
+            pe.extend_from_slice(&rva.to_le_bytes());
+            pe.extend_from_slice(&size.to_le_bytes());
+        }
+
+        // Section header
+        pe.extend_from_slice(b".text\0\0\0");
+        pe.extend_from_slice(&section_size.to_le_bytes());
+        pe.extend_from_slice(&0x200u32.to_le_bytes());
+        pe.extend_from_slice(&section_size.to_le_bytes());
+        pe.extend_from_slice(&0x200u32.to_le_bytes());
+        pe.extend_from_slice(&[0; 12]);
+        pe.extend_from_slice(&0x60000020u32.to_le_bytes());
+        pe.resize(0x200, 0);
+
+        // CLI header followed by the metadata
+        pe.extend_from_slice(&72u32.to_le_bytes());
+        pe.extend_from_slice(&[2, 0, 5, 0]);
+        pe.extend_from_slice(&(0x200u32 + 72).to_le_bytes());
+        pe.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+        pe.resize(0x200 + 72, 0);
+        pe.extend_from_slice(metadata);
+        pe.resize(0x200 + section_size as usize, 0);
+        pe
+    }
+
+    #[test]
+    fn test_metadata_version() {
+        let version = metadata_version(&metadata([1, 6, 15, 24356])).unwrap();
+        assert_eq!(version.to_string(), "1.6.15");
+        assert_eq!(version.build, 24356);
+    }
+
+    #[test]
+    fn test_pe32_and_pe32_plus() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("Stardew Valley.dll");
+
+        fs::write(&path, assembly(false, &metadata([1, 6, 15, 24356]))).unwrap();
+        assert_eq!(get_file_version(&path), Some("1.6.15".to_string()));
+
+        fs::write(&path, assembly(true, &metadata([1, 5, 6, 0]))).unwrap();
+        assert_eq!(get_file_version(&path), Some("1.5.6".to_string()));
+    }
+
+    #[test]
+    fn test_broken_metadata() {
+        let metadata = metadata([1, 6, 15, 0]);
+        assert!(metadata_version(&metadata[..metadata.len() - 8]).is_err());
+        assert!(metadata_version(&metadata[4..]).is_err());
+        assert!(metadata_version(&[]).is_err());
+    }
+
+    #[test]
+    fn test_read_version_of_invalid_files() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join("Stardew Valley.dll");
+        assert!(read_version(&path).is_err());
+
+        fs::write(&path, "not a real dll").unwrap();
+        assert!(read_version(&path).is_err());
+
+        let mut bytes = b"MZ".to_vec();
+        bytes.resize(512, 0xFF);
+        fs::write(&path, bytes).unwrap();
+        assert!(read_version(&path).is_err());
+        assert_eq!(get_file_version(&path), None);
+    }
 }
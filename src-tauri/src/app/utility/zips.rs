@@ -8,91 +8,60 @@ use walkdir::WalkDir;
 use zip::write::SimpleFileOptions;
 use zip::{ZipArchive, ZipWriter};
 
+use crate::app::models::mod_info::ModInfo;
 use crate::app::profiles::Profile;
+use crate::app::error::JunimoError;
+use crate::app::utility::archives::{self, ModArchive};
+use crate::app::utility::extraction::{self, EntryKind, Extraction, ExtractionLimits};
 use crate::app::utility::paths;
 use crate::app::{console, mod_installation, profiles};
 
-/// Unpacks a new installed zip file
-pub fn unpack_zip<R: io::Read + io::Seek>(
+/// Unpacks a new installed mod archive
+///
+/// * `app_handle` - The handle to the Tauri app
+/// * `archive` - The zip, 7z, rar or tar.gz archive of the mod
+/// * `destination` - The destination path to unpack the mod to
+/// * `limits` - The limits the archive has to stay within
+///
+/// # Returns a Result with the success state
+pub fn unpack_archive(
     app_handle: &AppHandle,
-    mut archive: ZipArchive<R>,
+    archive: &ModArchive,
     destination: &Path,
-    max: usize,
-) -> Result<String, String> {
-    let mut main_dir = "".to_string();
-    let mut has_manifest = false;
-
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i).unwrap();
-        let replaced = file.name().replace("\\", "/");
-        let split = replaced.split('/').collect::<Vec<&str>>();
-
-        if split[0] != main_dir {
-            main_dir = split[0].to_string();
-        }
-
-        if replaced.contains("__MACOSX") {
-            continue;
-        }
-
-        let outpath = match file.enclosed_name() {
-            Some(path) => destination.join(path),
-            None => continue,
-        };
-
-        if file.name().ends_with('/') {
-            if !outpath.exists() {
-                fs::create_dir_all(&outpath).unwrap();
-            }
-        } else {
-            if let Some(parent) = outpath.parent() {
-                if !parent.exists() {
-                    fs::create_dir_all(parent).unwrap();
-                }
-            }
-            let mut outfile = File::create(&outpath).unwrap();
-            io::copy(&mut file, &mut outfile).unwrap();
-
-            if outpath.to_string_lossy().contains("manifest.json") {
-                has_manifest = true;
-            }
-        }
-
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            if let Some(mode) = file.unix_mode() {
-                match std::fs::set_permissions(&outpath, std::fs::Permissions::from_mode(mode)) {
-                    Ok(_) => {}
-                    Err(e) => {
-                        return Err(e.to_string());
-                    }
-                };
-            }
-        }
+    limits: ExtractionLimits,
+) -> Result<(), String> {
+    let max = archive.entries()?.len();
 
+    archive.extract(destination, limits, |i| {
         console::modify_line(
             &app_handle,
             mod_installation::install_progress(&i, &max).to_string(),
         );
-    }
-
-    return if !has_manifest {
-        Err("No manifest found".to_string())
-    } else {
-        Ok(main_dir)
-    };
+    })
 }
 
-/// Unpacks a new installed zip file
+/// Unpacks the SMAPI installer or the files it installs into the game folder
+///
+/// * `archive` - The zip archive to unpack
+/// * `destination` - The destination path to unpack the archive to
+/// * `limits` - The limits the archive has to stay within
+///
+/// # Returns the name of the top folder of the archive
 pub fn unpack_smapi<R: io::Read + io::Seek>(
     mut archive: ZipArchive<R>,
-    destination: &Path
+    destination: &Path,
+    limits: ExtractionLimits,
 ) -> Result<String, String> {
+    let mut extraction = Extraction::new(limits, compressed_size(&mut archive)?);
     let mut main_dir = "".to_string();
 
     for i in 0..archive.len() {
-        let mut file = archive.by_index(i).unwrap();
+        let mut file = archive.by_index(i).map_err(|e| e.to_string())?;
+        let kind = archives::zip_entry_kind(&file);
+        let outpath = match extraction.entry(destination, file.name(), kind)? {
+            Some(path) => path,
+            None => continue,
+        };
 
         if main_dir == "" {
             let new_filepath = file.name().to_string().replace("\\", "/");
@@ -100,35 +69,14 @@ pub fn unpack_smapi<R: io::Read + io::Seek>(
             main_dir = split[0].to_string();
         }
 
-        let outpath = match file.enclosed_name() {
-            Some(path) => destination.join(path),
-            None => continue,
-        };
-
-        if file.name().ends_with('/') {
-            if !outpath.exists() {
-                fs::create_dir_all(&outpath).unwrap();
-            }
-        } else {
-            if let Some(parent) = outpath.parent() {
-                if !parent.exists() {
-                    fs::create_dir_all(parent).unwrap();
-                }
-            }
-            let mut outfile = File::create(&outpath).unwrap();
-            io::copy(&mut file, &mut outfile).unwrap();
-        }
+        extraction.write(&outpath, kind, &mut file)?;
 
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
             if let Some(mode) = file.unix_mode() {
-                match std::fs::set_permissions(&outpath, std::fs::Permissions::from_mode(mode)) {
-                    Ok(_) => {}
-                    Err(e) => {
-                        return Err(e.to_string());
-                    }
-                };
+                let mode = extraction::file_permissions(mode);
+                fs::set_permissions(&outpath, fs::Permissions::from_mode(mode)).map_err(|e| e.to_string())?;
             }
         }
     }
@@ -136,6 +84,17 @@ pub fn unpack_smapi<R: io::Read + io::Seek>(
     Ok(main_dir)
 }
 
+/// Gets the size of all compressed entries of a zip archive
+///
+/// * `archive` - The zip archive
+fn compressed_size<R: io::Read + io::Seek>(archive: &mut ZipArchive<R>) -> Result<u64, String> {
+    let mut size = 0;
+    for i in 0..archive.len() {
+        size += archive.by_index_raw(i).map_err(|e| e.to_string())?.compressed_size();
+    }
+    Ok(size)
+}
+
 /// Writes mod files into a zip archive
 ///
 /// * `zip` - The zip writer to write to
@@ -171,66 +130,80 @@ pub fn zip_mods(zip: &mut ZipWriter<File>, src_dir: &PathBuf) -> zip::result::Zi
     Ok(())
 }
 
+/// The profiles and mod infos of an exported zip file. They are applied through the state store,
+/// so the import can't overwrite changes that happen at the same time.
+#[derive(Default)]
+pub struct ImportedMetadata {
+    pub mods: Vec<ModInfo>,
+    pub profiles: Vec<Profile>,
+}
+
 /// Import an exported zip file
 ///
 /// * `archive` - The zip archive to import
 /// * `destination` - The destination path to import to
 /// * `temp_path` - The temporary path to extract the metafiles like profile.json or mods.json into
 /// * `all` - Whether the import should overwrite all existing profiles
+/// * `limits` - The limits the archive has to stay within
 ///
-/// # Returns a zip result
+/// # Returns the imported profiles and mod infos
 pub fn import_zip<R: io::Read + io::Seek>(
     mut archive: ZipArchive<R>,
     destination: &Path,
     temp_path: &Path,
     all: bool,
-) -> zip::result::ZipResult<()> {
+    limits: ExtractionLimits,
+) -> Result<ImportedMetadata, JunimoError> {
+    let size = compressed_size(&mut archive).map_err(JunimoError::Archive)?;
+    let mut extraction = Extraction::new(limits, size);
+    let mut metadata = ImportedMetadata::default();
+
     // Go through all files in zip file
     for i in 0..archive.len() {
-        let mut file = archive.by_index(i).unwrap();
-        match file.enclosed_name() {
-            Some(path) => {
-                if path.starts_with("mods") {
-                    import_mod_file(destination, &path, &mut file, all)?;
-                } else {
-                    import_metadata_file(destination, temp_path, &path, &mut file, all)?;
-                }
-            }
+        let mut file = archive.by_index(i)?;
+        let kind = archives::zip_entry_kind(&file);
+        // The path is checked relative to the archive, because the files go into different folders
+        let path = match extraction.entry(Path::new(""), file.name(), kind).map_err(JunimoError::Archive)? {
+            Some(path) => path,
             None => continue,
-        }
+        };
 
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            if let Some(mode) = file.unix_mode() {
-                std::fs::set_permissions(&destination, std::fs::Permissions::from_mode(mode))?;
-            }
+        if path.starts_with("mods") {
+            import_mod_file(&mut extraction, destination, &path, kind, &mut file, all)?;
+        } else if kind == EntryKind::File {
+            import_metadata_file(&mut extraction, temp_path, &path, &mut file, &mut metadata)?;
         }
     }
-    Ok(())
+    Ok(metadata)
 }
 
 /// Import a mod file from a zip archive
 ///
+/// * `extraction` - Checks the size of the imported files
 /// * `destination` - The destination path to import to
 /// * `path` - Path of the mod in the zip file
+/// * `kind` - Whether the entry is a file or folder
 /// * `file` - The file to import
 /// * `all` - Whether the import should overwrite all existing profiles
 ///
-/// # Returns a zip result
+/// # Returns a Result with the success state
 fn import_mod_file(
+    extraction: &mut Extraction,
     destination: &Path,
     path: &Path,
+    kind: EntryKind,
     file: &mut zip::read::ZipFile,
     all: bool,
-) -> zip::result::ZipResult<()> {
-    let mod_dest = destination.join(&path);
-    let mut path_iter = path.iter();
-    let dir_name = path_iter.nth(1).unwrap();
+) -> Result<(), JunimoError> {
+    // Only the mod folders inside of the mods folder are imported
+    let dir_name = match path.iter().nth(1) {
+        Some(dir_name) => dir_name,
+        None => return Ok(()),
+    };
     let path_in_mods = paths::mod_path().join(dir_name);
-    let path_in_mods_dot = paths::mod_path().join(format!(".{:?}", dir_name));
+    let path_in_mods_dot = paths::mod_path().join(format!(".{}", dir_name.to_string_lossy()));
 
-    if path_iter.clone().count() == 0 {
+    if path.iter().count() == 2 {
         if (path_in_mods.exists() || path_in_mods_dot.exists()) && !all {
             return Ok(());
         } else if (path_in_mods.exists() || path_in_mods_dot.exists()) && all {
@@ -239,19 +212,9 @@ fn import_mod_file(
         }
     }
 
-    if file.is_dir() {
-        fs::create_dir_all(mod_dest).unwrap();
-    } else {
-        if let Some(parent) = mod_dest.parent() {
-            if !parent.exists() {
-                fs::create_dir_all(parent)?;
-            }
-        }
-        let mut outfile = File::create(&mod_dest)?;
-        io::copy(file, &mut outfile)?;
-    }
-
-    Ok(())
+    extraction
+        .write(&destination.join(path), kind, file)
+        .map_err(JunimoError::Archive)
 }
 
 /// Remove a file or directory if it exists
@@ -269,64 +232,85 @@ fn remove_if_exists(path: PathBuf) {
 
 /// Import a metadata file from a zip archive (profile.json or mods.json)
 ///
-/// * `destination` - The destination path to import to
+/// * `extraction` - Checks the size of the imported files
 /// * `temp_path` - The temporary path to extract the metafiles like profile.json or mods.json into
 /// * `path` - Path of the metadata file in the zip file
 /// * `file` - The file to import
-/// * `all` - Whether the import should overwrite all existing profiles
+/// * `metadata` - Collects the imported profiles and mod infos
 ///
-/// # Returns a zip result
+/// # Returns a Result with the success state
 fn import_metadata_file(
-    destination: &Path,
+    extraction: &mut Extraction,
     temp_path: &Path,
     path: &Path,
     file: &mut zip::read::ZipFile,
-    all: bool,
-) -> zip::result::ZipResult<()> {
+    metadata: &mut ImportedMetadata,
+) -> Result<(), JunimoError> {
     let outpath = temp_path.join(path);
 
     if file.name().contains("json") {
-        let mut outfile = File::create(&outpath)?;
-        io::copy(file, &mut outfile)?;
+        extraction
+            .write(&outpath, EntryKind::File, file)
+            .map_err(JunimoError::Archive)?;
+        let data_raw = fs::read_to_string(&outpath)?;
+        let _ = fs::remove_file(&outpath);
+
+        if file.name().contains("mods.json") {
+            metadata.mods = serde_json::from_str(&data_raw).unwrap_or_default();
+        } else if let Ok((loaded_profiles, _)) = profiles::parse_profiles(&data_raw) {
+            metadata.profiles = loaded_profiles;
+        }
+    }
+
+    Ok(())
+}
 
-        if all {
-            let profile_dest = destination.join("profile.json");
-            let mods_dest = destination.join("mods.json");
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
 
-            if file.name().contains("profile.json") {
-                if profile_dest.exists() {
-                    fs::remove_file(&profile_dest).unwrap();
-                }
+    use tempfile::tempdir;
 
-                fs::rename(&outpath, &profile_dest).unwrap();
-            } else if file.name().contains("mods.json") {
-                if mods_dest.exists() {
-                    fs::remove_file(&mods_dest).unwrap();
-                }
+    use super::*;
 
-                fs::rename(&outpath, &mods_dest).unwrap();
-            }
-        } else {
-            let data_raw = fs::read_to_string(&outpath).unwrap();
-            let data = data_raw.as_str();
-            let loaded_profiles: Vec<Profile> = serde_json::from_str(data).unwrap();
+    /// Writes a zip archive with the given files into memory
+    fn zip_fixture(files: &[(&str, &[u8])]) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        ZipArchive::new(zip.finish().unwrap()).unwrap()
+    }
 
-            if loaded_profiles.len() == 0 {
-                return Ok(());
-            }
+    #[test]
+    fn test_unpack_smapi() {
+        let tmp_dir = tempdir().unwrap();
+        let destination = tmp_dir.path().join("game");
+
+        let archive = zip_fixture(&[("SMAPI 4.0.0 installer/install.dat", b"data")]);
+        let main_dir = unpack_smapi(archive, &destination, ExtractionLimits::default()).unwrap();
+        assert_eq!(main_dir, "SMAPI 4.0.0 installer");
+        assert!(destination.join("SMAPI 4.0.0 installer").join("install.dat").exists());
 
-            let loaded_profile = loaded_profiles[0].clone();
-            let current_profiles = profiles::get_profiles(paths::profile_path());
+        let archive = zip_fixture(&[("StardewModdingAPI.dll", b"smapi"), ("../../evil.dll", b"evil")]);
+        let error = unpack_smapi(archive, &destination, ExtractionLimits::default()).unwrap_err();
+        assert!(error.contains("outside"), "{}", error);
+        assert!(!tmp_dir.path().join("evil.dll").exists());
+    }
 
-            let mut current_profiles = current_profiles
-                .into_iter()
-                .filter(|prof| prof.name != loaded_profile.name)
-                .collect::<Vec<Profile>>();
-            current_profiles.push(loaded_profile);
+    #[test]
+    fn test_import_zip_rejects_zip_slip() {
+        let tmp_dir = tempdir().unwrap();
+        let destination = tmp_dir.path().join("appdata");
+        let temp_path = tmp_dir.path().join("temp");
 
-            profiles::save_profiles(&current_profiles, &paths::profile_path());
+        for name in ["mods/../../evil.dll", "../profile.json", "/tmp/mods.json"] {
+            let archive = zip_fixture(&[(name, b"[]")]);
+            let result = import_zip(archive, &destination, &temp_path, true, ExtractionLimits::default());
+            assert_eq!(result.err().map(|error| error.kind()), Some("Archive"));
         }
+        assert!(!tmp_dir.path().join("evil.dll").exists());
+        assert!(!tmp_dir.path().join("profile.json").exists());
     }
-
-    Ok(())
 }
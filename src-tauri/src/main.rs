@@ -8,64 +8,56 @@ use tauri::tray::{ClickType, TrayIconBuilder};
 use tauri::{command, Manager};
 use tauri_plugin_updater::UpdaterExt;
 
-use crate::app::api::{downloader, github};
+use crate::app::api::{downloader, github, verification};
 use crate::app::app_state::AppState;
+use crate::app::error::JunimoError;
 use crate::app::utility::paths;
-use crate::app::{api, export, game, smapi, junimo_updater};
-use crate::app::{config, import, mods, profiles, user};
+use crate::app::{api, export, game, game_detection, mod_adoption, mod_config_editor, mod_identity, mod_store, smapi, smapi_manager, junimo_updater};
+use crate::app::{config, dependencies, import, load_order, mod_installation, mods, profiles, user};
 
 mod app;
 
 #[command]
-async fn greet(app: tauri::AppHandle) -> String {
+async fn greet(app: tauri::AppHandle) -> Result<String, JunimoError> {
     let version = app.package_info().version.clone().to_string();
-    format!(
+    Ok(format!(
         "<span style='color: #8bc24a'><b>[Junimo]</b> Welcome to Junimo Version {}!</span>",
         version
-    )
+    ))
 }
 
 #[command]
-async fn init(app_handle: tauri::AppHandle) -> bool {
+async fn init(app_handle: tauri::AppHandle) -> Result<bool, JunimoError> {
     mods::compatibility_check(app_handle).await
 }
 
 #[command]
-async fn show_window(window: tauri::Window, label: String) -> String {
+async fn show_window(window: tauri::Window, label: String) -> Result<String, JunimoError> {
     window
         .get_webview_window(label.as_str())
-        .unwrap()
-        .show()
-        .unwrap();
-    "Opened".to_owned()
+        .ok_or_else(|| JunimoError::NotFound(format!("The window {}", label)))?
+        .show()?;
+    Ok("Opened".to_owned())
 }
 
 #[command]
-async fn close_splashscreen(window: tauri::Window, handle: tauri::AppHandle) {
-    if handle.get_webview_window("splashscreen").is_some() {
-        handle
-            .get_webview_window("splashscreen")
-            .expect("no window labeled 'splashscreen' found")
-            .close()
-            .unwrap();
+async fn close_splashscreen(window: tauri::Window, handle: tauri::AppHandle) -> Result<(), JunimoError> {
+    if let Some(splashscreen) = handle.get_webview_window("splashscreen") {
+        splashscreen.close()?;
     }
 
-    handle
+    let main = handle
         .get_webview_window("main")
-        .expect("no window labeled 'main' found")
-        .show()
-        .unwrap();
-
-    handle
-        .get_webview_window("main")
-        .expect("no window labeled 'main' found")
-        .set_focus()
-        .unwrap();
+        .ok_or_else(|| JunimoError::NotFound("The main window".to_string()))?;
+    main.show()?;
+    main.set_focus()?;
+    Ok(())
 }
 
 #[command]
-async fn close(handle: tauri::AppHandle) {
+async fn close(handle: tauri::AppHandle) -> Result<(), JunimoError> {
     handle.exit(0);
+    Ok(())
 }
 
 #[derive(Clone, serde::Serialize)]
@@ -86,7 +78,13 @@ pub fn main() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .setup(|app| {
             let app_handle = app.app_handle();
-            let _ = config::init_config(&app_handle, paths::appdata_path().as_path());
+            if let Err(e) = config::init_config(&app_handle, paths::appdata_path().as_path()) {
+                println!("Failed to create config.json: {}", e);
+            }
+            mod_identity::migrate_mod_identity(&app_handle);
+            mod_store::migrate_store(&app_handle);
+            mod_installation::clean_up_plans();
+            downloader::restore_queue(&app_handle, &app.state::<AppState>());
 
             let toggle = MenuItemBuilder::with_id("close", "Close").build(app)?;
             let menu = MenuBuilder::new(app).items(&[&toggle]).build()?;
@@ -129,7 +127,7 @@ pub fn main() {
         .on_window_event(|window, event| match event {
             tauri::WindowEvent::CloseRequested { api, .. } => {
                 if window.label() == "main" {
-                    let config = config::get_config(paths::config_path());
+                    let config = config::load_config(paths::config_path());
                     if config.keep_open.is_none() || config.keep_open.unwrap() {
                         window.hide().unwrap();
                         api.prevent_close();
@@ -144,15 +142,29 @@ pub fn main() {
             show_window,
             close_splashscreen,
             close,
-            paths::config_path,
-            paths::profile_path,
+            paths::commands::config_path,
+            paths::commands::profile_path,
             game::start_game,
             game::stop_game,
             mods::add_mod,
+            mods::apply_installation,
+            mods::cancel_installation,
             mods::get_installed_mods,
             mods::uninstall_mod,
+            mods::get_mod_versions,
+            mods::remove_unused_versions,
             mods::uninstall_mods,
             mods::open_search_browser,
+            mods::check_updates,
+            mods::update_all_mods,
+            dependencies::get_dependency_graph,
+            dependencies::get_missing_dependencies,
+            dependencies::install_missing_dependencies,
+            load_order::get_load_order,
+            mod_adoption::adopt_mods_folder,
+            mod_config_editor::list_mod_configs,
+            mod_config_editor::get_mod_config,
+            mod_config_editor::save_mod_config,
             config::open_config,
             config::get_config,
             config::save_config_button,
@@ -169,17 +181,30 @@ pub fn main() {
             profiles::remove_profile,
             profiles::modify_profile,
             profiles::change_profile_mods,
+            profiles::pin_mod_version,
             profiles::duplicate_profile,
+            profiles::set_smapi_version,
             export::open_export,
             export::select_export_dir,
             export::export_profile,
             import::open_import,
             import::select_import_dir,
             import::import_profile,
+            downloader::get_downloads,
             downloader::stop_download,
+            downloader::pause_download,
+            downloader::resume_download,
+            downloader::clear_downloads,
+            verification::verify_archive,
             github::check_smapi_version,
+            github::get_smapi_releases,
             smapi::open_smapi,
             smapi::download_smapi,
+            smapi_manager::get_smapi_status,
+            smapi_manager::repair_smapi,
+            smapi_manager::uninstall_smapi,
+            game_detection::detect_games,
+            game_detection::validate_game_path,
             junimo_updater::open_updater
         ])
         .run(tauri::generate_context!())
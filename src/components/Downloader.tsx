@@ -15,8 +15,16 @@ import {
 import {TabsContent} from "@components/ui/tabs.tsx";
 
 export default function Downloader({downloadList}: {downloadList: Download[]}) {
-    async function stopDownload() {
-        await invoke('stop_download');
+    async function stopDownload(id: number) {
+        await invoke('stop_download', {id});
+    }
+
+    async function pauseDownload(id: number) {
+        await invoke('pause_download', {id});
+    }
+
+    async function resumeDownload(id: number) {
+        await invoke('resume_download', {id});
     }
 
     function bytesToString(bytes: number) {
@@ -36,21 +44,29 @@ export default function Downloader({downloadList}: {downloadList: Download[]}) {
                 <h2 className="text-lg">Downloads</h2>
             </div>
             <div className="flex flex-col gap-2 w-full aboslute mt-2 overflow-auto relative p-1 pb-4 pr-4">
-                {downloadList.map((mod, index) => (
-                        <ContextMenu>
+                {downloadList.map((mod) => (
+                        <ContextMenu key={mod.id}>
                             <ContextMenuTrigger>
-                                <div key={index}
+                                <div
                                      className="w-full transform duration-150 cursor-pointer bg-muted hover:bg-muted-dark rounded-lg pb-0 overflow-hidden">
                                     <div className="w-full flex justify-between p-2 pb-1">
                                         <p>{mod.name}</p>
-                                        <p className="text-zinc-500 h-2">{bytesToString(mod.current)} / {bytesToString(mod.size)}</p>
+                                        <p className="text-zinc-500 h-2">
+                                            {mod.status === 'AwaitingSelection' ? 'Waiting for selection' : mod.status !== 'Downloading' ? mod.status : mod.size !== null ? `${bytesToString(mod.current)} / ${bytesToString(mod.size)}` : bytesToString(mod.current)}
+                                        </p>
                                     </div>
-                                    <Progress value={mod.current / mod.size * 100}/>
+                                    <Progress value={mod.size ? mod.current / mod.size * 100 : 0}/>
                                 </div>
                             </ContextMenuTrigger>
                             <ContextMenuContent>
-                                {!mod.aborted && (
-                                    <ContextMenuItem onClick={stopDownload}>Cancel</ContextMenuItem>
+                                {(mod.status === 'Queued' || mod.status === 'Downloading') && (
+                                    <ContextMenuItem onClick={() => pauseDownload(mod.id)}>Pause</ContextMenuItem>
+                                )}
+                                {(mod.status === 'Paused' || mod.status === 'Failed') && (
+                                    <ContextMenuItem onClick={() => resumeDownload(mod.id)}>Resume</ContextMenuItem>
+                                )}
+                                {mod.status !== 'Finished' && (
+                                    <ContextMenuItem onClick={() => stopDownload(mod.id)}>Cancel</ContextMenuItem>
                                 )}
                             </ContextMenuContent>
                         </ContextMenu>
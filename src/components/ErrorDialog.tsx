@@ -0,0 +1,68 @@
+import {useEffect, useState} from "react";
+import {listen} from "@tauri-apps/api/event";
+import {
+    Dialog,
+    DialogContent,
+    DialogDescription,
+    DialogFooter,
+    DialogHeader,
+    DialogTitle
+} from "@components/ui/dialog.tsx";
+import {JunimoError} from "@models/junimoError.ts";
+import {useTranslation} from "react-i18next";
+
+
+function isJunimoError(value: unknown): value is JunimoError {
+    return typeof value === "object" && value !== null && "kind" in value && "message" in value;
+}
+
+export default function ErrorDialog() {
+    const [error, setError] = useState<JunimoError | null>(null);
+    const { t } = useTranslation('home');
+
+    useEffect(() => {
+        // Errors of background tasks like downloads and exports
+        let unsubscribeEvent = listen('notification', (event) => {
+            setError(event.payload as JunimoError);
+        });
+
+        // Errors of commands that weren't handled by the component that invoked them
+        const onRejection = (event: PromiseRejectionEvent) => {
+            if (isJunimoError(event.reason) && event.reason.kind !== 'Cancelled') {
+                setError(event.reason);
+            }
+        };
+        window.addEventListener('unhandledrejection', onRejection);
+
+        return () => {
+            unsubscribeEvent.then((unsub) => unsub());
+            window.removeEventListener('unhandledrejection', onRejection);
+        };
+    }, []);
+
+    return (
+        <Dialog open={error !== null} onOpenChange={(open) => !open && setError(null)}>
+            <DialogContent>
+                <DialogHeader>
+                    <DialogTitle className="mb-4">{t("errorTitle")}</DialogTitle>
+                    <DialogDescription>
+                        {error?.message}
+                    </DialogDescription>
+                </DialogHeader>
+                {error?.errors && (
+                    <ul className="flex flex-col gap-1 text-sm text-muted-foreground">
+                        {error.errors.map(x => (
+                            <li key={x.path}>{x.path ? `${x.path}: ${x.message}` : x.message}</li>
+                        ))}
+                    </ul>
+                )}
+                <DialogFooter className="sm:justify-end">
+                    <button onClick={() => setError(null)}
+                            className="transition duration-300 text-foreground bg-primary hover:brightness-75 p-2 px-4 rounded">
+                        {t("errorClose")}
+                    </button>
+                </DialogFooter>
+            </DialogContent>
+        </Dialog>
+    )
+}
@@ -0,0 +1,86 @@
+import {useEffect, useState} from "react";
+import {invoke} from "@tauri-apps/api/core";
+import {listen} from "@tauri-apps/api/event";
+import {
+    Dialog,
+    DialogContent,
+    DialogDescription,
+    DialogFooter,
+    DialogHeader,
+    DialogTitle
+} from "@components/ui/dialog.tsx";
+import {Checkbox} from "@components/ui/checkbox.tsx";
+import {InstallPlan} from "@models/installPlan.ts";
+import {useTranslation} from "react-i18next";
+
+
+export default function InstallPlanDialog() {
+    const [plan, setPlan] = useState<InstallPlan | null>(null);
+    const [selected, setSelected] = useState<string[]>([]);
+    const { t, i18n } = useTranslation('home');
+
+    useEffect(() => {
+        let unsubscribeEvent = listen('install-plan', (event) => {
+            const data = event.payload as InstallPlan;
+            setPlan(data);
+            setSelected(data.mods.map(x => x.path));
+        });
+
+        return () => {
+            unsubscribeEvent.then((unsub) => unsub());
+        };
+    }, []);
+
+    function toggle(path: string, checked: boolean) {
+        setSelected(prev => checked ? [...prev, path] : prev.filter(x => x !== path));
+    }
+
+    async function install() {
+        if (plan === null) return;
+        setPlan(null);
+        await invoke('apply_installation', {id: plan.id, selected: selected});
+    }
+
+    async function cancel() {
+        if (plan === null) return;
+        setPlan(null);
+        await invoke('cancel_installation', {id: plan.id});
+    }
+
+    return (
+        <Dialog open={plan !== null} onOpenChange={(open) => !open && cancel()}>
+            <DialogContent>
+                <DialogHeader>
+                    <DialogTitle className="mb-4">{t("installPlanTitle")}</DialogTitle>
+                    <DialogDescription>
+                        {t("installPlanDesc", {archive: plan?.archive})}
+                    </DialogDescription>
+                </DialogHeader>
+                <div className="flex flex-col gap-2 max-h-[50vh] overflow-y-auto">
+                    {plan?.mods.map(mod => (
+                        <label key={mod.path} className="flex items-center gap-3 p-2 rounded hover:bg-muted">
+                            <Checkbox checked={selected.includes(mod.path)}
+                                      onCheckedChange={(checked) => toggle(mod.path, checked === true)} />
+                            <div className="flex flex-col">
+                                <span>{mod.name} <span className="text-muted-foreground">{mod.version}</span></span>
+                                <span className="text-xs text-muted-foreground">
+                                    {mod.unique_id} · {mod.path || "/"}
+                                </span>
+                            </div>
+                        </label>
+                    ))}
+                </div>
+                <DialogFooter className="sm:justify-end">
+                    <button onClick={cancel}
+                            className="transition duration-300 text-foreground hover:bg-muted p-2 px-4 rounded">
+                        {t("installPlanCancel")}
+                    </button>
+                    <button onClick={install} disabled={selected.length === 0}
+                            className="transition duration-300 text-foreground bg-primary hover:brightness-75 p-2 px-4 rounded disabled:opacity-50">
+                        {t("installSelected")}
+                    </button>
+                </DialogFooter>
+            </DialogContent>
+        </Dialog>
+    )
+}
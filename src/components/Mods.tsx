@@ -1,5 +1,5 @@
 import {invoke} from "@tauri-apps/api/core";
-import {Profile} from "@models/profile.ts";
+import {ResolvedProfile} from "@models/profile.ts";
 import {Dispatch, SetStateAction, useEffect, useState} from "react";
 import Wrapper from "@components/ui/wrapper.tsx";
 import {clsx} from "clsx";
@@ -9,7 +9,7 @@ import UninstallMod from "@components/UninstallMod.tsx";
 import UninstallAll from "@components/UninstallAll.tsx";
 import { DropdownMenu, DropdownMenuContent, DropdownMenuItem, DropdownMenuLabel, DropdownMenuSeparator, DropdownMenuTrigger, } from "@components/ui/dropdown-menu"
 import {useModsState} from "@components/ModsProvider.tsx";
-import {ModInfos} from "@models/mods.ts";
+import {ModInfos, modKey} from "@models/mods.ts";
 import {useTranslation} from "react-i18next";
 
 export default function Mods() {
@@ -31,7 +31,7 @@ export default function Mods() {
 
     async function loadProfile() {
         const profilePath = await invoke<string>('profile_path');
-        const loadedMods = await invoke<Profile>('get_current_profile', {path: profilePath});
+        const loadedMods = await invoke<ResolvedProfile>('get_current_profile', {path: profilePath});
 
         console.log(loadedMods);
         profile[1](loadedMods);
@@ -65,10 +65,10 @@ export default function Mods() {
     }
 
     function toggleGroup(groupName: string) {
-        if (groupedMods[groupName].some(mod => selectedRemove[0].includes(mod.name))) {
-            selectedRemove[1](selectedRemove[0].filter(i => !groupedMods[groupName].some(mod => mod.name === i)));
+        if (groupedMods[groupName].some(mod => selectedRemove[0].includes(modKey(mod)))) {
+            selectedRemove[1](selectedRemove[0].filter(i => !groupedMods[groupName].some(mod => modKey(mod) === i)));
         } else {
-            selectedRemove[1]([...selectedRemove[0], ...groupedMods[groupName].map(mod => mod.name)]);
+            selectedRemove[1]([...selectedRemove[0], ...groupedMods[groupName].map(mod => modKey(mod))]);
         }
     }
 
@@ -99,13 +99,13 @@ export default function Mods() {
                                     )}>
                                         <ContextMenu>
                                             <ContextMenuTrigger>
-                                                <div onClick={i => setIndex(mod.name)}>
-                                                    <Wrapper mod={mod} selected={selectedRemove[0].includes(mod.name)}/>
+                                                <div onClick={i => setIndex(modKey(mod))}>
+                                                    <Wrapper mod={mod} selected={selectedRemove[0].includes(modKey(mod))}/>
                                                 </div>
                                             </ContextMenuTrigger>
                                             <ContextMenuContent>
                                                 <ContextMenuItem onClick={unselectAll}>{t("unselect")}</ContextMenuItem>
-                                                <UninstallMod name={mod.name} />
+                                                <UninstallMod id={modKey(mod)} />
                                                 <UninstallAll mods={profile[0]?.mods} names={selectedRemove[0]} />
                                             </ContextMenuContent>
                                         </ContextMenu>
@@ -131,12 +131,12 @@ export default function Mods() {
                                                         <ContextMenuTrigger>
                                                             <div>
                                                                 <Wrapper mod={mod}
-                                                                         selected={selectedRemove[0].includes(mod.name)}/>
+                                                                         selected={selectedRemove[0].includes(modKey(mod))}/>
                                                             </div>
                                                         </ContextMenuTrigger>
                                                         <ContextMenuContent>
                                                             <ContextMenuItem onClick={unselectAll}>{t('unselect')}</ContextMenuItem>
-                                                            <UninstallMod name={mod.name}/>
+                                                            <UninstallMod id={modKey(mod)}/>
                                                             <UninstallAll mods={profile[0]?.mods}
                                                                           names={selectedRemove[0]}/>
                                                         </ContextMenuContent>
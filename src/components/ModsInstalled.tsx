@@ -1,5 +1,5 @@
 import {invoke} from "@tauri-apps/api/core";
-import {ModInfos} from "@models/mods.ts";
+import {ModInfos, modKey} from "@models/mods.ts";
 import {useEffect, useState} from "react";
 import {clsx} from "clsx";
 import {ContextMenu, ContextMenuContent, ContextMenuItem, ContextMenuTrigger} from "@components/ui/context-menu.tsx";
@@ -55,10 +55,10 @@ export default function ModsInstalled() {
     }
 
     function toggleGroup(groupName: string) {
-        if (groupedMods[groupName].some(mod => selectedAdd[0].includes(mod.name))) {
-            selectedAdd[1](selectedAdd[0].filter(i => !groupedMods[groupName].some(mod => mod.name === i)));
+        if (groupedMods[groupName].some(mod => selectedAdd[0].includes(modKey(mod)))) {
+            selectedAdd[1](selectedAdd[0].filter(i => !groupedMods[groupName].some(mod => modKey(mod) === i)));
         } else {
-            selectedAdd[1]([...selectedAdd[0], ...groupedMods[groupName].map(mod => mod.name)]);
+            selectedAdd[1]([...selectedAdd[0], ...groupedMods[groupName].map(mod => modKey(mod))]);
         }
     }
 
@@ -93,13 +93,13 @@ export default function ModsInstalled() {
                                     )}>
                                         <ContextMenu>
                                             <ContextMenuTrigger>
-                                                <div onClick={i => setIndex(mod.name)}>
-                                                    <Wrapper mod={mod} selected={selectedAdd[0].includes(mod.name)}/>
+                                                <div onClick={i => setIndex(modKey(mod))}>
+                                                    <Wrapper mod={mod} selected={selectedAdd[0].includes(modKey(mod))}/>
                                                 </div>
                                             </ContextMenuTrigger>
                                             <ContextMenuContent>
                                                 <ContextMenuItem onClick={unselectAll}>{t("unselect")}</ContextMenuItem>
-                                                <UninstallMod name={mod.name} />
+                                                <UninstallMod id={modKey(mod)} />
                                                 <UninstallAll mods={installedMods[0]} names={selectedAdd[0]} />
                                             </ContextMenuContent>
                                         </ContextMenu>
@@ -122,12 +122,12 @@ export default function ModsInstalled() {
                                                         <ContextMenuTrigger>
                                                             <div>
                                                                 <Wrapper mod={mod}
-                                                                         selected={selectedAdd[0].includes(mod.name)}/>
+                                                                         selected={selectedAdd[0].includes(modKey(mod))}/>
                                                             </div>
                                                         </ContextMenuTrigger>
                                                         <ContextMenuContent>
                                                             <ContextMenuItem onClick={unselectAll}>{t("unselect")}</ContextMenuItem>
-                                                            <UninstallMod name={mod.name}/>
+                                                            <UninstallMod id={modKey(mod)}/>
                                                             <UninstallAll mods={installedMods[0]}
                                                                           names={selectedAdd[0]}/>
                                                         </ContextMenuContent>
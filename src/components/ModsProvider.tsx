@@ -1,6 +1,6 @@
 import React, { createContext, useState, useContext } from 'react';
-import {Profile} from "@models/profile.ts";
-import {ModInfos} from "@models/mods.ts";
+import {ResolvedProfile} from "@models/profile.ts";
+import {ModInfos, modKey} from "@models/mods.ts";
 import {invoke} from "@tauri-apps/api/core";
 
 
@@ -8,7 +8,7 @@ import {invoke} from "@tauri-apps/api/core";
 interface ModsContextType {
     appKey: [number, React.Dispatch<React.SetStateAction<number>>];
     reloadKey: [number, React.Dispatch<React.SetStateAction<number>>];
-    profile: [Profile | undefined, React.Dispatch<React.SetStateAction<Profile | undefined>>];
+    profile: [ResolvedProfile | undefined, React.Dispatch<React.SetStateAction<ResolvedProfile | undefined>>];
     activatedMods: [Map<string, ModInfos[]>, React.Dispatch<React.SetStateAction<Map<string, ModInfos[]>>>];
     installedMods: [ModInfos[], React.Dispatch<React.SetStateAction<ModInfos[]>>];
     selectedAdd: [string[], React.Dispatch<React.SetStateAction<string[]>>];
@@ -30,7 +30,7 @@ export const useModsState = () => {
 const ModsProvider = ({ children }: { children: React.ReactNode }) => {
     const [appKey, setAppKey] = useState(0);
     const [key, setKey] = useState(0);
-    const [profile, setProfile] = useState<Profile>();
+    const [profile, setProfile] = useState<ResolvedProfile>();
     const [activatedMods, setActivatedMods] = useState<Map<string, ModInfos[]>>(new Map());
     const [modList, setModList] = useState<ModInfos[]>([]);
     const [selectedAdd, setSelectedAdd] = useState<string[]>([]);
@@ -39,7 +39,7 @@ const ModsProvider = ({ children }: { children: React.ReactNode }) => {
     async function add() {
         console.log("Add");
         if (profile !== undefined) {
-            const mods = profile.mods.concat(modList.filter((x, i) => selectedAdd.includes(x.name)));
+            const mods = profile.mods.concat(modList.filter((x, i) => selectedAdd.includes(modKey(x))));
             const path = await invoke('profile_path');
             await invoke('change_profile_mods', {name: profile.name, mods: mods, path: path});
             setSelectedAdd([]);
@@ -49,7 +49,7 @@ const ModsProvider = ({ children }: { children: React.ReactNode }) => {
 
     async function remove() {
         if (profile !== undefined) {
-            const mods = profile.mods.filter((x, i) => !selectedRemove.includes(x.name));
+            const mods = profile.mods.filter((x, i) => !selectedRemove.includes(modKey(x)));
             const path = await invoke('profile_path');
             await invoke('change_profile_mods', {name: profile.name, mods: mods, path: path});
             setSelectedRemove([]);
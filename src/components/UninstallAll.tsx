@@ -8,7 +8,7 @@ import {
     DialogTitle,
     DialogTrigger
 } from "@components/ui/dialog.tsx";
-import {ModInfos} from "@models/mods.ts";
+import {ModInfos, modKey} from "@models/mods.ts";
 import {useModsState} from "@components/ModsProvider.tsx";
 import {useTranslation} from "react-i18next";
 
@@ -21,8 +21,8 @@ export default function UninstallAll({mods, names}: {mods: ModInfos[] | undefine
         console.log(mods)
         let newMods: string[] = [];
         if (mods) {
-            //Remove names from mods through mods.name
-            newMods = mods.filter(mod => names.includes(mod.name)).map(mod => mod.name);
+            //Remove selected mods through their unique id
+            newMods = mods.filter(mod => names.includes(modKey(mod))).map(mod => modKey(mod));
         }
         await invoke("uninstall_mods", {mods: newMods});
         reloadKey[1](prevKey => prevKey + 1);
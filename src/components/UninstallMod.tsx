@@ -15,12 +15,12 @@ import {useModsState} from "@components/ModsProvider.tsx";
 import {useTranslation} from "react-i18next";
 
 
-export default function UninstallMod({name}: {name: string}) {
+export default function UninstallMod({id}: {id: string}) {
     const { reloadKey } = useModsState();
     const { t, i18n } = useTranslation('home');
 
     async function uninstallMods() {
-        await invoke<ModInfos[]>('uninstall_mod', {name: name});
+        await invoke<ModInfos[]>('uninstall_mod', {id: id});
         reloadKey[1](prevKey => prevKey + 1);
     }
 
@@ -65,7 +65,10 @@ export default function CheckConfig() {
                 activate_broken: activateBroken,
                 block_on_broken: blockOnBroken,
                 lang: savedConfig.lang,
-                keep_open: savedConfig.keep_open
+                keep_open: savedConfig.keep_open,
+                max_downloads: savedConfig.max_downloads,
+                activation_strategy: savedConfig.activation_strategy,
+                extraction_limits: savedConfig.extraction_limits
             }
 
             const path = await invoke<ConfigModel>('save_config_button', {config: config, path: configPath});
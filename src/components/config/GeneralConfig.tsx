@@ -1,8 +1,10 @@
 import React, {useEffect, useState} from "react";
 import { Input } from "@components/ui/input"
 import { Label } from "@components/ui/label"
-import { Folder } from 'lucide-react';
+import { Folder, Search } from 'lucide-react';
 import { Config as ConfigModel } from "@models/config";
+import { GameCandidate } from "@models/gameCandidate.ts";
+import { JunimoError } from "@models/junimoError.ts";
 import {invoke} from "@tauri-apps/api/core";
 import { open } from '@tauri-apps/plugin-dialog';
 import {Select, SelectContent, SelectItem, SelectTrigger, SelectValue} from "@components/ui/select.tsx";
@@ -13,8 +15,11 @@ import {useTranslation} from "react-i18next";
 export default function GeneralConfig() {
     const [savedConfig, setSavedConfig] = useState<ConfigModel | undefined>();
     const [gamePath, setGamePath] = useState("");
+    const [candidates, setCandidates] = useState<GameCandidate[]>([]);
+    const [pathError, setPathError] = useState<string | null>(null);
     const [lang, setLang] = useState("en");
     const [keepOpen, setKeepOpen] = useState(true);
+    const [linkMods, setLinkMods] = useState(true);
 
     const { t, i18n } = useTranslation("config");
 
@@ -27,6 +32,7 @@ export default function GeneralConfig() {
                 setLang(config.lang);
             if (config.keep_open !== null)
                 setKeepOpen(config.keep_open);
+            setLinkMods(config.activation_strategy !== "Rename");
             setSavedConfig(config);
         } catch (error) {
             console.error('Failed to fetch configuration path:', error);
@@ -43,8 +49,25 @@ export default function GeneralConfig() {
         }
     }
 
+    async function detectGames() {
+        const games = await invoke<GameCandidate[]>('detect_games');
+        setCandidates(games);
+    }
+
+    function candidateLabel(candidate: GameCandidate) {
+        const version = candidate.version !== null ? ` ${candidate.version}` : "";
+        return `${candidate.path} (${t("source" + candidate.source)}${version})`;
+    }
+
     async function save() {
         const configPath = await invoke<string>('config_path');
+        try {
+            await invoke<GameCandidate>('validate_game_path', {path: gamePath});
+            setPathError(null);
+        } catch (error) {
+            setPathError((error as JunimoError).message);
+            return;
+        }
         if (savedConfig !== undefined) {
             const config : ConfigModel = {
                 init_app: true,
@@ -55,7 +78,10 @@ export default function GeneralConfig() {
                 activate_broken: savedConfig.activate_broken,
                 block_on_broken: savedConfig.block_on_broken,
                 lang: lang,
-                keep_open: keepOpen
+                keep_open: keepOpen,
+                max_downloads: savedConfig.max_downloads,
+                activation_strategy: linkMods ? "Link" : "Rename",
+                extraction_limits: savedConfig.extraction_limits
             }
 
             const path = await invoke<ConfigModel>('save_config_button', {config: config, path: configPath});
@@ -66,6 +92,7 @@ export default function GeneralConfig() {
 
     useEffect(() => {
         loadConfig();
+        detectGames();
     }, []);
 
     return (
@@ -79,7 +106,7 @@ export default function GeneralConfig() {
             <div className="w-full flex flex-col gap-4">
                 <div className="flex flex-col">
                     <Label htmlFor="path" className="ml-1 mb-2 text-xl">{t("gamePath")}</Label>
-                    <p className="text-sm text-muted-foreground"></p>
+                    <p className="text-sm text-muted-foreground mb-2">{t("gamePathDesc")}</p>
                     <div className="flex gap-2">
                         <Input id="path" placeholder={t("gamePathPlaceholder")}
                                value={gamePath} onChange={x => setGamePath(x.target.value)}/>
@@ -87,7 +114,25 @@ export default function GeneralConfig() {
                         bg-muted hover:bg-muted-dark">
                             <Folder size={20}/>
                         </button>
+                        <button onClick={detectGames} title={t("detectGames")} className="w-10 h-10 flex items-center justify-center transition duration-150 border rounded-lg
+                        bg-muted hover:bg-muted-dark">
+                            <Search size={20}/>
+                        </button>
                     </div>
+                    {pathError !== null && <p className="text-sm text-destructive mt-2">{pathError}</p>}
+                    {candidates.length > 0 && (
+                        <Select value={candidates.some(x => x.path === gamePath) ? gamePath : ""}
+                                onValueChange={e => setGamePath(e)}>
+                            <SelectTrigger className="mt-2">
+                                <SelectValue placeholder={t("detectedGames")}/>
+                            </SelectTrigger>
+                            <SelectContent>
+                                {candidates.map(x => (
+                                    <SelectItem key={x.path} value={x.path}>{candidateLabel(x)}</SelectItem>
+                                ))}
+                            </SelectContent>
+                        </Select>
+                    )}
                 </div>
                 <div className="w-full h-[2px] border-lg bg-muted"/>
 
@@ -114,6 +159,15 @@ export default function GeneralConfig() {
                         <Checkbox checked={keepOpen} onCheckedChange={e => setKeepOpen(!keepOpen)}/>
                     </div>
                 </div>
+                <div className="w-full h-[2px] border-lg bg-muted"/>
+
+                <div className="flex flex-col">
+                    <Label htmlFor="path" className="mb-1 text-xl">{t("linkMods")}</Label>
+                    <p className="text-sm text-muted-foreground mb-2">{t("linkModsDesc")}</p>
+                    <div className="flex gap-2">
+                        <Checkbox checked={linkMods} onCheckedChange={e => setLinkMods(!linkMods)}/>
+                    </div>
+                </div>
             </div>
         </>
     )
@@ -52,7 +52,10 @@ export default function NexusConfig() {
                 activate_broken: savedConfig.activate_broken,
                 block_on_broken: savedConfig.block_on_broken,
                 lang: savedConfig.lang,
-                keep_open: savedConfig.keep_open
+                keep_open: savedConfig.keep_open,
+                max_downloads: savedConfig.max_downloads,
+                activation_strategy: savedConfig.activation_strategy,
+                extraction_limits: savedConfig.extraction_limits
             }
 
             await invoke('set_api_key', {key: apiKey});
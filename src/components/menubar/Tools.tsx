@@ -19,12 +19,17 @@ export default function Tools() {
         await invoke("open_import")
     }
 
+    async function removeUnusedVersions() {
+        await invoke("remove_unused_versions")
+    }
+
     return (
         <MenubarMenu>
             <MenubarTrigger>{t("tools")}</MenubarTrigger>
             <MenubarContent>
                 <MenubarItem onClick={openImport}>{t("import")}</MenubarItem>
                 <MenubarItem onClick={openExport}>{t("export")}</MenubarItem>
+                <MenubarItem onClick={removeUnusedVersions}>{t("removeUnusedVersions")}</MenubarItem>
                 <MenubarSeparator />
                 <MenubarItem onClick={openConfig}>
                     <Settings size={16} className="mr-1" /> {t("settings")}
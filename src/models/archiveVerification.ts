@@ -0,0 +1,13 @@
+export interface ArchiveMatch {
+    mod_id: number,
+    mod_name: string | null,
+    file_id: number,
+    file_name: string,
+    version: string | null
+}
+
+export interface ArchiveVerification {
+    md5: string,
+    size: number,
+    matches: ArchiveMatch[]
+}
@@ -8,4 +8,14 @@ export interface Config {
     block_on_broken: boolean | null;
     lang: string | null;
     keep_open: boolean | null;
+    max_downloads: number | null;
+    activation_strategy: "Link" | "Rename" | null;
+    extraction_limits: ExtractionLimits | null;
+}
+
+export interface ExtractionLimits {
+    max_total_size: number;
+    max_entries: number;
+    max_ratio: number;
+    max_buffered_size: number;
 }
\ No newline at end of file
@@ -1,7 +1,11 @@
+export type DownloadStatus = 'Queued' | 'Downloading' | 'Paused' | 'AwaitingSelection' | 'Cancelled' | 'Failed' | 'Finished';
+
 export interface Download {
+    id: number;
     name: string;
-    size: number;
+    size: number | null;
     current: number;
-    aborted: boolean;
-    finished: boolean;
-}
\ No newline at end of file
+    status: DownloadStatus;
+    error: string | null;
+    plan_id: number | null;
+}
@@ -0,0 +1,6 @@
+export interface GameCandidate {
+    path: string,
+    source: 'Steam' | 'Gog' | 'Xbox' | 'Manual',
+    version: string | null,
+    valid: boolean,
+}
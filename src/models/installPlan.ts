@@ -0,0 +1,21 @@
+export interface PlannedDependency {
+    UniqueID: string,
+    MinimumVersion: string | null,
+    IsRequired: boolean | null
+}
+
+export interface PlannedMod {
+    path: string,
+    name: string,
+    unique_id: string,
+    version: string,
+    dependencies: PlannedDependency[],
+    target_folder: string,
+    group: string | null
+}
+
+export interface InstallPlan {
+    id: number,
+    archive: string,
+    mods: PlannedMod[]
+}
@@ -0,0 +1,10 @@
+export interface ConfigError {
+    path: string,
+    message: string
+}
+
+export interface JunimoError {
+    kind: 'Io' | 'Json' | 'Manifest' | 'Network' | 'Archive' | 'InvalidConfig' | 'GameNotFound' | 'NotFound' | 'Unsupported' | 'Cancelled' | 'Other',
+    message: string,
+    errors?: ConfigError[]
+}
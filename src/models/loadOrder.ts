@@ -0,0 +1,12 @@
+export type DiagnosticKind = 'SelfReference' | 'Cycle' | 'DuplicateId' | 'SkippedDependency';
+
+export interface LoadOrderDiagnostic {
+    kind: DiagnosticKind,
+    mods: string[],
+    message: string
+}
+
+export interface LoadOrder {
+    order: string[],
+    diagnostics: LoadOrderDiagnostic[]
+}
@@ -0,0 +1,11 @@
+export interface ModSource {
+    unique_id: string,
+    name: string | null,
+    nexus_id: number | null,
+    github_repo: string | null,
+    custom_source_url: string | null,
+    version: string | null,
+    url: string | null,
+    installed: boolean,
+    minimum_version: string | null
+}
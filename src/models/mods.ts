@@ -26,4 +26,13 @@ export interface ModInfos {
     invisible: boolean,
     more_info: string | undefined,
     group: string | undefined,
+    update_keys: string[] | undefined,
+    update_version: string | undefined,
+    update_url: string | undefined,
+    unique_id: string | null,
+}
+
+// Mods are identified by their unique id, only mods without one fall back to their name
+export function modKey(mod: ModInfos): string {
+    return mod.unique_id ?? mod.name;
 }
\ No newline at end of file
@@ -1,7 +1,23 @@
 import { ModInfos } from './mods';
 
+export interface ModReference {
+    unique_id: string,
+    version: string | null,
+    enabled: boolean
+}
+
 export interface Profile {
+    name: string,
+    mods: ModReference[],
+    currently: boolean,
+    configs: Record<string, string> | null,
+    smapi_version: string | null
+}
+
+export interface ResolvedProfile {
     name: string,
     mods: ModInfos[],
-    currently: boolean
-}
\ No newline at end of file
+    currently: boolean,
+    configs: Record<string, string> | null,
+    smapi_version: string | null
+}
@@ -1,7 +1,7 @@
 export default interface SmapiProcess {
     started: boolean,
     download_progress: number,
-    size: number,
+    size: number | null,
     download_finished: boolean,
     installation_started: boolean,
     installation_finished: boolean,
@@ -0,0 +1,7 @@
+export type SmapiChannel = "Stable" | "Beta";
+
+export default interface SmapiRelease {
+    version: string,
+    prerelease: boolean,
+    download_url: string,
+}
@@ -0,0 +1,6 @@
+export default interface SmapiStatus {
+    version: string | null,
+    missing_files: string[],
+    backup: boolean,
+    repairable: boolean,
+}
@@ -21,6 +21,8 @@ import File from "@components/menubar/File.tsx";
 import WindowActions from "@components/menubar/WindowActions.tsx";
 import {useModsState} from "@components/ModsProvider.tsx";
 import MiddleButtons from "@components/MiddleButtons.tsx";
+import InstallPlanDialog from "@components/InstallPlanDialog.tsx";
+import ErrorDialog from "@components/ErrorDialog.tsx";
 import {useTranslation} from "react-i18next";
 import { check } from '@tauri-apps/plugin-updater';
 import { relaunch } from '@tauri-apps/plugin-process';
@@ -40,6 +42,7 @@ function Home() {
 
   async function initApp() {
       await invoke("init");
+      setDownloadList(await invoke<Download[]>("get_downloads"));
       setTimeout(async () => {
           reloadKey[1](prevKey => prevKey + 1);
           await invoke("close_splashscreen");
@@ -59,23 +62,19 @@ function Home() {
         const handleDownload = (event: any) => {
             const data = event.payload as Download;
             setDownloadList(prevLines => {
-                const newDownloads = [...prevLines];
-
-                if (prevLines.find(x => x.name === data.name) === undefined) {
-                    newDownloads.push(data);
-                    return newDownloads;
+                if (data.status === 'Finished') {
+                    reloadKey[1](prevKey => prevKey + 1);
                 }
 
-                if (data.aborted) {
-                    return prevLines.filter(x => x.name !== data.name);
+                if (data.status === 'Cancelled') {
+                    return prevLines.filter(x => x.id !== data.id);
                 }
 
-                if (data.finished) {
-                    reloadKey[1](prevKey => prevKey + 1);
-                    return prevLines.map(x => x.name === data.name ? data : x);
+                if (prevLines.find(x => x.id === data.id) === undefined) {
+                    return [...prevLines, data];
                 }
 
-                return prevLines.map(x => x.name === data.name ? data : x);
+                return prevLines.map(x => x.id === data.id ? data : x);
             });
         };
 
@@ -88,6 +87,7 @@ function Home() {
         let unsubscribeEvent = listen('close', handleNewData);
         let unsubscribeDownloadEvent = listen('download', handleDownload);
         let unsubscribeReloadEvent = listen('reload', handleReload);
+        let unsubscribeModsEvent = listen('mods-update', handleReload);
         let unsubscribeLanguageEvent = listen('language_changed', async (event) => {
             const lang = event.payload as string;
             await i18n.changeLanguage(lang);
@@ -97,6 +97,7 @@ function Home() {
             unsubscribeEvent.then((unsub) => unsub());
             unsubscribeDownloadEvent.then((unsub) => unsub());
             unsubscribeReloadEvent.then((unsub) => unsub());
+            unsubscribeModsEvent.then((unsub) => unsub());
             unsubscribeLanguageEvent.then((unsub) => unsub());
         };
     }, []);
@@ -152,6 +153,8 @@ function Home() {
             </div>
 
             <Console />
+            <InstallPlanDialog />
+            <ErrorDialog />
         </div>
     </div>
   );
@@ -72,7 +72,7 @@ export default function Profiles() {
                             {profile.currently ? "🟢" : "⚫"}
                         </div>
                         <p>{profile.name}</p>
-                        <p>(Mods active: {profile.mods.length})</p>
+                        <p>(Mods active: {profile.mods.filter(mod => mod.enabled).length})</p>
                         <div className="ml-auto flex gap-2">
                             <Dialog>
                                 <DialogTrigger className="h-4" onClick={i => setNewProfile("")}>
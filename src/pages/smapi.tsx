@@ -4,6 +4,13 @@ import {listen} from "@tauri-apps/api/event";
 import SmapiIcon from "../assets/Smapi.png";
 import {MoveRight} from "lucide-react";
 import SmapiProcess from "@models/smapiProcess";
+import SmapiStatus from "@models/smapiStatus.ts";
+import SmapiRelease, {SmapiChannel} from "@models/smapiRelease.ts";
+import {ResolvedProfile} from "@models/profile.ts";
+import {Select, SelectContent, SelectItem, SelectTrigger, SelectValue} from "@components/ui/select.tsx";
+import {Switch} from "@components/ui/switch.tsx";
+import {Checkbox} from "@components/ui/checkbox.tsx";
+import {JunimoError} from "@models/junimoError.ts";
 import {clsx} from "clsx";
 import JunimoDance from "@assets/JunimoDance.gif";
 import {useTranslation} from "react-i18next";
@@ -13,7 +20,7 @@ function InfoText(returnLength: number) {
 
     switch (returnLength) {
         case 0:
-            return t("noSmapi");
+            return t("noVersion");
         case 1:
             return t("firstInstall");
         case 2:
@@ -33,6 +40,11 @@ export default function SmapiPage() {
     const [version, setVersion] = useState<string>("");
     const [oldVersion, setOldVersion] = useState<string>("");
     const [returnLength, setReturnLength] = useState<number>(0);
+    const [status, setStatus] = useState<SmapiStatus | null>(null);
+    const [uninstalled, setUninstalled] = useState<boolean>(false);
+    const [channel, setChannel] = useState<SmapiChannel>("Stable");
+    const [releases, setReleases] = useState<SmapiRelease[]>([]);
+    const [profile, setProfile] = useState<ResolvedProfile | null>(null);
 
     const { t } = useTranslation('smapi');
 
@@ -42,10 +54,88 @@ export default function SmapiPage() {
         }
         const path = await invoke<string>('download_smapi', { link: downloadLink });
         console.log(path);
+        setUninstalled(false);
+        await loadSmapi();
+        await loadStatus();
     }
 
-    async function loadSmapi() {
-        const smapi = await invoke<string[]>('check_smapi_version');
+    async function loadReleases(smapiChannel: SmapiChannel) {
+        const smapiReleases = await invoke<SmapiRelease[]>('get_smapi_releases', {channel: smapiChannel})
+            .catch((error: JunimoError) => {
+                console.error(error.message);
+                return [] as SmapiRelease[];
+            });
+        setReleases(smapiReleases);
+    }
+
+    async function loadProfile() {
+        const profilePath = await invoke<string>('profile_path');
+        setProfile(await invoke<ResolvedProfile>('get_current_profile', {path: profilePath}));
+    }
+
+    async function changeChannel(beta: boolean) {
+        const smapiChannel: SmapiChannel = beta ? "Beta" : "Stable";
+        setChannel(smapiChannel);
+        await loadReleases(smapiChannel);
+        await loadSmapi(smapiChannel);
+    }
+
+    function selectRelease(selected: string) {
+        const release = releases.find(x => x.version === selected);
+        if (release === undefined) return;
+        setVersion(release.version);
+        setDownloadLink(release.download_url);
+        setReturnLength(Math.max(returnLength, 2));
+    }
+
+    async function pin(pinned: boolean) {
+        if (profile === null || version === "") return;
+        await invoke('set_smapi_version', {name: profile.name, version: pinned ? version : null});
+        await loadProfile();
+    }
+
+    async function loadStatus() {
+        const smapiStatus = await invoke<SmapiStatus>('get_smapi_status')
+            .catch((error: JunimoError) => {
+                console.error(error.message);
+                return null;
+            });
+        setStatus(smapiStatus);
+    }
+
+    async function repair() {
+        if (status === null || !status.repairable || installing) {
+            return;
+        }
+        setInstalling(true);
+        try {
+            setStatus(await invoke<SmapiStatus>('repair_smapi'));
+        } finally {
+            setInstalling(false);
+        }
+    }
+
+    async function uninstall() {
+        if (status === null || status.version === null || installing) {
+            return;
+        }
+        setInstalling(true);
+        try {
+            await invoke('uninstall_smapi');
+            setUninstalled(true);
+            await loadSmapi();
+            await loadStatus();
+        } finally {
+            setInstalling(false);
+        }
+    }
+
+    async function loadSmapi(smapiChannel: SmapiChannel = channel) {
+        const smapi = await invoke<string[]>('check_smapi_version', {channel: smapiChannel})
+            .catch((error: JunimoError) => {
+                console.error(error.message);
+                return [] as string[];
+            });
         console.log(smapi);
         setReturnLength(smapi.length);
         if (smapi.length >= 1) {
@@ -61,6 +151,9 @@ export default function SmapiPage() {
 
     useEffect(() => {
         loadSmapi();
+        loadReleases(channel);
+        loadProfile();
+        loadStatus();
 
         let unsubscribeSmapi = listen('smapi_progress', async (event) => {
             const process = event.payload as SmapiProcess;
@@ -87,7 +180,56 @@ export default function SmapiPage() {
                     <p className="text-green-500">{version}</p>
                 </div>
             )}
-            <div className="mt-auto w-full flex justify-end items-end absolute bottom-5 right-5">
+            <div className="w-full flex justify-center items-center gap-6 mt-6">
+                <Select value={version} onValueChange={selectRelease}>
+                    <SelectTrigger className="w-[200px]">
+                        <SelectValue placeholder={t("selectVersion")}/>
+                    </SelectTrigger>
+                    <SelectContent>
+                        {releases.map(release => (
+                            <SelectItem key={release.version} value={release.version}>{release.version}</SelectItem>
+                        ))}
+                    </SelectContent>
+                </Select>
+                <label className="flex items-center gap-2">
+                    <Switch checked={channel === "Beta"} onCheckedChange={changeChannel}/>
+                    {t("betaChannel")}
+                </label>
+                {profile !== null && (
+                    <label className="flex items-center gap-2">
+                        <Checkbox checked={profile.smapi_version !== null && profile.smapi_version === version}
+                                  onCheckedChange={checked => pin(checked === true)}/>
+                        {t("pinVersion", {profile: profile.name})}
+                    </label>
+                )}
+            </div>
+            {(status !== null && status.version !== null && status.missing_files.length > 0) && (
+                <p className="w-full text-center text-red-500 px-16 mt-4">
+                    {t("missingFiles", {files: status.missing_files.join(", ")})}
+                </p>
+            )}
+            {uninstalled && (
+                <p className="w-full text-center text-input px-16 mt-4">{t("uninstalled")}</p>
+            )}
+            <div className="mt-auto w-full flex justify-end items-end gap-2 absolute bottom-5 right-5">
+                {(status !== null && status.version !== null) && (
+                    <>
+                        <button onClick={uninstall}
+                                className={clsx(
+                                    "p-2 px-6 transition duration-150 text-foreground hover:bg-muted rounded-lg",
+                                    installing ? "opacity-50 cursor-not-allowed" : ""
+                                )}>
+                            {t("uninstallButton")}
+                        </button>
+                        <button onClick={repair}
+                                className={clsx(
+                                    "p-2 px-6 transition duration-150 text-foreground hover:bg-muted rounded-lg",
+                                    !status.repairable || installing ? "opacity-50 cursor-not-allowed" : ""
+                                )}>
+                            {t("repairButton")}
+                        </button>
+                    </>
+                )}
                 <button onClick={download}
                         className={clsx(
                             "p-2 px-6 transition duration-150 bg-green-500 hover:bg-green-600 rounded-lg text-white relative",